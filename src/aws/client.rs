@@ -45,6 +45,6 @@ pub fn best_move_aws(aws_function_name: &str, payload: &Event) -> io::Result<Out
                 ))
             }
         }
-        Err(err) => Err(io::Error::new(io::ErrorKind::Other, err)),
+        Err(err) => Err(io::Error::other(err)),
     }
 }
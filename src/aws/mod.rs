@@ -1,3 +1,4 @@
+use crate::position::Komi;
 use serde::Deserialize;
 use serde::Serialize;
 use std::time::Duration;
@@ -17,6 +18,8 @@ pub enum TimeControl {
 pub struct Event {
     pub size: usize,
     pub tps: Option<String>,
+    #[serde(default)]
+    pub komi: Komi,
     pub moves: Vec<String>,
     pub time_control: TimeControl,
     pub dirichlet_noise: Option<f32>,
@@ -1,37 +1,25 @@
 use crate::aws::{Event, Output, TimeControl};
-use crate::position::Position;
-use crate::search;
-use crate::search::MctsSetting;
-use board_game_traits::{GameResult, Position as EvalPosition};
+use crate::position::AnyPosition;
+use crate::search::{AnyMctsSetting, AnyTree};
+use board_game_traits::GameResult;
 use lambda_runtime::Context;
-use pgn_traits::PgnPosition;
 use std::time::Duration;
 
 type Error = Box<dyn std::error::Error + Sync + Send>;
 
 /// AWS serverside handler
-pub async fn handle_aws_event(e: Event, c: Context) -> Result<Output, Error> {
-    match e.size {
-        4 => handle_aws_event_generic::<4>(e, c),
-        5 => handle_aws_event_generic::<5>(e, c),
-        6 => handle_aws_event_generic::<6>(e, c),
-        s => panic!("Unsupported board size {}", s),
-    }
-}
-
-pub fn handle_aws_event_generic<const S: usize>(e: Event, _c: Context) -> Result<Output, Error> {
+pub async fn handle_aws_event(e: Event, _c: Context) -> Result<Output, Error> {
     let mut position = match e.tps {
-        Some(tps) => <Position<S>>::from_fen(&tps)?,
-        None => <Position<S>>::default(),
+        Some(tps) => AnyPosition::from_fen_with_komi(e.size, &tps, e.komi)?,
+        None => AnyPosition::start_position_with_komi(e.size, e.komi)
+            .ok_or_else(|| format!("Unsupported board size {}", e.size))?,
     };
+    let size = e.size;
     for move_string in e.moves {
         let mv = position.move_from_san(&move_string)?;
-        let mut legal_moves = vec![];
-        position.generate_moves(&mut legal_moves);
-        if !legal_moves.contains(&mv) {
-            return Err(format!("Illegal {}s move {}", S, move_string).into());
-        }
-        position.do_move(mv);
+        position
+            .try_do_move(mv)
+            .map_err(|err| format!("Illegal {}s move {}: {}", size, move_string, err))?;
     }
 
     match position.game_result() {
@@ -50,16 +38,13 @@ pub fn handle_aws_event_generic<const S: usize>(e: Event, _c: Context) -> Result
         None => (),
     }
 
-    let settings = if let Some(dirichlet) = e.dirichlet_noise {
-        MctsSetting::default()
-            .add_dirichlet(dirichlet)
-            .add_rollout_depth(e.rollout_depth)
-            .add_rollout_temperature(e.rollout_temperature)
-    } else {
-        MctsSetting::default()
-            .add_rollout_depth(e.rollout_depth)
-            .add_rollout_temperature(e.rollout_temperature)
-    };
+    let mut settings = AnyMctsSetting::default_for_size(position.size())
+        .unwrap()
+        .add_rollout_depth(e.rollout_depth)
+        .add_rollout_temperature(e.rollout_temperature);
+    if let Some(dirichlet) = e.dirichlet_noise {
+        settings = settings.add_dirichlet(dirichlet);
+    }
 
     match e.time_control {
         TimeControl::Time(time_left, increment) => {
@@ -69,19 +54,19 @@ pub fn handle_aws_event_generic<const S: usize>(e: Event, _c: Context) -> Result
                 Duration::min(time_left / 40 + increment / 3, Duration::from_secs(40))
             };
 
-            let (best_move, score) = search::play_move_time(position, max_time, settings);
+            let (best_move, score) = position.play_move_time(max_time, settings);
             Ok(Output {
-                pv: vec![best_move.to_string::<S>()],
+                pv: vec![best_move.to_string()],
                 score,
             })
         }
         TimeControl::FixedNodes(nodes) => {
-            let mut tree = search::MonteCarloTree::with_settings(position, settings);
+            let mut tree = AnyTree::with_settings(position, settings);
             for _ in 0..nodes {
                 tree.select();
             }
             let score = 1.0 - tree.best_move().1;
-            let pv = tree.pv().map(|mv| mv.to_string::<S>()).collect();
+            let pv = tree.pv().iter().map(|mv| mv.to_string()).collect();
             Ok(Output { pv, score })
         }
     }
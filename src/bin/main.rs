@@ -1,22 +1,21 @@
-#[cfg(feature = "constant-tuning")]
 use std::collections::HashSet;
 use std::io::{Read, Write};
-use std::{io, time};
+use std::{fs, io, time};
 
+use board_game_traits::Position as PositionTrait;
 use board_game_traits::{Color, GameResult};
-use board_game_traits::{EvalPosition, Position as PositionTrait};
-use pgn_traits::PgnPosition;
 #[cfg(feature = "constant-tuning")]
 use rayon::prelude::*;
 
-use tiltak::evaluation::parameters;
 use tiltak::minmax;
+use tiltak::opening_book::OpeningBook;
 use tiltak::position::Move;
 #[cfg(feature = "constant-tuning")]
 use tiltak::position::Role;
-use tiltak::position::{Position, Stack};
-use tiltak::ptn::{Game, PtnMove};
-use tiltak::search::MctsSetting;
+use tiltak::position::{AnyMove, AnyPosition, GameEndReason, Position, Stack};
+use tiltak::ptn::{AnyGame, PtnMove};
+use tiltak::search::{AnyMctsSetting, AnyTree};
+use tiltak::tinue::TinueResult;
 use tiltak::{position, search};
 
 #[cfg(test)]
@@ -26,11 +25,16 @@ pub mod playtak;
 pub mod tei;
 
 fn main() {
-    println!("play: Play against the engine through the command line");
+    println!("play <size>: Play against the engine through the command line");
     println!("aimatch: Watch the engine play against a very simple minmax implementation");
     println!("analyze <size>: Analyze a given position, provided from a PTN or a simple move list");
     println!("tps <size>: Analyze a given position, provided from a tps string");
     println!("game <size>: Analyze a whole game, provided from a PTN or a simple move list");
+    println!("perft <size> <depth>: Count move generation nodes for a given position, provided from a tps string");
+    println!("tinue <size> <plies>: Search for a forced road win in a given position, provided from a tps string");
+    println!("book <size> <plies> <ptn file> <book file>: Build an opening book from the first moves of a PTN game collection");
+    println!("analysis_book <size> <plies> <nodes> <book file>: Build an opening book by searching the most promising openings");
+    println!("selfplay [book file]: Watch the engine play against itself, optionally starting with book moves. Plays on the book's size, or 5s without a book");
     loop {
         let mut input = String::new();
         io::stdin().read_line(&mut input).unwrap();
@@ -39,118 +43,120 @@ fn main() {
             continue;
         }
         match words[0] {
-            "play" => {
-                let position = Position::default();
-                play_human(position);
-            }
+            "play" => match words.get(1).map_or(Some(5), |word| parse_size(word)) {
+                Some(size) => play_human(AnyPosition::start_position(size).unwrap()),
+                None => println!("Usage: play <size>"),
+            },
             "aimatch" => {
                 for i in 1..10 {
                     mcts_vs_minmax(3, 50000 * i);
                 }
             }
-            "analyze" => match words.get(1) {
-                Some(&"4") => analyze_position_from_ptn::<4>(),
-                Some(&"5") => analyze_position_from_ptn::<5>(),
-                Some(&"6") => analyze_position_from_ptn::<6>(),
-                Some(&"7") => analyze_position_from_ptn::<7>(),
-                Some(&"8") => analyze_position_from_ptn::<8>(),
-                _ => analyze_position_from_ptn::<5>(),
+            "analyze" => match words.get(1).map_or(Some(5), |word| parse_size(word)) {
+                Some(size) => analyze_position_from_ptn(size),
+                None => println!("Usage: analyze <size>"),
             },
-            "tps" => match words.get(1) {
-                Some(&"4") => analyze_position_from_tps::<4>(),
-                Some(&"5") => analyze_position_from_tps::<5>(),
-                Some(&"6") => analyze_position_from_tps::<6>(),
-                Some(&"7") => analyze_position_from_tps::<7>(),
-                Some(&"8") => analyze_position_from_tps::<8>(),
-                _ => analyze_position_from_tps::<5>(),
+            "tps" => match words.get(1).map_or(Some(5), |word| parse_size(word)) {
+                Some(size) => analyze_position_from_tps(size),
+                None => println!("Usage: tps <size>"),
             },
             #[cfg(feature = "constant-tuning")]
-            "openings" => {
-                let depth = 4;
-                let mut positions = HashSet::new();
-                let openings =
-                    generate_openings::<6>(Position::start_position(), &mut positions, depth);
-                println!("{} openings generated, evaluating...", openings.len());
-
-                let mut evaled_openings: Vec<_> = openings
-                    .into_par_iter()
-                    .filter(|opening| opening.len() == depth as usize)
-                    .map(|opening| {
-                        let mut position = <Position<6>>::start_position();
-                        for mv in opening.iter() {
-                            position.do_move(mv.clone());
-                        }
-                        (opening, search::mcts(position, 100_000))
-                    })
-                    .collect();
-
-                evaled_openings.sort_by(|(_, (_, score1)), (_, (_, score2))| {
-                    score1.partial_cmp(score2).unwrap()
-                });
-                for (p, (mv, s)) in evaled_openings {
-                    let mut position = <Position<6>>::start_position();
-                    for mv in p {
-                        print!("{} ", position.move_to_san(&mv));
-                        position.do_move(mv);
-                    }
-                    print!(": ");
-                    println!("{}, {}", position.move_to_san(&mv), s);
+            "openings" => match words.get(1).map_or(Some(6), |word| parse_size(word)) {
+                Some(size) => {
+                    evaluate_openings(size, 4);
+                    return;
                 }
-                return;
-            }
-            "analyze_openings" => analyze_openings::<6>(6_000_000),
-            "game" => {
-                println!("Enter move list or a full PTN, then press enter followed by CTRL+D");
-                let mut input = String::new();
-
-                match words.get(1) {
-                    Some(&"6") => {
-                        io::stdin().read_to_string(&mut input).unwrap();
-                        let games = tiltak::ptn::ptn_parser::parse_ptn(&input).unwrap();
-                        if games.is_empty() {
-                            continue;
-                        }
-                        println!("Analyzing 1 game: ");
-
-                        analyze_game::<6>(games[0].clone());
-                    }
-                    None | Some(&"5") => {
-                        io::stdin().read_to_string(&mut input).unwrap();
-                        let games = tiltak::ptn::ptn_parser::parse_ptn(&input).unwrap();
-                        if games.is_empty() {
-                            println!("Couldn't parse any games");
-                            continue;
-                        }
-                        println!("Analyzing 1 game: ");
-
-                        analyze_game::<5>(games[0].clone());
-                    }
-                    Some(s) => println!("Game analysis at size {} not available", s),
+                None => println!("Usage: openings <size>"),
+            },
+            "analyze_openings" => match words.get(1).map_or(Some(6), |word| parse_size(word)) {
+                Some(size) => analyze_openings(size, 6_000_000),
+                None => println!("Usage: analyze_openings <size>"),
+            },
+            "game" => match words.get(1).map_or(Some(5), |word| parse_size(word)) {
+                Some(size) => analyze_game_from_ptn(size),
+                None => println!("Usage: game <size>"),
+            },
+            "perft" => match (
+                words.get(1).and_then(|s| s.parse().ok()),
+                words.get(2).and_then(|s| s.parse().ok()),
+            ) {
+                (Some(size), Some(depth)) if position::SUPPORTED_SIZES.contains(&size) => {
+                    perft_from_tps(size, depth)
                 }
-            }
+                _ => println!("Usage: perft <size> <depth>"),
+            },
+            "tinue" => match (
+                words.get(1).and_then(|s| s.parse().ok()),
+                words.get(2).and_then(|s| s.parse().ok()),
+            ) {
+                (Some(size), Some(plies)) if position::SUPPORTED_SIZES.contains(&size) => {
+                    tinue_from_tps(size, plies)
+                }
+                _ => println!("Usage: tinue <size> <plies>"),
+            },
             "mem_usage" => mem_usage(),
             "bench" => bench(),
-            "selfplay" => mcts_selfplay(time::Duration::from_secs(10)),
+            "book" => match (
+                words.get(1).and_then(|word| parse_size(word)),
+                words.get(2).and_then(|s| s.parse().ok()),
+                words.get(3),
+                words.get(4),
+            ) {
+                (Some(size), Some(plies), Some(ptn_file), Some(book_file)) => {
+                    book_from_ptn(size, plies, ptn_file, book_file)
+                }
+                _ => println!("Usage: book <size> <plies> <ptn file> <book file>"),
+            },
+            "analysis_book" => match (
+                words.get(1).and_then(|word| parse_size(word)),
+                words.get(2).and_then(|s| s.parse().ok()),
+                words.get(3).and_then(|s| s.parse().ok()),
+                words.get(4),
+            ) {
+                (Some(size), Some(plies), Some(nodes), Some(book_file)) => {
+                    book_from_analysis(size, plies, nodes, book_file)
+                }
+                _ => println!("Usage: analysis_book <size> <plies> <nodes> <book file>"),
+            },
+            "selfplay" => {
+                let opening_book = match words.get(1).map(|path| read_book(path)) {
+                    Some(Ok(book)) => Some(book),
+                    Some(Err(err)) => {
+                        println!("Couldn't read book: {}", err);
+                        continue;
+                    }
+                    None => None,
+                };
+                let size = opening_book.as_ref().map_or(5, OpeningBook::size);
+                mcts_selfplay(size, time::Duration::from_secs(10), opening_book.as_ref())
+            }
             s => println!("Unknown option \"{}\"", s),
         }
     }
 }
 
-fn analyze_openings<const S: usize>(nodes: u64) {
+/// Parse a board size, if it is supported
+fn parse_size(word: &str) -> Option<usize> {
+    word.parse()
+        .ok()
+        .filter(|size| position::SUPPORTED_SIZES.contains(size))
+}
+
+fn analyze_openings(size: usize, nodes: u64) {
     let mut input = String::new();
     io::stdin().read_to_string(&mut input).unwrap();
     for line in input.lines() {
-        let mut position = <Position<6>>::start_position();
+        let mut position = AnyPosition::start_position(size).unwrap();
         for word in line.split_whitespace() {
             let mv = position.move_from_san(word).unwrap();
             position.do_move(mv);
         }
         let start_time = time::Instant::now();
-        let mut tree = search::MonteCarloTree::new(position.clone());
+        let mut tree = AnyTree::new(position.clone());
         for _ in 0..nodes {
             tree.select();
         }
-        let pv: Vec<Move> = tree.pv().take(4).collect();
+        let pv: Vec<AnyMove> = tree.pv().into_iter().take(4).collect();
         print!(
             "{}, {:.3}, {:.1}s, ",
             line.trim(),
@@ -165,30 +171,59 @@ fn analyze_openings<const S: usize>(nodes: u64) {
     }
 }
 
+/// Search every opening of `depth` flat placements, skipping symmetrical duplicates, and print them sorted by score
+#[cfg(feature = "constant-tuning")]
+fn evaluate_openings(size: usize, depth: u8) {
+    let mut positions = HashSet::new();
+    let openings = generate_openings(
+        &mut AnyPosition::start_position(size).unwrap(),
+        &mut positions,
+        depth,
+    );
+    println!("{} openings generated, evaluating...", openings.len());
+
+    let mut evaled_openings: Vec<_> = openings
+        .into_par_iter()
+        .filter(|opening| opening.len() == depth as usize)
+        .map(|opening| {
+            let mut position = AnyPosition::start_position(size).unwrap();
+            for mv in opening.iter() {
+                position.do_move(mv.clone());
+            }
+            let result = position.mcts(100_000);
+            (opening, result)
+        })
+        .collect();
+
+    evaled_openings
+        .sort_by(|(_, (_, score1)), (_, (_, score2))| score1.partial_cmp(score2).unwrap());
+    for (p, (mv, s)) in evaled_openings {
+        let mut position = AnyPosition::start_position(size).unwrap();
+        for mv in p {
+            print!("{} ", position.move_to_san(&mv));
+            position.do_move(mv);
+        }
+        print!(": ");
+        println!("{}, {}", position.move_to_san(&mv), s);
+    }
+}
+
 #[cfg(feature = "constant-tuning")]
-fn generate_openings<const S: usize>(
-    mut position: Position<S>,
-    positions: &mut HashSet<Position<S>>,
+fn generate_openings(
+    position: &mut AnyPosition,
+    positions: &mut HashSet<u64>,
     depth: u8,
-) -> Vec<Vec<Move>> {
+) -> Vec<Vec<AnyMove>> {
     let mut moves = vec![];
     position.generate_moves(&mut moves);
-    moves = moves
-        .into_iter()
-        .filter(|mv| matches!(mv, Move::Place(Role::Flat, _)))
-        .collect();
+    moves.retain(|mv| matches!(mv.mv(), Move::Place(Role::Flat, _)));
     moves
         .into_iter()
         .flat_map(|mv| {
             let reverse_move = position.do_move(mv.clone());
-            let mut child_lines = if position
-                .symmetries()
-                .iter()
-                .all(|board_symmetry| !positions.contains(board_symmetry))
-            {
-                positions.insert(position.clone());
+            let mut child_lines = if positions.insert(position.canonical_hash()) {
                 if depth > 1 {
-                    generate_openings(position.clone(), positions, depth - 1)
+                    generate_openings(position, positions, depth - 1)
                 } else {
                     vec![vec![]]
                 }
@@ -204,8 +239,84 @@ fn generate_openings<const S: usize>(
         .collect()
 }
 
-fn mcts_selfplay(max_time: time::Duration) {
-    let mut position = <Position<5>>::default();
+fn read_book(path: &str) -> io::Result<OpeningBook> {
+    OpeningBook::read(io::BufReader::new(fs::File::open(path)?))
+}
+
+fn write_book(book: &OpeningBook, path: &str) {
+    match fs::File::create(path).and_then(|file| book.write(io::BufWriter::new(file))) {
+        Ok(()) => println!("Wrote {} positions to {}", book.len(), path),
+        Err(err) => println!("Couldn't write book: {}", err),
+    }
+}
+
+fn book_from_ptn(size: usize, max_plies: usize, ptn_file: &str, book_file: &str) {
+    let file = match fs::File::open(ptn_file) {
+        Ok(file) => io::BufReader::new(file),
+        Err(err) => {
+            println!("Couldn't read {}: {}", ptn_file, err);
+            return;
+        }
+    };
+    let mut book = OpeningBook::new(size);
+    let mut num_games = 0;
+    for game in AnyGame::read_ptn(size, file).unwrap() {
+        match game {
+            Ok(game) => {
+                book.add_any_game(&game, max_plies);
+                num_games += 1;
+            }
+            Err(err) => println!("Skipping game in {}: {}", ptn_file, err),
+        }
+    }
+    println!("Read {} games", num_games);
+    write_book(&book, book_file);
+}
+
+fn book_from_analysis(size: usize, max_plies: usize, nodes: u64, book_file: &str) {
+    let mut book = OpeningBook::new(size);
+    add_analysis_to_book(
+        &mut book,
+        &mut AnyPosition::start_position(size).unwrap(),
+        max_plies,
+        nodes,
+        &mut HashSet::new(),
+    );
+    write_book(&book, book_file);
+}
+
+/// Search every position reached by moves that got at least 10% of the visits, up to `plies_left` plies deep
+fn add_analysis_to_book(
+    book: &mut OpeningBook,
+    position: &mut AnyPosition,
+    plies_left: usize,
+    nodes: u64,
+    visited: &mut HashSet<u64>,
+) {
+    if plies_left == 0
+        || position.game_result().is_some()
+        || !visited.insert(position.canonical_hash())
+    {
+        return;
+    }
+    let mut tree = AnyTree::new(position.clone());
+    for _ in 0..nodes.max(2) {
+        tree.select();
+    }
+    let min_visits = tree.visits() / 10;
+    book.add_any_analysis(position, &tree, min_visits);
+    for (mv, visits, _) in tree.move_visits_and_scores() {
+        if visits >= min_visits {
+            let reverse_move = position.do_move(mv);
+            add_analysis_to_book(book, position, plies_left - 1, nodes, visited);
+            position.reverse_move(reverse_move);
+        }
+    }
+}
+
+fn mcts_selfplay(size: usize, max_time: time::Duration, opening_book: Option<&OpeningBook>) {
+    let mut position = AnyPosition::start_position(size).unwrap();
+    let settings = AnyMctsSetting::default_for_size(size).unwrap();
     let mut moves = vec![];
 
     let mut white_elapsed = time::Duration::default();
@@ -213,8 +324,12 @@ fn mcts_selfplay(max_time: time::Duration) {
 
     while position.game_result().is_none() {
         let start_time = time::Instant::now();
-        let (best_move, score) =
-            search::play_move_time::<5>(position.clone(), max_time, MctsSetting::default());
+        let (best_move, score) = match opening_book
+            .and_then(|book| book.choose_any_move(&position, &mut rand::thread_rng()))
+        {
+            Some(book_move) => book_move,
+            None => position.play_move_time(max_time, settings.clone()),
+        };
 
         match position.side_to_move() {
             Color::White => white_elapsed += start_time.elapsed(),
@@ -225,7 +340,7 @@ fn mcts_selfplay(max_time: time::Duration) {
         moves.push(best_move.clone());
         println!(
             "{:6}: {:.3}, {:.1}s",
-            best_move.to_string::<5>(),
+            best_move.to_string(),
             score,
             start_time.elapsed().as_secs_f32()
         );
@@ -240,15 +355,15 @@ fn mcts_selfplay(max_time: time::Duration) {
 
     print!("\n[");
     for mv in moves.iter() {
-        print!("\"{:?}\", ", mv);
+        print!("\"{}\", ", mv);
     }
     println!("]");
 
     for (ply, mv) in moves.iter().enumerate() {
         if ply % 2 == 0 {
-            print!("{}. {:?} ", ply / 2 + 1, mv);
+            print!("{}. {} ", ply / 2 + 1, mv);
         } else {
-            println!("{:?}", mv);
+            println!("{}", mv);
         }
     }
     println!();
@@ -301,74 +416,113 @@ fn mcts_vs_minmax(minmax_depth: u16, mcts_nodes: u64) {
     println!("\n{:?}\nResult: {:?}", position, position.game_result());
 }
 
-fn analyze_position_from_ptn<const S: usize>() {
+fn analyze_position_from_ptn(size: usize) {
     println!("Enter move list or a full PTN, then press enter followed by CTRL+D");
 
     let mut input = String::new();
     io::stdin().read_to_string(&mut input).unwrap();
-    let games: Vec<Game<Position<S>>> = tiltak::ptn::ptn_parser::parse_ptn(&input).unwrap();
+    let games = AnyGame::parse_ptn(size, &input).unwrap();
     if games.is_empty() {
         println!("Couldn't parse any games");
         return;
     }
 
-    let mut position: Position<S> = games[0].start_position.clone();
+    let mut position = games[0].start_position.clone();
 
     for PtnMove { mv, .. } in games[0].moves.clone() {
-        position.do_move(mv);
+        position.do_move(AnyMove::new(size, mv).unwrap());
     }
     analyze_position(&position)
 }
 
-fn analyze_position_from_tps<const S: usize>() {
+fn analyze_position_from_tps(size: usize) {
     println!("Enter TPS");
     let mut input = String::new();
     io::stdin().read_line(&mut input).unwrap();
-    let position = <Position<S>>::from_fen(&input).unwrap();
+    let position = AnyPosition::from_fen(size, input.trim()).unwrap();
     analyze_position(&position)
 }
 
-fn analyze_position<const S: usize>(position: &Position<S>) {
-    println!("TPS {}", position.to_fen());
-    println!("{:?}", position);
-
-    assert_eq!(position.game_result(), None, "Cannot analyze finished game");
-
-    let mut simple_moves = vec![];
-    let mut moves = vec![];
+fn perft_from_tps(size: usize, depth: u16) {
+    println!("Enter TPS, or an empty line for the start position");
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).unwrap();
+    let mut position = if input.trim().is_empty() {
+        AnyPosition::start_position(size).unwrap()
+    } else {
+        match AnyPosition::from_fen(size, input.trim()) {
+            Ok(position) => position,
+            Err(err) => {
+                println!("Invalid TPS: {}", err);
+                return;
+            }
+        }
+    };
 
-    position.generate_moves_with_probabilities(
-        &position.group_data(),
-        &mut simple_moves,
-        &mut moves,
-        &mut vec![],
-        search::MctsSetting::<S>::default().policy_baseline(),
+    let start_time = time::Instant::now();
+    let mut total_nodes = 0;
+    for (mv, nodes) in position.divide(depth) {
+        println!("{}: {}", mv, nodes);
+        total_nodes += nodes;
+    }
+    if depth == 0 || position.game_result().is_some() {
+        total_nodes = 1;
+    }
+    let elapsed = start_time.elapsed();
+    println!(
+        "perft({}) = {} nodes in {:.2}s, {:.0} nodes/s",
+        depth,
+        total_nodes,
+        elapsed.as_secs_f64(),
+        total_nodes as f64 / elapsed.as_secs_f64()
     );
-    moves.sort_by(|(_mv, score1), (_, score2)| score1.partial_cmp(score2).unwrap().reverse());
+}
 
-    let group_data = position.group_data();
-    let mut feature_sets = vec![vec![0.0; parameters::num_policy_features::<S>()]; moves.len()];
-    let mut policy_feature_sets: Vec<_> = feature_sets
-        .iter_mut()
-        .map(|feature_set| parameters::PolicyFeatures::new::<S>(feature_set))
-        .collect();
+fn tinue_from_tps(size: usize, max_plies: u16) {
+    println!("Enter TPS");
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).unwrap();
+    let position = match AnyPosition::from_fen(size, input.trim()) {
+        Ok(position) => position,
+        Err(err) => {
+            println!("Invalid TPS: {}", err);
+            return;
+        }
+    };
+
+    let start_time = time::Instant::now();
+    match position.find_tinue(max_plies, 100_000_000) {
+        TinueResult::Win(line) => println!(
+            "Tinue in {} moves: {}",
+            line.len().div_ceil(2),
+            line.iter()
+                .map(|mv| mv.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        ),
+        TinueResult::NoWin => println!("No tinue within {} plies", max_plies),
+        TinueResult::Unknown => println!("Search ran out of nodes"),
+    }
+    println!("Searched for {:.2}s", start_time.elapsed().as_secs_f64());
+}
 
-    let simple_moves: Vec<Move> = moves.iter().map(|(mv, _)| mv.clone()).collect();
+fn analyze_position(position: &AnyPosition) {
+    println!("TPS {}", position.to_fen());
+    println!("{:?}", position);
 
-    position.features_for_moves(&mut policy_feature_sets, &simple_moves, &group_data);
+    assert_eq!(position.game_result(), None, "Cannot analyze finished game");
 
     println!("Top 10 heuristic moves:");
-    for ((mv, score), features) in moves.iter().zip(feature_sets).take(10) {
-        println!("{}: {:.3}%", mv.to_string::<S>(), score * 100.0);
+    for (mv, score, features) in position.move_heuristics().into_iter().take(10) {
+        println!("{}: {:.3}%", mv, score * 100.0);
         for feature in features {
             print!("{:.1}, ", feature);
         }
         println!();
     }
-    let settings: MctsSetting<S> = search::MctsSetting::default().exclude_moves(vec![]);
     let start_time = time::Instant::now();
 
-    let mut tree = search::MonteCarloTree::with_settings(position.clone(), settings);
+    let mut tree = AnyTree::new(position.clone());
     for i in 1.. {
         tree.select();
         if i % 100_000 == 0 {
@@ -381,65 +535,80 @@ fn analyze_position<const S: usize>(position: &Position<S>) {
                 start_time.elapsed().as_secs_f64()
             );
             tree.print_info();
-            println!("Best move: {:?}", tree.best_move())
+            let (best_move, score) = tree.best_move();
+            println!("Best move: {} {:.3}", best_move, score)
         }
     }
 }
 
-fn analyze_game<const S: usize>(game: Game<Position<S>>) {
+fn analyze_game_from_ptn(size: usize) {
+    println!("Enter move list or a full PTN, then press enter followed by CTRL+D");
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input).unwrap();
+    let games = AnyGame::parse_ptn(size, &input).unwrap();
+    if games.is_empty() {
+        println!("Couldn't parse any games");
+        return;
+    }
+    println!("Analyzing 1 game: ");
+
+    analyze_game(games[0].clone());
+}
+
+/// Search the position before every move, and print the game with the engine's preferred moves and evaluations in the move comments
+fn analyze_game(mut game: AnyGame) {
+    let size = game.size();
     let mut position = game.start_position.clone();
-    let mut ply_number = 2;
-    for PtnMove { mv, .. } in game.moves {
-        position.do_move(mv.clone());
-        if let Some(game_result) = position.game_result() {
-            let result_string = match game_result {
-                GameResult::WhiteWin => "1-0",
-                GameResult::BlackWin => "0-1",
-                GameResult::Draw => "1/2-1/2",
-            };
-            if ply_number % 2 == 0 {
-                print!(
-                    "{}. {} {}",
-                    ply_number / 2,
-                    mv.to_string::<S>(),
-                    result_string
-                );
-                io::stdout().flush().unwrap();
-            } else {
-                println!(
-                    "{}... {} {}",
-                    ply_number / 2,
-                    mv.to_string::<S>(),
-                    result_string
-                );
+    for i in 0..=game.moves.len() {
+        if let Some(outcome) = position.game_outcome() {
+            let mut description = outcome.to_string();
+            if let GameEndReason::Road(squares) = &outcome.reason {
+                let square_strings: Vec<String> = squares
+                    .iter()
+                    .map(|square| position.square_to_string(*square))
+                    .collect();
+                description.push_str(&format!(": {}", square_strings.join(" ")));
             }
-        } else {
-            let (best_move, score) = search::mcts::<S>(position.clone(), 1_000_000);
-            if ply_number % 2 == 0 {
-                print!(
-                    "{}. {} {{{:.2}%, best reply {}}} ",
-                    ply_number / 2,
-                    position.move_to_san(&mv),
-                    (1.0 - score) * 100.0,
-                    best_move.to_string::<S>()
-                );
-                io::stdout().flush().unwrap();
-            } else {
-                println!(
-                    "{}... {} {{{:.2}%, best reply {}}}",
-                    ply_number / 2,
-                    position.move_to_san(&mv),
-                    (1.0 - score) * 100.0,
-                    best_move.to_string::<S>()
-                );
+            if i > 0 {
+                let last_move = &mut game.moves[i - 1];
+                if !last_move.comment.is_empty() {
+                    last_move.comment.push(' ');
+                }
+                last_move.comment.push_str(&description);
+            }
+            game.game_result = Some(outcome.result);
+            break;
+        }
+        let mut tree = AnyTree::new(position.clone());
+        for _ in 0..1_000_000 {
+            tree.select();
+        }
+        let (best_move, score) = tree.best_move();
+        // The eval of a move is from the perspective of the player who made it
+        let is_final_position = i == game.moves.len();
+        if i > 0 {
+            let last_move = &mut game.moves[i - 1];
+            last_move.comment_tags.eval = Some(1.0 - score);
+            if is_final_position {
+                if !last_move.comment.is_empty() {
+                    last_move.comment.push(' ');
+                }
+                last_move
+                    .comment
+                    .push_str(&format!("Best reply: {}", best_move));
             }
         }
-        ply_number += 1;
+        if let Some(ptn_move) = game.moves.get_mut(i) {
+            ptn_move.comment_tags.best_move = Some(best_move.into_inner());
+            position.do_move(AnyMove::new(size, ptn_move.mv.clone()).unwrap());
+        }
     }
+    println!();
+    game.game_to_ptn(&mut io::stdout()).unwrap();
 }
 
 /// Play a game against the engine through stdin
-fn play_human(mut position: Position<5>) {
+fn play_human(mut position: AnyPosition) {
     match position.game_result() {
         None => {
             use board_game_traits::Color::*;
@@ -450,8 +619,6 @@ fn play_human(mut position: Position<5>) {
 
                 let reader = io::stdin();
                 let mut input_str = "".to_string();
-                let mut legal_moves = vec![];
-                position.generate_moves(&mut legal_moves);
                 // Loop until user enters a valid move
                 loop {
                     input_str.clear();
@@ -460,25 +627,23 @@ fn play_human(mut position: Position<5>) {
                         .expect("Failed to read line");
 
                     match position.move_from_san(input_str.trim()) {
-                        Ok(val) => {
-                            if legal_moves.contains(&val) {
-                                break;
+                        Ok(val) => match position.try_do_move(val) {
+                            Ok(_) => break,
+                            Err(error) => {
+                                println!("Move {} is illegal: {}", input_str.trim(), error);
+                                println!("Try again: ");
                             }
-                            println!("Move {:?} is illegal! Legal moves: {:?}", val, legal_moves);
-                            println!("Try again: ");
-                        }
+                        },
 
                         Err(error) => {
                             println!("{}, try again.", error);
                         }
                     }
                 }
-                let c_move = position.move_from_san(input_str.trim()).unwrap();
-                position.do_move(c_move);
             } else {
-                let (best_move, score) = search::mcts::<5>(position.clone(), 1_000_000);
+                let (best_move, score) = position.mcts(1_000_000);
 
-                println!("Computer played {:?} with score {}", best_move, score);
+                println!("Computer played {} with score {}", best_move, score);
                 position.do_move(best_move);
             }
             play_human(position);
@@ -494,22 +659,22 @@ fn bench() {
     const NODES: u64 = 1_000_000;
     let start_time = time::Instant::now();
     {
-        let position = <Position<5>>::default();
+        let position = AnyPosition::start_position(5).unwrap();
 
-        let (_move, score) = search::mcts::<5>(position, NODES);
+        let (_move, score) = position.mcts(NODES);
         print!("{:.3}, ", score);
     }
 
     {
-        let mut position = Position::default();
+        let mut position = AnyPosition::start_position(5).unwrap();
 
         do_moves_and_check_validity(&mut position, &["d3", "c3", "c4", "1d3<", "1c4+", "Sc4"]);
 
-        let (_move, score) = search::mcts::<5>(position, NODES);
+        let (_move, score) = position.mcts(NODES);
         print!("{:.3}, ", score);
     }
     {
-        let mut position = Position::default();
+        let mut position = AnyPosition::start_position(5).unwrap();
 
         do_moves_and_check_validity(
             &mut position,
@@ -519,7 +684,7 @@ fn bench() {
             ],
         );
 
-        let (_move, score) = search::mcts::<5>(position, NODES);
+        let (_move, score) = position.mcts(NODES);
         println!("{:.3}", score);
     }
     let time_taken = start_time.elapsed();
@@ -552,7 +717,7 @@ fn mem_usage() {
     );
 }
 
-fn do_moves_and_check_validity(position: &mut Position<5>, move_strings: &[&str]) {
+fn do_moves_and_check_validity(position: &mut AnyPosition, move_strings: &[&str]) {
     let mut moves = vec![];
     for mv_san in move_strings.iter() {
         let mv = position.move_from_san(mv_san).unwrap();
@@ -2,8 +2,9 @@ use std::convert::Infallible;
 use std::io::{BufRead, Result, Write};
 use std::net::TcpStream;
 use std::str::FromStr;
+use std::sync::Arc;
 use std::time::Duration;
-use std::{io, net, thread};
+use std::{fs, io, net, thread};
 
 use board_game_traits::{Color, GameResult, Position as PositionTrait};
 use bufstream::BufStream;
@@ -18,9 +19,10 @@ use rand::seq::SliceRandom;
 use rand::Rng;
 #[cfg(feature = "aws-lambda-client")]
 use tiltak::aws;
-use tiltak::position::Position;
-use tiltak::position::{squares_iterator, Move, Role, Square};
-use tiltak::ptn::{Game, PtnMove};
+use tiltak::opening_book::OpeningBook;
+use tiltak::position::{squares_iterator, with_size, Move, Role, Square};
+use tiltak::position::{starting_capstones, starting_stones, Komi, Position};
+use tiltak::ptn::{Annotations, CommentTags, Game, PtnMove};
 use tiltak::search;
 use tiltak::search::MctsSetting;
 
@@ -32,8 +34,11 @@ pub struct PlaytakSettings {
     dirichlet_noise: Option<f32>,
     rollout_depth: u16,
     rollout_temperature: f64,
+    threads: usize,
+    ponder: bool,
     seek_game_time: Duration,
     seek_increment: Duration,
+    seek_komi: Komi,
 }
 
 impl PlaytakSettings {
@@ -43,10 +48,12 @@ impl PlaytakSettings {
                 .add_dirichlet(dirichlet)
                 .add_rollout_depth(self.rollout_depth)
                 .add_rollout_temperature(self.rollout_temperature)
+                .add_threads(self.threads)
         } else {
             MctsSetting::default()
                 .add_rollout_depth(self.rollout_depth)
                 .add_rollout_temperature(self.rollout_temperature)
+                .add_threads(self.threads)
         }
     }
 }
@@ -79,7 +86,7 @@ pub fn main() -> Result<()> {
                 .help("Board size")
                 .takes_value(true)
                 .default_value("5")
-                .possible_values(&["4", "5", "6"]),
+                .possible_values(&["3", "4", "5", "6", "7", "8"]),
         )
         .arg(
             Arg::with_name("logfile")
@@ -107,6 +114,11 @@ pub fn main() -> Result<()> {
                  .takes_value(true)
                  .required(true),
         )
+        .arg(Arg::with_name("komi")
+            .long("komi")
+            .help("Komi to seek games with, in flats. May be a half-integer, like 2.5")
+            .takes_value(true)
+            .default_value("0"))
         .arg(Arg::with_name("allowChoosingColor")
             .long("allow-choosing-color")
             .help("Allow users to change the bot's seek color through chat")
@@ -138,6 +150,20 @@ pub fn main() -> Result<()> {
             .long("fixed-nodes")
             .conflicts_with("aws-function-name")
             .help("Normally, the bot will search a variable number of nodes, depending on hardware on time control. This option overrides that to calculate a fixed amount of nodes each move")
+            .takes_value(true))
+        .arg(Arg::with_name("threads")
+            .long("threads")
+            .help("Number of threads to search with")
+            .takes_value(true)
+            .default_value("1"))
+        .arg(Arg::with_name("ponder")
+            .long("ponder")
+            .conflicts_with("fixedNodes")
+            .help("Keep searching while the opponent is thinking. If the opponent plays a move that was searched, the search continues from it")
+            .takes_value(false))
+        .arg(Arg::with_name("book")
+            .long("book")
+            .help("Opening book file. In positions that are in the book, the bot plays a book move instantly instead of searching")
             .takes_value(true));
 
     if cfg!(feature = "aws-lambda-client") {
@@ -215,8 +241,28 @@ pub fn main() -> Result<()> {
 
     let fixed_nodes: Option<u64> = matches.value_of("fixedNodes").map(|v| v.parse().unwrap());
 
+    let threads: usize = matches.value_of("threads").unwrap().parse().unwrap();
+    let ponder = matches.is_present("ponder");
+
     let tc = matches.value_of("tc").map(parse_tc);
 
+    let seek_komi = Komi::from_str(matches.value_of("komi").unwrap()).unwrap();
+
+    let opening_book = match matches.value_of("book") {
+        Some(path) => {
+            let book = OpeningBook::read(io::BufReader::new(fs::File::open(path)?))?;
+            assert_eq!(
+                book.size(),
+                size,
+                "Opening book is for {}s, but playing {}s",
+                book.size(),
+                size
+            );
+            Some(Arc::new(book))
+        }
+        None => None,
+    };
+
     let playtak_settings = PlaytakSettings {
         allow_choosing_color,
         default_seek_color,
@@ -224,8 +270,11 @@ pub fn main() -> Result<()> {
         dirichlet_noise,
         rollout_depth,
         rollout_temperature,
+        threads,
+        ponder,
         seek_game_time: tc.unwrap_or_default().0,
         seek_increment: tc.unwrap_or_default().1,
+        seek_komi,
     };
 
     loop {
@@ -257,26 +306,24 @@ pub fn main() -> Result<()> {
             warn!("No username/password provided, logging in as guest");
             session.login_guest()?;
         }
+        session.opening_book = opening_book.clone();
 
         // Re-connect if we get disconnected from the server
         let error = match matches.value_of("playBot") {
             Some(bot_name) => {
-                match match size {
-                    4 => session.accept_seek::<4>(playtak_settings, bot_name),
-                    5 => session.accept_seek::<5>(playtak_settings, bot_name),
-                    6 => session.accept_seek::<6>(playtak_settings, bot_name),
-                    s => panic!("Unsupported size {}", s),
-                } {
+                // Clap only accepts supported sizes
+                match with_size!(size,
+                    S => session.accept_seek::<S>(playtak_settings, bot_name),
+                    _ => unreachable!()
+                ) {
                     Ok(_game) => return Ok(()),
                     Err(err) => err,
                 }
             }
-            None => match size {
-                4 => session.seek_playtak_games::<4>(playtak_settings),
-                5 => session.seek_playtak_games::<5>(playtak_settings),
-                6 => session.seek_playtak_games::<6>(playtak_settings),
-                s => panic!("Unsupported size {}", s),
-            }
+            None => with_size!(size,
+                S => session.seek_playtak_games::<S>(playtak_settings),
+                _ => unreachable!()
+            )
             .unwrap_err(),
         };
 
@@ -372,6 +419,7 @@ struct PlaytakSession {
     username: Option<String>,
     #[cfg(feature = "aws-lambda-client")]
     aws_function_name: Option<String>,
+    opening_book: Option<Arc<OpeningBook>>,
     connection: BufStream<TcpStream>,
     // The server requires regular pings, to not kick the user
     // This thread does nothing but provide those pings
@@ -387,6 +435,7 @@ struct PlaytakGame<'a> {
     our_color: Color,
     time_left: Duration,
     increment: Duration,
+    komi: Komi,
 }
 
 impl<'a> PlaytakGame<'a> {
@@ -403,6 +452,14 @@ impl<'a> PlaytakGame<'a> {
             },
             time_left: Duration::from_secs(u64::from_str(words[8]).unwrap()),
             increment,
+            // Older versions of the server do not send komi
+            komi: words
+                .get(9)
+                .map(|half_komi| {
+                    Komi::from_half_komi(i8::from_str(half_komi).unwrap())
+                        .unwrap_or_else(|| panic!("Bad komi \"{}\"", half_komi))
+                })
+                .unwrap_or_default(),
         }
     }
 }
@@ -421,6 +478,7 @@ impl PlaytakSession {
             username: None,
             #[cfg(feature = "aws-lambda-client")]
             aws_function_name: None,
+            opening_book: None,
             connection,
             ping_thread,
         })
@@ -506,17 +564,35 @@ impl PlaytakSession {
         playtak_settings: PlaytakSettings,
         color: Option<Color>,
     ) -> Result<()> {
-        self.send_line(&format!(
-            "Seek {} {} {} {}",
-            S,
-            playtak_settings.seek_game_time.as_secs(),
-            playtak_settings.seek_increment.as_secs(),
-            match color {
-                Some(Color::White) => "W",
-                Some(Color::Black) => "B",
-                None => "",
-            }
-        ))
+        if playtak_settings.seek_komi == Komi::default() {
+            self.send_line(&format!(
+                "Seek {} {} {} {}",
+                S,
+                playtak_settings.seek_game_time.as_secs(),
+                playtak_settings.seek_increment.as_secs(),
+                match color {
+                    Some(Color::White) => "W",
+                    Some(Color::Black) => "B",
+                    None => "",
+                }
+            ))
+        } else {
+            // Seeks with komi must also specify the color and the reserve counts
+            self.send_line(&format!(
+                "Seek {} {} {} {} {} {} {}",
+                S,
+                playtak_settings.seek_game_time.as_secs(),
+                playtak_settings.seek_increment.as_secs(),
+                match color {
+                    Some(Color::White) => "W",
+                    Some(Color::Black) => "B",
+                    None => "A",
+                },
+                playtak_settings.seek_komi.half_komi(),
+                starting_stones::<S>(),
+                starting_capstones::<S>(),
+            ))
+        }
     }
 
     fn seek_playtak_games<const S: usize>(
@@ -633,40 +709,54 @@ impl PlaytakSession {
         mut restoring_previous_session: bool,
     ) -> io::Result<(Game<Position<S>>, Option<Color>)> {
         info!(
-            "Starting game #{}, {} vs {} as {}, {}+{:.1}",
+            "Starting game #{}, {} vs {} as {}, {}+{:.1}, komi {}",
             game.game_no,
             game.white_player,
             game.black_player,
             game.our_color,
             game.time_left.as_secs(),
-            game.increment.as_secs_f32()
+            game.increment.as_secs_f32(),
+            game.komi
         );
         let mut next_seek_color = playtak_settings.default_seek_color;
-        let mut position = <Position<S>>::start_position();
+        let mut position = <Position<S>>::start_position_with_komi(game.komi);
         let mut moves = vec![];
         let mut our_time_left = game.time_left;
+        // The tree from our previous search, which is re-used for the next move
+        let mut search_tree: Option<search::MonteCarloTree<S>> = None;
+        let mut ponder: Option<search::BackgroundSearch<search::MonteCarloTree<S>>> = None;
         'gameloop: loop {
             if position.game_result().is_some() {
                 break;
             }
             if position.side_to_move() == game.our_color && !restoring_previous_session {
+                if let Some(ponder) = ponder.take() {
+                    let tree = ponder.stop();
+                    debug!("Searched {} nodes while pondering", tree.visits());
+                    search_tree = Some(tree);
+                }
                 let (best_move, score) =
                     // On the very first move, always place instantly in a random corner
                     if squares_iterator::<S>().all(|square| position[square].is_empty()) {
                         let mut rng = rand::thread_rng();
-                        let moves = vec![
+                        let moves = [
                             Move::Place(Role::Flat, Square(0)),
                             Move::Place(Role::Flat, Square(S as u8 - 1)),
                             Move::Place(Role::Flat, Square((S * (S - 1)) as u8)),
                             Move::Place(Role::Flat, Square((S * S - 1) as u8)),
                         ];
                         (moves.choose(&mut rng).unwrap().clone(), 0.0)
+                    } else if let Some((book_move, score)) = self
+                        .opening_book
+                        .as_ref()
+                        .and_then(|book| book.choose_move(&position, &mut rand::thread_rng()))
+                    {
+                        info!("Playing book move {}", book_move.to_string::<S>());
+                        (book_move, score)
                     } else if let Some(fixed_nodes) = playtak_settings.fixed_nodes {
                         let settings = playtak_settings.to_mcts_setting();
-                        let mut tree = search::MonteCarloTree::with_settings(position.clone(), settings);
-                        for _ in 0..fixed_nodes {
-                            tree.select();
-                        }
+                        let tree = advance_search_tree(&mut search_tree, &position, &moves, settings);
+                        tree.search(fixed_nodes.saturating_sub(tree.visits()));
 
                         // Wait for a bit
                         let mut rng = rand::thread_rng();
@@ -681,6 +771,7 @@ impl PlaytakSession {
                             let event = aws::Event {
                                 size: S,
                                 tps: None,
+                                komi: game.komi,
                                 moves: moves
                                     .iter()
                                     .map(|PtnMove { mv, .. }: &PtnMove<Move>| mv.to_string::<S>())
@@ -701,15 +792,21 @@ impl PlaytakSession {
 
                             let maximum_time = our_time_left / 20 + game.increment;
 
-                            search::play_move_time(position.clone(), maximum_time, settings)
+                            advance_search_tree(&mut search_tree, &position, &moves, settings)
+                                .search_for_time(maximum_time)
                         }
                     };
 
                 position.do_move(best_move.clone());
                 moves.push(PtnMove {
                     mv: best_move.clone(),
-                    annotations: vec![],
-                    comment: score.to_string(),
+                    annotations: Annotations::default(),
+                    comment: String::new(),
+                    comment_tags: CommentTags {
+                        eval: Some(score),
+                        ..CommentTags::default()
+                    },
+                    variations: vec![],
                 });
 
                 let output_string = format!(
@@ -719,6 +816,15 @@ impl PlaytakSession {
                 );
                 self.send_line(&output_string)?;
 
+                // Search the opponent's replies until they move
+                if playtak_settings.ponder && position.game_result().is_none() {
+                    let settings = playtak_settings.to_mcts_setting();
+                    advance_search_tree(&mut search_tree, &position, &moves, settings);
+                    ponder = search_tree
+                        .take()
+                        .map(search::MonteCarloTree::search_in_background);
+                }
+
                 // Say "Tak" whenever there is a threat to win
                 // Only do this vs Shigewara
                 if game.white_player == "shigewara" || game.black_player == "shigewara" {
@@ -777,8 +883,10 @@ impl PlaytakSession {
                                 position.do_move(move_played.clone());
                                 moves.push(PtnMove {
                                     mv: move_played,
-                                    annotations: vec![],
-                                    comment: "0.0".to_string(),
+                                    annotations: Annotations::default(),
+                                    comment: String::new(),
+                                    comment_tags: CommentTags::default(),
+                                    variations: vec![],
                                 });
                                 break;
                             }
@@ -802,7 +910,10 @@ impl PlaytakSession {
             }
         }
 
-        info!("Game finished. Pgn: ");
+        match position.game_outcome() {
+            Some(outcome) => info!("Game finished: {}. Pgn: ", outcome),
+            None => info!("Game finished. Pgn: "),
+        }
 
         let date = Local::today();
 
@@ -819,7 +930,7 @@ impl PlaytakSession {
         ];
 
         let game = Game {
-            start_position: <Position<S>>::start_position(),
+            start_position: <Position<S>>::start_position_with_komi(game.komi),
             moves: moves.clone(),
             game_result: position.game_result(),
             tags,
@@ -841,6 +952,32 @@ impl PlaytakSession {
     }
 }
 
+/// Advance the tree from our previous search to the current position, keeping the subtree of the moves that were played.
+/// Starts a new tree if there was no previous search.
+fn advance_search_tree<'a, const S: usize>(
+    search_tree: &'a mut Option<search::MonteCarloTree<S>>,
+    position: &Position<S>,
+    moves: &[PtnMove<Move>],
+    settings: MctsSetting<S>,
+) -> &'a mut search::MonteCarloTree<S> {
+    match search_tree {
+        Some(tree) => {
+            let moves_since_search: Vec<Move> = moves[tree.position().half_moves_played()..]
+                .iter()
+                .map(|ptn_move| ptn_move.mv.clone())
+                .collect();
+            tree.advance(&moves_since_search);
+        }
+        None => {
+            *search_tree = Some(search::MonteCarloTree::with_settings(
+                position.clone(),
+                settings,
+            ))
+        }
+    }
+    search_tree.as_mut().unwrap()
+}
+
 fn connect() -> Result<BufStream<TcpStream>> {
     let connection = dial()?;
     Ok(connection)
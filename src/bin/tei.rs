@@ -1,14 +1,125 @@
-use board_game_traits::{Color, Position as PositionTrait};
-use pgn_traits::PgnPosition;
+use board_game_traits::Color;
+use std::fs;
 use std::io;
 use std::io::{BufRead, BufReader};
 use std::str::FromStr;
 use std::time::{Duration, Instant};
-use tiltak::position::Position;
+use tiltak::opening_book::OpeningBook;
+use tiltak::position::{AnyMove, AnyPosition, Komi, SUPPORTED_SIZES};
+use tiltak::search::{AnyMctsSetting, AnyTree, BackgroundSearch, ProvenResult, Score};
+use tiltak::tinue::TinueResult;
 
-use std::any::Any;
-use tiltak::search;
-use tiltak::search::MctsSetting;
+/// The search tree of the last `go` command, which the next search continues from if the game has only moved forward
+struct SearchTree {
+    start_position: AnyPosition,
+    moves: Vec<AnyMove>,
+    tree: AnyTree,
+}
+
+impl SearchTree {
+    /// Get a tree for the position after `moves`, re-using the previous tree if possible
+    fn for_position<'a>(
+        search_tree: &'a mut Option<SearchTree>,
+        start_position: &AnyPosition,
+        moves: &[AnyMove],
+        settings: AnyMctsSetting,
+    ) -> &'a mut AnyTree {
+        match search_tree {
+            Some(search_tree)
+                if search_tree.start_position == *start_position
+                    && moves.starts_with(&search_tree.moves) =>
+            {
+                search_tree.tree.advance(&moves[search_tree.moves.len()..]);
+                search_tree.moves = moves.to_vec();
+            }
+            _ => {
+                let mut position = start_position.clone();
+                for mv in moves {
+                    position.do_move(mv.clone());
+                }
+                *search_tree = Some(SearchTree {
+                    start_position: start_position.clone(),
+                    moves: moves.to_vec(),
+                    tree: AnyTree::with_settings(position, settings),
+                });
+            }
+        }
+        &mut search_tree.as_mut().unwrap().tree
+    }
+}
+
+/// Engine options that apply to every search
+struct SearchOptions {
+    threads: usize,
+    /// Memory limit for the search tree, in megabytes
+    hash_mb: usize,
+    /// Node limit for solving flat races when they are reached in search. Disabled if 0
+    endgame_solver_nodes: u64,
+}
+
+impl SearchOptions {
+    fn mcts_settings(&self, size: usize) -> AnyMctsSetting {
+        let settings = AnyMctsSetting::default_for_size(size)
+            .unwrap()
+            .add_threads(self.threads)
+            .add_memory_limit(self.hash_mb * 1024 * 1024);
+        if self.endgame_solver_nodes > 0 {
+            settings.add_endgame_solver(self.endgame_solver_nodes)
+        } else {
+            settings
+        }
+    }
+
+    /// How full the search tree is, in permille of the memory limit
+    fn hashfull(&self, tree: &AnyTree) -> usize {
+        (tree.mem_usage() * 1000 / (self.hash_mb * 1024 * 1024)).min(1000)
+    }
+}
+
+/// A `go ponder` search running in the background, until `ponderhit` or `stop` is received
+struct Ponder {
+    /// The `go` command without `ponder`, which is searched if the opponent plays the expected move
+    go_line: String,
+    start_position: AnyPosition,
+    moves: Vec<AnyMove>,
+    search: BackgroundSearch<AnyTree>,
+}
+
+impl Ponder {
+    fn start(
+        go_line: &str,
+        start_position: &AnyPosition,
+        moves: &[AnyMove],
+        search_tree: &mut Option<SearchTree>,
+        settings: AnyMctsSetting,
+    ) -> Self {
+        SearchTree::for_position(search_tree, start_position, moves, settings);
+        let SearchTree {
+            start_position,
+            moves,
+            tree,
+        } = search_tree.take().unwrap();
+        Ponder {
+            go_line: go_line
+                .split_whitespace()
+                .filter(|word| *word != "ponder")
+                .collect::<Vec<_>>()
+                .join(" "),
+            start_position,
+            moves,
+            search: tree.search_in_background(),
+        }
+    }
+
+    /// Stop pondering, keeping the tree for the next search
+    fn stop(self) -> SearchTree {
+        SearchTree {
+            start_position: self.start_position,
+            moves: self.moves,
+            tree: self.search.stop(),
+        }
+    }
+}
 
 pub fn main() {
     loop {
@@ -21,116 +132,289 @@ pub fn main() {
 
     println!("id name tiltak");
     println!("id author Morten Lohne");
+    println!("option name HalfKomi type spin default 0 min -20 max 20");
+    println!("option name BookFile type string default <empty>");
+    println!("option name Threads type spin default 1 min 1 max 512");
+    println!("option name Hash type spin default 1024 min 1 max 1048576");
+    println!("option name Ponder type check default false");
+    println!("option name EndgameSolverNodes type spin default 0 min 0 max 1000000000");
     println!("teiok");
 
-    // Position stored in a `dyn Any` variable, because it can be any size
-    let mut position: Option<Box<dyn Any>> = None;
+    // The start position and the moves played from it
+    let mut position: Option<(AnyPosition, Vec<AnyMove>)> = None;
+    let mut search_tree: Option<SearchTree> = None;
+    let mut ponder: Option<Ponder> = None;
     let mut size: Option<usize> = None;
+    let mut komi = Komi::default();
+    let mut opening_book: Option<OpeningBook> = None;
+    let mut search_options = SearchOptions {
+        threads: 1,
+        hash_mb: 1024,
+        endgame_solver_nodes: 0,
+    };
 
     for line in BufReader::new(io::stdin()).lines().map(Result::unwrap) {
         let mut words = line.split_whitespace();
-        match words.next().unwrap() {
+        let command = words.next().unwrap();
+        // Any other command means the opponent didn't play the move we pondered on
+        if !matches!(command, "isready" | "ponderhit" | "stop") {
+            if let Some(ponder) = ponder.take() {
+                search_tree = Some(ponder.stop());
+            }
+        }
+        match command {
             "quit" => break,
             "isready" => println!("readyok"),
-            "setoption" => panic!("Unknown option \"{}\"", line),
+            "ponderhit" => {
+                let ponder = ponder
+                    .take()
+                    .unwrap_or_else(|| panic!("Received ponderhit without pondering"));
+                let go_line = ponder.go_line.clone();
+                let SearchTree {
+                    start_position,
+                    moves,
+                    ..
+                } = search_tree.insert(ponder.stop());
+                let (start_position, moves) = (start_position.clone(), moves.clone());
+                parse_go_string(
+                    &go_line,
+                    &start_position,
+                    &moves,
+                    &mut search_tree,
+                    opening_book.as_ref(),
+                    &search_options,
+                )
+            }
+            "stop" => {
+                if let Some(ponder) = ponder.take() {
+                    let SearchTree {
+                        start_position,
+                        moves,
+                        tree,
+                    } = search_tree.insert(ponder.stop());
+                    let mut position = start_position.clone();
+                    for mv in moves.iter() {
+                        position.do_move(mv.clone());
+                    }
+                    // The search may have been stopped before the root's moves were generated
+                    while tree.visits() < 2 {
+                        tree.select();
+                    }
+                    print_best_move(&position, tree);
+                }
+            }
+            "setoption" => {
+                search_tree = None;
+                let option_words: Vec<&str> = words.collect();
+                match option_words.as_slice() {
+                    ["name", "HalfKomi", "value", value] => {
+                        komi = i8::from_str(value)
+                            .ok()
+                            .and_then(Komi::from_half_komi)
+                            .unwrap_or_else(|| panic!("Invalid komi \"{}\"", line))
+                    }
+                    ["name", "BookFile", "value"] | ["name", "BookFile", "value", "<empty>"] => {
+                        opening_book = None
+                    }
+                    ["name", "BookFile", "value", path] => {
+                        let file = fs::File::open(path)
+                            .unwrap_or_else(|err| panic!("Couldn't open book {}: {}", path, err));
+                        opening_book = Some(
+                            OpeningBook::read(BufReader::new(file))
+                                .unwrap_or_else(|err| panic!("Invalid book {}: {}", path, err)),
+                        )
+                    }
+                    ["name", "Threads", "value", value] => {
+                        search_options.threads = usize::from_str(value)
+                            .ok()
+                            .filter(|threads| (1..=512).contains(threads))
+                            .unwrap_or_else(|| panic!("Invalid thread count \"{}\"", line))
+                    }
+                    ["name", "Hash", "value", value] => {
+                        search_options.hash_mb = usize::from_str(value)
+                            .ok()
+                            .filter(|hash_mb| (1..=1048576).contains(hash_mb))
+                            .unwrap_or_else(|| panic!("Invalid hash size \"{}\"", line))
+                    }
+                    ["name", "EndgameSolverNodes", "value", value] => {
+                        search_options.endgame_solver_nodes = u64::from_str(value)
+                            .ok()
+                            .filter(|nodes| *nodes <= 1_000_000_000)
+                            .unwrap_or_else(|| panic!("Invalid endgame solver nodes \"{}\"", line))
+                    }
+                    // Pondering is controlled by the GUI through `go ponder`, so the option only tells the GUI that it's supported
+                    ["name", "Ponder", "value", "true" | "false"] => (),
+                    _ => panic!("Unknown option \"{}\"", line),
+                }
+            }
             "teinewgame" => {
                 let size_string = words.next();
                 size = size_string.and_then(|s| usize::from_str(s).ok());
                 position = None;
+                search_tree = None;
 
                 match size {
-                    Some(4) | Some(5) | Some(6) => (),
+                    Some(s) if SUPPORTED_SIZES.contains(&s) => (),
                     _ => panic!("Error: Unsupported size {}", size.unwrap_or_default()),
                 }
             }
             "position" => {
-                position = match size {
-                    None => panic!("Received position without receiving teinewgame string"),
-                    Some(4) => Some(Box::new(parse_position_string::<4>(&line))),
-                    Some(5) => Some(Box::new(parse_position_string::<5>(&line))),
-                    Some(6) => Some(Box::new(parse_position_string::<6>(&line))),
-                    Some(s) => panic!("Unsupported size {}", s),
-                }
+                let size = size.unwrap_or_else(|| {
+                    panic!("Received position without receiving teinewgame string")
+                });
+                position = Some(parse_position_string(&line, size, komi));
             }
-            "go" => match size {
-                Some(4) => parse_go_string::<4>(
+            "go" if line.split_whitespace().any(|word| word == "ponder") => {
+                let (start_position, moves) = position.as_ref().unwrap_or_else(|| {
+                    panic!("Error: Received go without receiving position string")
+                });
+                let settings = search_options.mcts_settings(start_position.size());
+                ponder = Some(Ponder::start(
                     &line,
-                    position.as_ref().and_then(|p| p.downcast_ref()).unwrap(),
-                ),
-                Some(5) => parse_go_string::<5>(
-                    &line,
-                    position.as_ref().and_then(|p| p.downcast_ref()).unwrap(),
-                ),
-                Some(6) => parse_go_string::<6>(
+                    start_position,
+                    moves,
+                    &mut search_tree,
+                    settings,
+                ));
+            }
+            "go" => {
+                let (start_position, moves) = position.as_ref().unwrap_or_else(|| {
+                    panic!("Error: Received go without receiving position string")
+                });
+                parse_go_string(
                     &line,
-                    position.as_ref().and_then(|p| p.downcast_ref()).unwrap(),
-                ),
-                Some(s) => panic!("Error: Unsupported size {}", s),
-                None => panic!("Error: Received go without receiving teinewgame string"),
-            },
+                    start_position,
+                    moves,
+                    &mut search_tree,
+                    opening_book.as_ref(),
+                    &search_options,
+                )
+            }
             s => panic!("Unknown command \"{}\"", s),
         }
     }
 }
 
-fn parse_position_string<const S: usize>(line: &str) -> Position<S> {
+/// Print the best move, and the expected reply to ponder on, if the search found one
+fn print_best_move(position: &AnyPosition, tree: &AnyTree) {
+    let (best_move, _score) = tree.best_move();
+    match tree.pv().get(1) {
+        Some(ponder_move) => println!(
+            "bestmove {} ponder {}",
+            position.move_to_san(&best_move),
+            ponder_move
+        ),
+        None => println!("bestmove {}", position.move_to_san(&best_move)),
+    }
+}
+
+/// Format the score for an info line, as `mate N` if the search has proven the result with an exact length, and `cp N` otherwise.
+/// Mate scores count moves for the side to move, and are negative if it will be mated
+fn score_string(tree: &AnyTree, score: Score) -> String {
+    match tree.proven_result() {
+        Some(ProvenResult::Win { plies, exact: true }) => format!("mate {}", plies.div_ceil(2)),
+        Some(ProvenResult::Loss { plies, exact: true }) => format!("mate -{}", plies.div_ceil(2)),
+        Some(ProvenResult::Draw) => "cp 0".to_string(),
+        Some(proven_result) => format!("cp {}", (proven_result.score() * 200.0 - 100.0) as i64),
+        None => format!("cp {}", (score * 200.0 - 100.0) as i64),
+    }
+}
+
+/// Returns the start position, and the moves played from it
+fn parse_position_string(line: &str, size: usize, komi: Komi) -> (AnyPosition, Vec<AnyMove>) {
     let mut words_iter = line.split_whitespace();
     words_iter.next(); // position
-    let mut position = match words_iter.next() {
-        Some("startpos") => Position::default(),
+    let start_position = match words_iter.next() {
+        Some("startpos") => AnyPosition::start_position_with_komi(size, komi).unwrap(),
         Some("tps") => {
             let tps: String = (&mut words_iter).take(3).collect::<Vec<_>>().join(" ");
-            <Position<S>>::from_fen(&tps).unwrap()
+            AnyPosition::from_fen_with_komi(size, &tps, komi).unwrap()
         }
         _ => panic!("Expected \"startpos\" or \"tps\" to specify position."),
     };
 
+    let mut position = start_position.clone();
+    let mut moves = vec![];
     match words_iter.next() {
         Some("moves") => {
             for move_string in words_iter {
-                position.do_move(position.move_from_san(move_string).unwrap());
+                let mv = position.move_from_san(move_string).unwrap();
+                position.do_move(mv.clone());
+                moves.push(mv);
             }
         }
         Some(s) => panic!("Expected \"moves\" in \"{}\", got \"{}\".", line, s),
         None => (),
     }
-    position
+    (start_position, moves)
 }
 
-fn parse_go_string<const S: usize>(line: &str, position: &Position<S>) {
+fn parse_go_string(
+    line: &str,
+    start_position: &AnyPosition,
+    moves: &[AnyMove],
+    search_tree: &mut Option<SearchTree>,
+    opening_book: Option<&OpeningBook>,
+    search_options: &SearchOptions,
+) {
     let mut words = line.split_whitespace();
     words.next(); // go
 
-    let mcts_settings = MctsSetting::default();
+    let mut position = start_position.clone();
+    for mv in moves {
+        position.do_move(mv.clone());
+    }
+
+    let mcts_settings = search_options.mcts_settings(position.size());
+
+    let go_type = words.next();
+
+    // Play book moves instantly, unless we are only looking for a forced win
+    if go_type != Some("tinue") {
+        if let Some((book_move, score)) =
+            opening_book.and_then(|book| book.choose_any_move(&position, &mut rand::thread_rng()))
+        {
+            println!("info string book move");
+            println!(
+                "info score cp {} pv {}",
+                (score * 200.0 - 100.0) as i64,
+                book_move
+            );
+            println!("bestmove {}", book_move);
+            return;
+        }
+    }
 
-    match words.next() {
+    match go_type {
         Some("movetime") => {
             let msecs = words.next().unwrap();
             let movetime = Duration::from_millis(u64::from_str(msecs).unwrap());
             let start_time = Instant::now();
 
-            let mut tree = search::MonteCarloTree::with_settings(position.clone(), mcts_settings);
+            let tree = SearchTree::for_position(search_tree, start_position, moves, mcts_settings);
             let mut total_nodes = 0;
             for i in 0.. {
                 let nodes_to_search = (200.0 * f64::powf(1.26, i as f64)) as u64;
-                for _ in 0..nodes_to_search {
-                    tree.select();
-                }
+                tree.search(nodes_to_search);
                 total_nodes += nodes_to_search;
-                let (best_move, score) = tree.best_move();
+                let (_, score) = tree.best_move();
                 println!(
-                    "info depth {} seldepth {} score cp {} nodes {} time {} pv {}",
+                    "info depth {} seldepth {} score {} nodes {} hashfull {} time {} pv {}",
                     i / 2 + 1,
-                    tree.pv().count(),
-                    (score * 200.0 - 100.0) as i64,
+                    tree.pv().len(),
+                    score_string(tree, score),
                     total_nodes,
+                    search_options.hashfull(tree),
                     start_time.elapsed().as_millis(),
                     tree.pv()
-                        .map(|mv| mv.to_string::<S>() + " ")
+                        .iter()
+                        .map(|mv| mv.to_string() + " ")
                         .collect::<String>()
                 );
-                if start_time.elapsed().as_secs_f64() > movetime.as_secs_f64() * 0.7 {
-                    println!("bestmove {}", position.move_to_san(&best_move));
+                if start_time.elapsed().as_secs_f64() > movetime.as_secs_f64() * 0.7
+                    || tree.proven_result().is_some()
+                {
+                    print_best_move(&position, tree);
                     break;
                 }
             }
@@ -164,17 +448,60 @@ fn parse_go_string<const S: usize>(line: &str, position: &Position<S>) {
             };
 
             let start_time = Instant::now();
-            let (best_move, score) =
-                search::play_move_time::<S>(position.clone(), max_time, mcts_settings);
+            let tree = SearchTree::for_position(search_tree, start_position, moves, mcts_settings);
+            let (best_move, score) = tree.search_for_time(max_time);
 
             println!(
-                "info score cp {} time {} pv {}",
-                (score * 200.0 - 100.0) as i64,
+                "info score {} hashfull {} time {} pv {}",
+                score_string(tree, score),
+                search_options.hashfull(tree),
                 start_time.elapsed().as_millis(),
                 position.move_to_san(&best_move)
             );
 
-            println!("bestmove {}", position.move_to_san(&best_move));
+            print_best_move(&position, tree);
+        }
+        // Non-standard extension: search only for a forced road win
+        // Only sends a bestmove if a win was found
+        Some("tinue") => {
+            let mut max_plies = 9;
+            let mut max_nodes = 100_000_000;
+            while let Some(word) = words.next() {
+                let value = words.next();
+                match (word, value) {
+                    ("plies", Some(value)) => {
+                        max_plies = value
+                            .parse()
+                            .unwrap_or_else(|_| panic!("Invalid plies in go command {}", line))
+                    }
+                    ("nodes", Some(value)) => {
+                        max_nodes = value
+                            .parse()
+                            .unwrap_or_else(|_| panic!("Invalid nodes in go command {}", line))
+                    }
+                    _ => panic!("Incorrect go command {}", line),
+                }
+            }
+
+            let start_time = Instant::now();
+            match position.find_tinue(max_plies, max_nodes) {
+                TinueResult::Win(line) => {
+                    println!(
+                        "info score mate {} time {} pv {}",
+                        line.len().div_ceil(2),
+                        start_time.elapsed().as_millis(),
+                        line.iter()
+                            .map(|mv| mv.to_string())
+                            .collect::<Vec<_>>()
+                            .join(" ")
+                    );
+                    println!("bestmove {}", line[0]);
+                }
+                TinueResult::NoWin => {
+                    println!("info string no tinue within {} plies", max_plies)
+                }
+                TinueResult::Unknown => println!("info string tinue search ran out of nodes"),
+            }
         }
         Some(_) | None => {
             panic!("Invalid go command \"{}\"", line);
@@ -3,9 +3,12 @@ use std::path::Path;
 use clap::{App, Arg, SubCommand};
 
 use tiltak::evaluation::parameters::{
-    NUM_POLICY_FEATURES_4S, NUM_POLICY_FEATURES_5S, NUM_POLICY_FEATURES_6S, NUM_VALUE_FEATURES_4S,
-    NUM_VALUE_FEATURES_5S, NUM_VALUE_FEATURES_6S, POLICY_PARAMS_4S, POLICY_PARAMS_5S,
-    POLICY_PARAMS_6S, VALUE_PARAMS_4S, VALUE_PARAMS_5S, VALUE_PARAMS_6S,
+    NUM_POLICY_FEATURES_3S, NUM_POLICY_FEATURES_4S, NUM_POLICY_FEATURES_5S, NUM_POLICY_FEATURES_6S,
+    NUM_POLICY_FEATURES_7S, NUM_POLICY_FEATURES_8S, NUM_VALUE_FEATURES_3S, NUM_VALUE_FEATURES_4S,
+    NUM_VALUE_FEATURES_5S, NUM_VALUE_FEATURES_6S, NUM_VALUE_FEATURES_7S, NUM_VALUE_FEATURES_8S,
+    POLICY_PARAMS_3S, POLICY_PARAMS_4S, POLICY_PARAMS_5S, POLICY_PARAMS_6S, POLICY_PARAMS_7S,
+    POLICY_PARAMS_8S, VALUE_PARAMS_3S, VALUE_PARAMS_4S, VALUE_PARAMS_5S, VALUE_PARAMS_6S,
+    VALUE_PARAMS_7S, VALUE_PARAMS_8S,
 };
 use tiltak::tune::{spsa, training};
 
@@ -21,7 +24,7 @@ fn main() {
                 .help("Board size")
                 .takes_value(true)
                 .default_value("5")
-                .possible_values(&["4", "5", "6"]),
+                .possible_values(&["3", "4", "5", "6", "7", "8"]),
         )
         .subcommand(SubCommand::with_name("selfplay")
             .about("Tune value and policy constants by playing against itself. Will write the games to text files in the working directory."))
@@ -70,29 +73,41 @@ fn main() {
                 let file_name = format!("games{}_s{}_batch0.ptn", i, size);
                 if !Path::new(&file_name).exists() {
                     match size {
+                        3 => training::train_perpetually::<
+                            3,
+                            NUM_VALUE_FEATURES_3S,
+                            NUM_POLICY_FEATURES_3S,
+                        >(i, &VALUE_PARAMS_3S, &POLICY_PARAMS_3S, 0)
+                        .unwrap(),
                         4 => training::train_perpetually::<
                             4,
                             NUM_VALUE_FEATURES_4S,
                             NUM_POLICY_FEATURES_4S,
-                        >(
-                            i, &VALUE_PARAMS_4S, &POLICY_PARAMS_4S, vec![], vec![], 0
-                        )
+                        >(i, &VALUE_PARAMS_4S, &POLICY_PARAMS_4S, 0)
                         .unwrap(),
                         5 => training::train_perpetually::<
                             5,
                             NUM_VALUE_FEATURES_5S,
                             NUM_POLICY_FEATURES_5S,
-                        >(
-                            i, &VALUE_PARAMS_5S, &POLICY_PARAMS_5S, vec![], vec![], 0
-                        )
+                        >(i, &VALUE_PARAMS_5S, &POLICY_PARAMS_5S, 0)
                         .unwrap(),
                         6 => training::train_perpetually::<
                             6,
                             NUM_VALUE_FEATURES_6S,
                             NUM_POLICY_FEATURES_6S,
-                        >(
-                            i, &VALUE_PARAMS_6S, &POLICY_PARAMS_6S, vec![], vec![], 0
-                        )
+                        >(i, &VALUE_PARAMS_6S, &POLICY_PARAMS_6S, 0)
+                        .unwrap(),
+                        7 => training::train_perpetually::<
+                            7,
+                            NUM_VALUE_FEATURES_7S,
+                            NUM_POLICY_FEATURES_7S,
+                        >(i, &VALUE_PARAMS_7S, &POLICY_PARAMS_7S, 0)
+                        .unwrap(),
+                        8 => training::train_perpetually::<
+                            8,
+                            NUM_VALUE_FEATURES_8S,
+                            NUM_POLICY_FEATURES_8S,
+                        >(i, &VALUE_PARAMS_8S, &POLICY_PARAMS_8S, 0)
                         .unwrap(),
                         _ => panic!("Size {} not supported.", size),
                     }
@@ -107,6 +122,12 @@ fn main() {
                 let file_name = format!("games{}_s{}_batch0.ptn", i, size);
                 if !Path::new(&file_name).exists() {
                     match size {
+                        3 => training::train_from_scratch::<
+                            3,
+                            NUM_VALUE_FEATURES_3S,
+                            NUM_POLICY_FEATURES_3S,
+                        >(i)
+                        .unwrap(),
                         4 => training::train_from_scratch::<
                             4,
                             NUM_VALUE_FEATURES_4S,
@@ -125,6 +146,18 @@ fn main() {
                             NUM_POLICY_FEATURES_6S,
                         >(i)
                         .unwrap(),
+                        7 => training::train_from_scratch::<
+                            7,
+                            NUM_VALUE_FEATURES_7S,
+                            NUM_POLICY_FEATURES_7S,
+                        >(i)
+                        .unwrap(),
+                        8 => training::train_from_scratch::<
+                            8,
+                            NUM_VALUE_FEATURES_8S,
+                            NUM_POLICY_FEATURES_8S,
+                        >(i)
+                        .unwrap(),
                         _ => panic!("Size {} not supported.", size),
                     }
                     break;
@@ -136,6 +169,12 @@ fn main() {
         ("continue-selfplay", Some(arg)) => {
             let training_id: usize = arg.value_of("training-id").unwrap().parse().unwrap();
             match size {
+                3 => {
+                    training::continue_training::<3, NUM_VALUE_FEATURES_3S, NUM_POLICY_FEATURES_3S>(
+                        training_id,
+                    )
+                    .unwrap()
+                }
                 4 => {
                     training::continue_training::<4, NUM_VALUE_FEATURES_4S, NUM_POLICY_FEATURES_4S>(
                         training_id,
@@ -154,12 +193,30 @@ fn main() {
                     )
                     .unwrap()
                 }
+                7 => {
+                    training::continue_training::<7, NUM_VALUE_FEATURES_7S, NUM_POLICY_FEATURES_7S>(
+                        training_id,
+                    )
+                    .unwrap()
+                }
+                8 => {
+                    training::continue_training::<8, NUM_VALUE_FEATURES_8S, NUM_POLICY_FEATURES_8S>(
+                        training_id,
+                    )
+                    .unwrap()
+                }
                 _ => panic!("Size {} not supported.", size),
             }
         }
         ("value-from-file", Some(arg)) => {
             let file_name = arg.value_of("file-name").unwrap();
             match size {
+                3 => {
+                    let value_params =
+                        training::tune_value_from_file::<3, NUM_VALUE_FEATURES_3S>(file_name)
+                            .unwrap();
+                    println!("{:?}", value_params);
+                }
                 4 => {
                     let value_params =
                         training::tune_value_from_file::<4, NUM_VALUE_FEATURES_4S>(file_name)
@@ -178,6 +235,18 @@ fn main() {
                             .unwrap();
                     println!("{:?}", value_params);
                 }
+                7 => {
+                    let value_params =
+                        training::tune_value_from_file::<7, NUM_VALUE_FEATURES_7S>(file_name)
+                            .unwrap();
+                    println!("{:?}", value_params);
+                }
+                8 => {
+                    let value_params =
+                        training::tune_value_from_file::<8, NUM_VALUE_FEATURES_8S>(file_name)
+                            .unwrap();
+                    println!("{:?}", value_params);
+                }
                 _ => panic!("Size {} not supported.", size),
             }
         }
@@ -185,6 +254,17 @@ fn main() {
             let value_file_name = arg.value_of("value-file-name").unwrap();
             let policy_file_name = arg.value_of("policy-file-name").unwrap();
             match size {
+                3 => {
+                    let (value_params, policy_params) =
+                        training::tune_value_and_policy_from_file::<
+                            3,
+                            NUM_VALUE_FEATURES_3S,
+                            NUM_POLICY_FEATURES_3S,
+                        >(value_file_name, policy_file_name)
+                        .unwrap();
+                    println!("Value: {:?}", value_params);
+                    println!("Policy: {:?}", policy_params);
+                }
                 4 => {
                     let (value_params, policy_params) =
                         training::tune_value_and_policy_from_file::<
@@ -218,6 +298,28 @@ fn main() {
                     println!("Value: {:?}", value_params);
                     println!("Policy: {:?}", policy_params);
                 }
+                7 => {
+                    let (value_params, policy_params) =
+                        training::tune_value_and_policy_from_file::<
+                            7,
+                            NUM_VALUE_FEATURES_7S,
+                            NUM_POLICY_FEATURES_7S,
+                        >(value_file_name, policy_file_name)
+                        .unwrap();
+                    println!("Value: {:?}", value_params);
+                    println!("Policy: {:?}", policy_params);
+                }
+                8 => {
+                    let (value_params, policy_params) =
+                        training::tune_value_and_policy_from_file::<
+                            8,
+                            NUM_VALUE_FEATURES_8S,
+                            NUM_POLICY_FEATURES_8S,
+                        >(value_file_name, policy_file_name)
+                        .unwrap();
+                    println!("Value: {:?}", value_params);
+                    println!("Policy: {:?}", policy_params);
+                }
                 _ => panic!("Size {} not supported.", size),
             }
         }
@@ -245,9 +347,12 @@ fn main() {
                 },
             ];
             match size {
+                3 => spsa::tune::<3>(&mut variables, arg.value_of("book")),
                 4 => spsa::tune::<4>(&mut variables, arg.value_of("book")),
                 5 => spsa::tune::<5>(&mut variables, arg.value_of("book")),
                 6 => spsa::tune::<6>(&mut variables, arg.value_of("book")),
+                7 => spsa::tune::<7>(&mut variables, arg.value_of("book")),
+                8 => spsa::tune::<8>(&mut variables, arg.value_of("book")),
                 _ => panic!("Size {} not supported.", size),
             }
         }
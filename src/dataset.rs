@@ -0,0 +1,383 @@
+//! Compact, versioned binary storage of positions, moves and move distributions, for large training datasets.
+//!
+//! A dataset starts with a header containing the magic bytes `TLTK`, the format version, the board size and the game's rules.
+//! The header is followed by any number of entries. All integers are little-endian.
+//!
+//! # Examples
+//!
+//! ```
+//! use tiltak::dataset::{DatasetEntry, DatasetReader, DatasetWriter};
+//! use tiltak::position::{Position, RuleSet};
+//! use board_game_traits::Position as PositionTrait;
+//!
+//! let position = <Position<5>>::start_position();
+//! let mut moves = vec![];
+//! position.generate_moves(&mut moves);
+//! let entry = DatasetEntry {
+//!     position,
+//!     move_scores: moves.into_iter().map(|mv| (mv, 1.0 / 25.0)).collect(),
+//!     game_result: None,
+//! };
+//!
+//! let mut writer = DatasetWriter::new(vec![], RuleSet::default()).unwrap();
+//! writer.write_entry(&entry).unwrap();
+//! let bytes = writer.into_inner();
+//!
+//! let reader = DatasetReader::<_, 5>::new(bytes.as_slice()).unwrap();
+//! let entries: Vec<DatasetEntry<5>> = reader.collect::<Result<_, _>>().unwrap();
+//! assert_eq!(entries, vec![entry]);
+//! ```
+
+use std::convert::TryFrom;
+use std::io;
+use std::io::{Read, Write};
+
+use board_game_traits::GameResult;
+
+use crate::position::{
+    squares_iterator, Direction, Komi, Move, Movement, Piece, Position, Role, RuleSet, Square,
+    Stack, StackMovement,
+};
+
+const MAGIC: &[u8; 4] = b"TLTK";
+
+/// The current version of the format. Readers reject datasets with a different version.
+pub const FORMAT_VERSION: u8 = 1;
+
+/// A single training sample: a position, the score of each move in it, and the result of its game.
+#[derive(Clone, PartialEq, Debug)]
+pub struct DatasetEntry<const S: usize> {
+    pub position: Position<S>,
+    /// The score, or probability of being played, for each legal move
+    pub move_scores: Vec<(Move, f32)>,
+    pub game_result: Option<GameResult>,
+}
+
+/// Writes a dataset header, followed by one entry at a time.
+pub struct DatasetWriter<W: Write, const S: usize> {
+    writer: W,
+    rules: RuleSet<S>,
+}
+
+impl<W: Write, const S: usize> DatasetWriter<W, S> {
+    /// Write the header. Every position in the dataset must be played with `rules`.
+    pub fn new(mut writer: W, rules: RuleSet<S>) -> io::Result<Self> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[FORMAT_VERSION, S as u8])?;
+        write_rules(&mut writer, &rules)?;
+        Ok(DatasetWriter { writer, rules })
+    }
+
+    pub fn write_entry(&mut self, entry: &DatasetEntry<S>) -> io::Result<()> {
+        if *entry.position.rules() != self.rules {
+            return Err(invalid_data(
+                "Position has different rules than the dataset".to_string(),
+            ));
+        }
+        write_position(&mut self.writer, &entry.position)?;
+        let game_result_byte = match entry.game_result {
+            None => 0,
+            Some(GameResult::WhiteWin) => 1,
+            Some(GameResult::BlackWin) => 2,
+            Some(GameResult::Draw) => 3,
+        };
+        self.writer.write_all(&[game_result_byte])?;
+        let num_moves = u16::try_from(entry.move_scores.len())
+            .map_err(|_| invalid_data("Too many moves in entry".to_string()))?;
+        self.writer.write_all(&num_moves.to_le_bytes())?;
+        for (mv, score) in entry.move_scores.iter() {
+            write_move::<_, S>(&mut self.writer, mv)?;
+            self.writer.write_all(&score.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Reads a dataset one entry at a time, without loading the whole dataset into memory.
+pub struct DatasetReader<R: Read, const S: usize> {
+    reader: R,
+    rules: RuleSet<S>,
+}
+
+impl<R: Read, const S: usize> DatasetReader<R, S> {
+    /// Read and validate the header
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("Not a tiltak dataset".to_string()));
+        }
+        let [version, size] = read_bytes::<_, 2>(&mut reader)?;
+        if version != FORMAT_VERSION {
+            return Err(invalid_data(format!(
+                "Unsupported dataset version {}, expected {}",
+                version, FORMAT_VERSION
+            )));
+        }
+        if size as usize != S {
+            return Err(invalid_data(format!(
+                "Dataset is for {}s, expected {}s",
+                size, S
+            )));
+        }
+        let rules = read_rules(&mut reader)?;
+        Ok(DatasetReader { reader, rules })
+    }
+
+    pub fn rules(&self) -> &RuleSet<S> {
+        &self.rules
+    }
+
+    /// Returns `Ok(None)` at the end of the dataset
+    pub fn read_entry(&mut self) -> io::Result<Option<DatasetEntry<S>>> {
+        // Only a clean EOF at the start of an entry ends the dataset
+        let mut first_byte = [0];
+        if self.reader.read(&mut first_byte)? == 0 {
+            return Ok(None);
+        }
+        let position = read_position(&mut first_byte.chain(&mut self.reader), self.rules)?;
+        let game_result = match read_bytes::<_, 1>(&mut self.reader)? {
+            [0] => None,
+            [1] => Some(GameResult::WhiteWin),
+            [2] => Some(GameResult::BlackWin),
+            [3] => Some(GameResult::Draw),
+            [byte] => return Err(invalid_data(format!("Invalid game result {}", byte))),
+        };
+        let num_moves = u16::from_le_bytes(read_bytes(&mut self.reader)?);
+        let mut move_scores = Vec::with_capacity(num_moves as usize);
+        for _ in 0..num_moves {
+            let mv = read_move::<_, S>(&mut self.reader)?;
+            let score = f32::from_le_bytes(read_bytes(&mut self.reader)?);
+            move_scores.push((mv, score));
+        }
+        Ok(Some(DatasetEntry {
+            position,
+            move_scores,
+            game_result,
+        }))
+    }
+}
+
+impl<R: Read, const S: usize> Iterator for DatasetReader<R, S> {
+    type Item = io::Result<DatasetEntry<S>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_entry().transpose()
+    }
+}
+
+fn write_rules<W: Write, const S: usize>(writer: &mut W, rules: &RuleSet<S>) -> io::Result<()> {
+    let flags = rules.opening_swap() as u8 | (rules.repetition_draws() as u8) << 1;
+    writer.write_all(&[
+        rules.starting_stones(),
+        rules.starting_capstones(),
+        rules.komi().half_komi() as u8,
+        flags,
+    ])
+}
+
+fn read_rules<R: Read, const S: usize>(reader: &mut R) -> io::Result<RuleSet<S>> {
+    let [starting_stones, starting_capstones, half_komi, flags] = read_bytes(reader)?;
+    let komi = Komi::from_half_komi(half_komi as i8)
+        .ok_or_else(|| invalid_data(format!("Invalid komi {}", half_komi as i8 as f32 / 2.0)))?;
+    let mut rules = RuleSet::default()
+        .add_starting_stones(starting_stones)
+        .and_then(|rules| rules.add_starting_capstones(starting_capstones))
+        .ok_or_else(|| {
+            invalid_data(format!(
+                "Invalid reserves {}/{}",
+                starting_stones, starting_capstones
+            ))
+        })?
+        .add_komi(komi);
+    if flags & 1 == 0 {
+        rules = rules.without_opening_swap();
+    }
+    if flags & 2 == 0 {
+        rules = rules.without_repetition_draws();
+    }
+    Ok(rules)
+}
+
+/// Write a position's board and move number. The rules are not included.
+///
+/// The encoding is the number of half moves played as a `u16`, a bitmap of occupied squares, and for each occupied square:
+/// * One byte with the top stone in the lowest 3 bits, and the number of stones below it in the upper 5 bits.
+///   Stacks with 31 or more stones below the top stone store the number in an extra byte.
+/// * The colors of the flatstones below the top stone, one bit each, with 1 for white.
+pub fn write_position<W: Write, const S: usize>(
+    writer: &mut W,
+    position: &Position<S>,
+) -> io::Result<()> {
+    let half_moves_played = u16::try_from(position.half_moves_played())
+        .map_err(|_| invalid_data("Too many moves played".to_string()))?;
+    writer.write_all(&half_moves_played.to_le_bytes())?;
+
+    let mut occupied = [0; 8];
+    for square in squares_iterator::<S>() {
+        if !position[square].is_empty() {
+            occupied[square.0 as usize / 8] |= 1 << (square.0 % 8);
+        }
+    }
+    writer.write_all(&occupied[0..bitmap_len::<S>()])?;
+
+    for square in squares_iterator::<S>() {
+        let stack = position[square];
+        if let Some(top_stone) = stack.top_stone() {
+            let stones_below = stack.len() - 1;
+            writer.write_all(&[top_stone as u8 | stones_below.min(31) << 3])?;
+            if stones_below >= 31 {
+                writer.write_all(&[stones_below])?;
+            }
+            let mut colors = vec![0; (stones_below as usize).div_ceil(8)];
+            for i in 0..stones_below {
+                if stack.get(i) == Some(Piece::WhiteFlat) {
+                    colors[i as usize / 8] |= 1 << (i % 8);
+                }
+            }
+            writer.write_all(&colors)?;
+        }
+    }
+    Ok(())
+}
+
+/// Read a position written by `write_position`
+pub fn read_position<R: Read, const S: usize>(
+    reader: &mut R,
+    rules: RuleSet<S>,
+) -> io::Result<Position<S>> {
+    let half_moves_played = u16::from_le_bytes(read_bytes(reader)?);
+
+    let mut occupied = [0; 8];
+    reader.read_exact(&mut occupied[0..bitmap_len::<S>()])?;
+
+    let mut stacks = vec![Stack::default(); S * S];
+    for square in squares_iterator::<S>() {
+        if occupied[square.0 as usize / 8] & (1 << (square.0 % 8)) == 0 {
+            continue;
+        }
+        let [byte] = read_bytes(reader)?;
+        let top_stone = piece_from_u8(byte & 0b111)?;
+        let stones_below = match byte >> 3 {
+            31 => read_bytes::<_, 1>(reader)?[0],
+            n => n,
+        };
+        let mut colors = vec![0; (stones_below as usize).div_ceil(8)];
+        reader.read_exact(&mut colors)?;
+
+        let stack = &mut stacks[square.0 as usize];
+        for i in 0..stones_below {
+            if colors[i as usize / 8] & (1 << (i % 8)) != 0 {
+                stack.push(Piece::WhiteFlat);
+            } else {
+                stack.push(Piece::BlackFlat);
+            }
+        }
+        stack.push(top_stone);
+    }
+
+    Position::from_stacks(stacks, half_moves_played as usize, rules)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Write a move as two bytes.
+///
+/// The lower byte contains the square in the lowest 6 bits, and the direction or role in the upper 2.
+/// For stack movements, the upper byte contains every number of pieces carried during the move, as a bitmap.
+/// This is enough to recover the move, because each step carries fewer pieces than the last.
+/// The upper byte is 0 for placements.
+pub fn write_move<W: Write, const S: usize>(writer: &mut W, mv: &Move) -> io::Result<()> {
+    let bytes = match mv {
+        Move::Place(role, square) => {
+            let role_bits = match role {
+                Role::Flat => 0,
+                Role::Wall => 1,
+                Role::Cap => 2,
+            };
+            [square.0 | role_bits << 6, 0]
+        }
+        Move::Move(square, direction, stack_movement) => {
+            let direction_bits = match direction {
+                Direction::North => 0,
+                Direction::West => 1,
+                Direction::East => 2,
+                Direction::South => 3,
+            };
+            let mut pieces_carried = 0;
+            for Movement { pieces_to_take } in stack_movement.into_iter() {
+                pieces_carried |= 1 << (pieces_to_take - 1);
+            }
+            [square.0 | direction_bits << 6, pieces_carried]
+        }
+    };
+    writer.write_all(&bytes)
+}
+
+/// Read a move written by `write_move`
+pub fn read_move<R: Read, const S: usize>(reader: &mut R) -> io::Result<Move> {
+    let [lower, pieces_carried] = read_bytes(reader)?;
+    let square = Square(lower & 0b11_1111);
+    if square.0 as usize >= S * S {
+        return Err(invalid_data(format!(
+            "Invalid square {} for {}s",
+            square.0, S
+        )));
+    }
+    if pieces_carried == 0 {
+        let role = match lower >> 6 {
+            0 => Role::Flat,
+            1 => Role::Wall,
+            2 => Role::Cap,
+            _ => return Err(invalid_data("Invalid role".to_string())),
+        };
+        Ok(Move::Place(role, square))
+    } else {
+        let direction = match lower >> 6 {
+            0 => Direction::North,
+            1 => Direction::West,
+            2 => Direction::East,
+            _ => Direction::South,
+        };
+        let mut stack_movement = StackMovement::new();
+        for pieces_to_take in (1..=8).rev() {
+            if pieces_carried & (1 << (pieces_to_take - 1)) != 0 {
+                stack_movement.push(Movement { pieces_to_take });
+            }
+        }
+        Ok(Move::Move(square, direction, stack_movement))
+    }
+}
+
+fn bitmap_len<const S: usize>() -> usize {
+    (S * S).div_ceil(8)
+}
+
+fn piece_from_u8(byte: u8) -> io::Result<Piece> {
+    match byte {
+        0 => Ok(Piece::WhiteFlat),
+        1 => Ok(Piece::BlackFlat),
+        2 => Ok(Piece::WhiteWall),
+        3 => Ok(Piece::BlackWall),
+        4 => Ok(Piece::WhiteCap),
+        5 => Ok(Piece::BlackCap),
+        _ => Err(invalid_data(format!("Invalid piece {}", byte))),
+    }
+}
+
+fn read_bytes<R: Read, const N: usize>(reader: &mut R) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
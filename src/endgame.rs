@@ -0,0 +1,152 @@
+//! An exact solver for flat race endgames, where a player is about to run out of pieces.
+//!
+//! The game ends at the latest when a player places their last piece, so when reserves are low,
+//! every line where that player keeps placing is short enough to search to the end.
+//! The search is exact, including roads, and only returns results that are proven.
+//! Since a player can delay the end of the game with stack movements, the solver mostly proves wins for the player who places their last pieces.
+
+use board_game_traits::{Color, GameResult, Position as PositionTrait};
+
+use crate::position::{Move, Position, Role};
+
+/// Positions where a player has at most this many pieces left are considered flat races
+pub const MAX_FLAT_RACE_RESERVES: u8 = 2;
+
+fn pieces_left<const S: usize>(position: &Position<S>, color: Color) -> u16 {
+    match color {
+        Color::White => position.white_reserves_left() as u16 + position.white_caps_left() as u16,
+        Color::Black => position.black_reserves_left() as u16 + position.black_caps_left() as u16,
+    }
+}
+
+/// Whether a player has at most `MAX_FLAT_RACE_RESERVES` pieces left
+pub fn is_flat_race<const S: usize>(position: &Position<S>) -> bool {
+    pieces_left(position, Color::White).min(pieces_left(position, Color::Black))
+        <= MAX_FLAT_RACE_RESERVES as u16
+}
+
+/// The number of plies until a player has placed all their pieces.
+/// The solver searches this deep, so every game result it proves is reached within this many plies.
+/// Only meaningful for positions where the game is not over.
+pub fn flat_race_plies<const S: usize>(position: &Position<S>) -> u16 {
+    let side_to_move = position.side_to_move();
+    (2 * pieces_left(position, side_to_move))
+        .saturating_sub(1)
+        .min(2 * pieces_left(position, !side_to_move))
+}
+
+/// Search a flat race to the end, making at most `max_nodes` moves on the board.
+/// Returns the result of the game with best play if it can be proven, or `None` if it could not be proven,
+/// or if the position is not a flat race.
+pub fn solve_flat_race<const S: usize>(
+    position: &Position<S>,
+    max_nodes: u64,
+) -> Option<GameResult> {
+    if let Some(game_result) = position.game_result() {
+        return Some(game_result);
+    }
+    if !is_flat_race(position) {
+        return None;
+    }
+    let side_to_move = position.side_to_move();
+    let max_plies = flat_race_plies(position);
+
+    let mut solver = FlatRaceSolver {
+        position: position.clone(),
+        nodes: 0,
+        max_nodes,
+    };
+
+    let (win, loss, draw) = match side_to_move {
+        Color::White => (GameResult::WhiteWin, GameResult::BlackWin, GameResult::Draw),
+        Color::Black => (GameResult::BlackWin, GameResult::WhiteWin, GameResult::Draw),
+    };
+    match solver.can_reach(Value::Win, max_plies) {
+        Some(true) => Some(win),
+        Some(false) => match solver.can_reach(Value::Draw, max_plies)? {
+            true => Some(draw),
+            false => Some(loss),
+        },
+        None => match solver.can_reach(Value::Draw, max_plies) {
+            Some(false) => Some(loss),
+            _ => None,
+        },
+    }
+}
+
+/// The result of the game, from the side to move's perspective
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+enum Value {
+    Loss,
+    Draw,
+    Win,
+}
+
+impl Value {
+    /// The lowest value the opponent must reach to keep us below `self`
+    fn opponent_threshold(self) -> Value {
+        match self {
+            Value::Loss => Value::Win, // Never used, every value is at least a loss
+            Value::Draw => Value::Win,
+            Value::Win => Value::Draw,
+        }
+    }
+}
+
+struct FlatRaceSolver<const S: usize> {
+    position: Position<S>,
+    nodes: u64,
+    max_nodes: u64,
+}
+
+impl<const S: usize> FlatRaceSolver<S> {
+    /// Whether the side to move can force the game to end with at least `threshold`, within `plies` plies.
+    /// Returns `None` if neither side can prove it.
+    fn can_reach(&mut self, threshold: Value, plies: u16) -> Option<bool> {
+        if let Some(game_result) = self.position.game_result() {
+            let value = match (game_result, self.position.side_to_move()) {
+                (GameResult::Draw, _) => Value::Draw,
+                (GameResult::WhiteWin, Color::White) | (GameResult::BlackWin, Color::Black) => {
+                    Value::Win
+                }
+                (GameResult::WhiteWin, Color::Black) | (GameResult::BlackWin, Color::White) => {
+                    Value::Loss
+                }
+            };
+            return Some(value >= threshold);
+        }
+        if threshold == Value::Loss {
+            return Some(true);
+        }
+        if plies == 0 || self.nodes >= self.max_nodes {
+            return None;
+        }
+
+        let mut moves = vec![];
+        self.position.generate_moves(&mut moves);
+        // Placing flats ends the game quickest, so try those first
+        moves.sort_by_key(|mv| match mv {
+            Move::Place(Role::Flat, _) => 0,
+            Move::Place(_, _) => 1,
+            Move::Move(_, _, _) => 2,
+        });
+
+        let mut all_refuted = true;
+        for mv in moves {
+            self.nodes += 1;
+            let reverse_move = self.position.do_move(mv);
+            let opponent_result = self.can_reach(threshold.opponent_threshold(), plies - 1);
+            self.position.reverse_move(reverse_move);
+            match opponent_result {
+                Some(false) => return Some(true),
+                Some(true) => (),
+                None => all_refuted = false,
+            }
+        }
+        if all_refuted {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
@@ -1,5 +1,8 @@
 use crate::position::num_square_symmetries;
 
+pub const NUM_VALUE_FEATURES_3S: usize = 61;
+pub const NUM_POLICY_FEATURES_3S: usize = 120;
+
 pub const NUM_VALUE_FEATURES_4S: usize = 64;
 pub const NUM_POLICY_FEATURES_4S: usize = 130;
 
@@ -9,6 +12,12 @@ pub const NUM_POLICY_FEATURES_5S: usize = 149;
 pub const NUM_VALUE_FEATURES_6S: usize = 85;
 pub const NUM_POLICY_FEATURES_6S: usize = 159;
 
+pub const NUM_VALUE_FEATURES_7S: usize = 108;
+pub const NUM_POLICY_FEATURES_7S: usize = 181;
+
+pub const NUM_VALUE_FEATURES_8S: usize = 111;
+pub const NUM_POLICY_FEATURES_8S: usize = 191;
+
 #[derive(Debug)]
 pub struct ValueFeatures<'a> {
     pub flat_psqt: &'a mut [f32],
@@ -241,18 +250,24 @@ impl<'a> PolicyFeatures<'a> {
 
 pub fn num_value_features<const S: usize>() -> usize {
     match S {
+        3 => NUM_VALUE_FEATURES_3S,
         4 => NUM_VALUE_FEATURES_4S,
         5 => NUM_VALUE_FEATURES_5S,
         6 => NUM_VALUE_FEATURES_6S,
+        7 => NUM_VALUE_FEATURES_7S,
+        8 => NUM_VALUE_FEATURES_8S,
         _ => unimplemented!(),
     }
 }
 
 pub fn num_policy_features<const S: usize>() -> usize {
     match S {
+        3 => NUM_POLICY_FEATURES_3S,
         4 => NUM_POLICY_FEATURES_4S,
         5 => NUM_POLICY_FEATURES_5S,
         6 => NUM_POLICY_FEATURES_6S,
+        7 => NUM_POLICY_FEATURES_7S,
+        8 => NUM_POLICY_FEATURES_8S,
         _ => unimplemented!(),
     }
 }
@@ -948,3 +963,803 @@ pub const POLICY_PARAMS_6S: [f32; NUM_POLICY_FEATURES_6S] = [
     1.185266,
     3.5835598,
 ];
+
+// The 3s parameters have not been tuned yet.
+// They are extrapolated from the 4s parameters, mapping each feature to the closest 4s feature.
+#[allow(clippy::unreadable_literal)]
+pub const VALUE_PARAMS_3S: [f32; NUM_VALUE_FEATURES_3S] = [
+    0.54923415,
+    0.7282322,
+    1.0255456,
+    1.583941,
+    2.0131814,
+    2.5839906,
+    0.002932434,
+    -0.0025715088,
+    -0.00047107227,
+    0.94568884,
+    1.0339974,
+    1.2408891,
+    0.59685516,
+    0.7428497,
+    0.9859054,
+    1.027361,
+    1.5435753,
+    0.009879224,
+    -0.0059808395,
+    0.43253043,
+    0.21758828,
+    0.005999675,
+    -0.0073812627,
+    -0.67089164,
+    -0.26996073,
+    -0.008598236,
+    0.0018530292,
+    -0.678351,
+    -0.71593153,
+    0.005024814,
+    0.0038007405,
+    1.7668045,
+    1.7301219,
+    2.287093,
+    0.6275563,
+    0.26913106,
+    1.4216075,
+    -0.17621191,
+    0.09113373,
+    0.07649631,
+    0.34329876,
+    0.02262616,
+    0.082917236,
+    -0.058202233,
+    -0.0047925757,
+    -0.005560641,
+    0.01647186,
+    -0.25089246,
+    -0.0082427785,
+    1.1404806,
+    -1.4436735,
+    -0.7150899,
+    0.06830657,
+    -1.1160055,
+    -0.808328,
+    0.33283132,
+    1.5984336,
+    -0.017376488,
+    0.0060048904,
+    0.07591783,
+    0.0022945618,
+];
+
+#[allow(clippy::unreadable_literal)]
+pub const POLICY_PARAMS_3S: [f32; NUM_POLICY_FEATURES_3S] = [
+    -3.9657896,
+    1.4110678,
+    1.0998982,
+    -3.1612859,
+    -3.3797283,
+    0.22887644,
+    0.46740365,
+    -0.25038686,
+    0.19303164,
+    -0.16947459,
+    0.15422852,
+    0.06293144,
+    0.16235097,
+    0.43227813,
+    -0.38303038,
+    -0.504268,
+    0.21411678,
+    0.007571606,
+    0.009635687,
+    -0.0014058612,
+    0.0085868295,
+    -0.27475312,
+    0.46439883,
+    0.020157898,
+    0.14288536,
+    0.055918362,
+    0.0061870757,
+    -0.0077467104,
+    -0.007937893,
+    0.14159878,
+    -0.38050216,
+    0.3239574,
+    -0.7297661,
+    -0.6360258,
+    -0.5853736,
+    -0.0057374667,
+    0.00015021767,
+    0.008557079,
+    0.6104631,
+    -0.5925414,
+    -0.009191279,
+    -1.3853506,
+    0.5065972,
+    -0.00008111913,
+    0.45035902,
+    0.8281232,
+    -0.008808966,
+    0.14468262,
+    -0.81799924,
+    -0.0049592447,
+    0.8167471,
+    1.2574946,
+    0.002150652,
+    0.1963947,
+    -0.7948367,
+    -0.004483435,
+    0.17575556,
+    0.4369178,
+    0.008150065,
+    -0.84050864,
+    -0.34566236,
+    0.0040705632,
+    2.4563792,
+    0.23108627,
+    0.9188401,
+    0.008161059,
+    0.79233116,
+    -3.536681,
+    -1.7433494,
+    0.24274868,
+    1.396689,
+    0.43048444,
+    0.28698713,
+    0.27532858,
+    0.58915645,
+    0.18701936,
+    0.33074096,
+    -0.3476396,
+    0.54176253,
+    0.008410923,
+    0.0043121455,
+    -0.008000903,
+    0.0010137418,
+    -0.006815267,
+    0.0031001903,
+    -0.002478423,
+    -0.002353027,
+    0.35292476,
+    0.07396798,
+    -0.007463541,
+    -0.009312019,
+    0.06629895,
+    -0.21437466,
+    0.0035254955,
+    -1.3564991,
+    -1.2744789,
+    0.008508706,
+    -0.04956134,
+    0.8928778,
+    -1.5557747,
+    0.62927735,
+    -1.0346689,
+    0.24117747,
+    0.5185278,
+    1.4246035,
+    0.761231,
+    -0.0077517964,
+    -1.1132826,
+    0.28384545,
+    -0.0027733874,
+    0.427133,
+    1.0458082,
+    0.001523626,
+    0.120106295,
+    -0.09654278,
+    -0.0018659113,
+    0.7107393,
+    2.2507515,
+    0.70542514,
+    2.967115,
+];
+
+// The 7s and 8s parameters have not been tuned yet.
+// They are extrapolated from the 6s parameters, mapping each feature to the closest 6s feature.
+#[allow(clippy::unreadable_literal)]
+pub const VALUE_PARAMS_7S: [f32; NUM_VALUE_FEATURES_7S] = [
+    0.17866804,
+    0.22226085,
+    0.24060796,
+    0.24060796,
+    0.36128962,
+    0.4073427,
+    0.4073427,
+    0.37085718,
+    0.37085718,
+    0.37085718,
+    0.65220183,
+    0.9070014,
+    0.8156371,
+    0.8156371,
+    1.1173159,
+    1.2254996,
+    1.2254996,
+    1.2302381,
+    1.2302381,
+    1.2302381,
+    -0.66431385,
+    0.047767594,
+    0.0017388997,
+    0.0017388997,
+    0.6871612,
+    0.96802086,
+    0.96802086,
+    1.1508409,
+    1.1508409,
+    1.1508409,
+    0.43042153,
+    0.51830196,
+    0.6240532,
+    0.6240532,
+    0.5827504,
+    0.6646772,
+    0.6646772,
+    0.6686843,
+    0.6686843,
+    0.6686843,
+    0.33338755,
+    0.40768048,
+    0.4190147,
+    0.4190147,
+    0.477453,
+    0.5197238,
+    0.5197238,
+    0.5113779,
+    0.5113779,
+    0.5113779,
+    0.6053766,
+    0.7995895,
+    1.4196115,
+    0.9969324,
+    0.17725955,
+    0.12482327,
+    -0.6727204,
+    0.030516021,
+    -0.47592217,
+    -0.18429834,
+    -0.13334727,
+    -0.11429567,
+    -0.41839454,
+    -0.40297738,
+    -0.6469009,
+    -0.30266562,
+    0.9496712,
+    0.7528593,
+    0.91527015,
+    0.8982577,
+    0.2844179,
+    0.6086833,
+    -0.2899202,
+    -0.22381426,
+    -0.0041540167,
+    0.22707729,
+    0.013562306,
+    0.1654211,
+    0.03609803,
+    0.118607484,
+    0.016971339,
+    0.008165523,
+    -0.116248645,
+    -0.14049052,
+    0.8282361,
+    -0.6251942,
+    -0.47099382,
+    -0.30671546,
+    -0.083461426,
+    0.20484383,
+    0.46950406,
+    0.46950406,
+    -0.97495294,
+    -0.775363,
+    -0.35786533,
+    0.15124361,
+    0.7378639,
+    1.2134849,
+    -0.0026799915,
+    -0.0026799915,
+    0.01891431,
+    0.042185962,
+    0.01598101,
+    0.11857532,
+    0.095538825,
+    0.25705943,
+    0.36397108,
+    0.36397108,
+];
+
+#[allow(clippy::unreadable_literal)]
+pub const POLICY_PARAMS_7S: [f32; NUM_POLICY_FEATURES_7S] = [
+    -3.5961018,
+    1.1715668,
+    0.17949656,
+    -3.1173847,
+    -2.7146156,
+    -0.29636982,
+    0.37646836,
+    -0.40992042,
+    0.13564974,
+    -0.080214545,
+    0.073455766,
+    -0.11587167,
+    0.07807559,
+    -0.070633404,
+    -0.070633404,
+    0.33107528,
+    0.29100162,
+    0.29100162,
+    0.12504591,
+    0.12504591,
+    0.12504591,
+    -0.31672958,
+    -0.28122553,
+    -0.3821588,
+    -0.3821588,
+    0.18652995,
+    0.2494285,
+    0.2494285,
+    0.2706151,
+    0.2706151,
+    0.2706151,
+    -0.7336709,
+    -0.6134914,
+    -1.3403125,
+    -1.3403125,
+    0.15585347,
+    0.7600765,
+    0.7600765,
+    2.3306339,
+    2.3306339,
+    2.3306339,
+    0.0066573303,
+    -0.28623724,
+    -0.097407274,
+    0.23667637,
+    0.62867105,
+    0.7386253,
+    0.7386253,
+    -0.21688832,
+    -0.16700281,
+    -0.08503936,
+    -0.013302018,
+    -0.056717705,
+    -0.024381556,
+    -0.024381556,
+    -0.054422356,
+    -0.3119709,
+    0.24450749,
+    0.4980525,
+    0.8796308,
+    -0.052035157,
+    -0.052035157,
+    0.29533902,
+    -0.09485476,
+    -0.022807823,
+    0.20023687,
+    0.46446759,
+    0.4231539,
+    0.4231539,
+    -0.31820518,
+    -0.37169176,
+    -0.3981396,
+    -0.23861855,
+    0.20347169,
+    0.58293164,
+    0.58293164,
+    -0.87294495,
+    -0.961616,
+    -0.7239369,
+    0.2536004,
+    1.0887674,
+    2.4055388,
+    2.4055388,
+    0.6042376,
+    -0.039670847,
+    0.5383345,
+    0.13897365,
+    -0.22086447,
+    -0.85672134,
+    0.4506342,
+    0.15414315,
+    0.5155593,
+    0.20372114,
+    0.1959227,
+    -0.5279979,
+    1.7749112,
+    0.5194491,
+    1.7603195,
+    -0.11568471,
+    -0.10367626,
+    -1.2129226,
+    0.49326625,
+    0.6533442,
+    1.3472204,
+    -0.08694865,
+    -0.2619005,
+    -0.3611921,
+    2.4824772,
+    -0.03424727,
+    2.1948102,
+    1.5011711,
+    0.6755501,
+    -4.0308566,
+    -2.4326375,
+    0.70404565,
+    1.534783,
+    0.4642022,
+    0.6228668,
+    0.1005706,
+    0.398483,
+    0.30044267,
+    0.19416665,
+    -0.030533506,
+    0.3133174,
+    -0.05576763,
+    0.3029141,
+    -0.10947571,
+    -1.0040523,
+    0.16132209,
+    0.27788267,
+    -0.4264962,
+    -0.0074874256,
+    0.2758454,
+    -0.020382686,
+    0.0054334905,
+    -0.006010456,
+    0.3515595,
+    0.022196317,
+    2.1574738,
+    -0.9567373,
+    -0.5230534,
+    -0.6011168,
+    0.51744986,
+    0.1323464,
+    -0.8579046,
+    -0.15650782,
+    -1.7326092,
+    -0.9643042,
+    0.4065113,
+    -0.029295646,
+    -0.040752478,
+    -0.1207008,
+    -0.1207008,
+    -0.043940593,
+    0.015043762,
+    0.10670062,
+    0.10670062,
+    -0.24279961,
+    0.0037804677,
+    -0.21292888,
+    -0.21292888,
+    -0.1140509,
+    -0.45980576,
+    -0.27085638,
+    -0.27085638,
+    2.1825454,
+    1.5512332,
+    1.9375703,
+    0.051246107,
+    1.354466,
+    1.8797683,
+    0.7818063,
+    1.7069739,
+    1.6903217,
+    0.38359755,
+    0.328086,
+    -0.4395057,
+    0.24705306,
+    3.3318887,
+    1.185266,
+    3.5835598,
+];
+
+#[allow(clippy::unreadable_literal)]
+pub const VALUE_PARAMS_8S: [f32; NUM_VALUE_FEATURES_8S] = [
+    0.17866804,
+    0.22226085,
+    0.24060796,
+    0.24060796,
+    0.36128962,
+    0.4073427,
+    0.4073427,
+    0.37085718,
+    0.37085718,
+    0.37085718,
+    0.65220183,
+    0.9070014,
+    0.8156371,
+    0.8156371,
+    1.1173159,
+    1.2254996,
+    1.2254996,
+    1.2302381,
+    1.2302381,
+    1.2302381,
+    -0.66431385,
+    0.047767594,
+    0.0017388997,
+    0.0017388997,
+    0.6871612,
+    0.96802086,
+    0.96802086,
+    1.1508409,
+    1.1508409,
+    1.1508409,
+    0.43042153,
+    0.51830196,
+    0.6240532,
+    0.6240532,
+    0.5827504,
+    0.6646772,
+    0.6646772,
+    0.6686843,
+    0.6686843,
+    0.6686843,
+    0.33338755,
+    0.40768048,
+    0.4190147,
+    0.4190147,
+    0.477453,
+    0.5197238,
+    0.5197238,
+    0.5113779,
+    0.5113779,
+    0.5113779,
+    0.6053766,
+    0.7995895,
+    1.4196115,
+    0.9969324,
+    0.17725955,
+    0.12482327,
+    -0.6727204,
+    0.030516021,
+    -0.47592217,
+    -0.18429834,
+    -0.13334727,
+    -0.11429567,
+    -0.41839454,
+    -0.40297738,
+    -0.6469009,
+    -0.30266562,
+    0.9496712,
+    0.7528593,
+    0.91527015,
+    0.8982577,
+    0.2844179,
+    0.6086833,
+    -0.2899202,
+    -0.22381426,
+    -0.0041540167,
+    0.22707729,
+    0.013562306,
+    0.1654211,
+    0.03609803,
+    0.118607484,
+    0.016971339,
+    0.008165523,
+    -0.116248645,
+    -0.14049052,
+    0.8282361,
+    -0.6251942,
+    -0.47099382,
+    -0.30671546,
+    -0.083461426,
+    0.20484383,
+    0.46950406,
+    0.46950406,
+    0.46950406,
+    -0.97495294,
+    -0.775363,
+    -0.35786533,
+    0.15124361,
+    0.7378639,
+    1.2134849,
+    -0.0026799915,
+    -0.0026799915,
+    -0.0026799915,
+    0.01891431,
+    0.042185962,
+    0.01598101,
+    0.11857532,
+    0.095538825,
+    0.25705943,
+    0.36397108,
+    0.36397108,
+    0.36397108,
+];
+
+#[allow(clippy::unreadable_literal)]
+pub const POLICY_PARAMS_8S: [f32; NUM_POLICY_FEATURES_8S] = [
+    -3.5961018,
+    1.1715668,
+    0.17949656,
+    -3.1173847,
+    -2.7146156,
+    -0.29636982,
+    0.37646836,
+    -0.40992042,
+    0.13564974,
+    -0.080214545,
+    0.073455766,
+    -0.11587167,
+    0.07807559,
+    -0.070633404,
+    -0.070633404,
+    0.33107528,
+    0.29100162,
+    0.29100162,
+    0.12504591,
+    0.12504591,
+    0.12504591,
+    -0.31672958,
+    -0.28122553,
+    -0.3821588,
+    -0.3821588,
+    0.18652995,
+    0.2494285,
+    0.2494285,
+    0.2706151,
+    0.2706151,
+    0.2706151,
+    -0.7336709,
+    -0.6134914,
+    -1.3403125,
+    -1.3403125,
+    0.15585347,
+    0.7600765,
+    0.7600765,
+    2.3306339,
+    2.3306339,
+    2.3306339,
+    0.0066573303,
+    -0.28623724,
+    -0.097407274,
+    0.23667637,
+    0.62867105,
+    0.7386253,
+    0.7386253,
+    0.7386253,
+    -0.21688832,
+    -0.16700281,
+    -0.08503936,
+    -0.013302018,
+    -0.056717705,
+    -0.024381556,
+    -0.024381556,
+    -0.024381556,
+    -0.054422356,
+    -0.3119709,
+    0.24450749,
+    0.4980525,
+    0.8796308,
+    -0.052035157,
+    -0.052035157,
+    -0.052035157,
+    0.29533902,
+    -0.09485476,
+    -0.022807823,
+    0.20023687,
+    0.46446759,
+    0.4231539,
+    0.4231539,
+    0.4231539,
+    -0.31820518,
+    -0.37169176,
+    -0.3981396,
+    -0.23861855,
+    0.20347169,
+    0.58293164,
+    0.58293164,
+    0.58293164,
+    -0.87294495,
+    -0.961616,
+    -0.7239369,
+    0.2536004,
+    1.0887674,
+    2.4055388,
+    2.4055388,
+    2.4055388,
+    0.6042376,
+    -0.039670847,
+    0.5383345,
+    0.13897365,
+    -0.22086447,
+    -0.85672134,
+    0.4506342,
+    0.15414315,
+    0.5155593,
+    0.20372114,
+    0.1959227,
+    -0.5279979,
+    1.7749112,
+    0.5194491,
+    1.7603195,
+    -0.11568471,
+    -0.10367626,
+    -1.2129226,
+    0.49326625,
+    0.6533442,
+    1.3472204,
+    -0.08694865,
+    -0.2619005,
+    -0.3611921,
+    2.4824772,
+    -0.03424727,
+    2.1948102,
+    1.5011711,
+    0.6755501,
+    -4.0308566,
+    -2.4326375,
+    0.70404565,
+    1.534783,
+    0.4642022,
+    0.6228668,
+    0.1005706,
+    0.398483,
+    0.30044267,
+    0.19416665,
+    -0.030533506,
+    0.3133174,
+    -0.05576763,
+    0.3029141,
+    -0.10947571,
+    -1.0040523,
+    0.16132209,
+    0.27788267,
+    -0.4264962,
+    -0.0074874256,
+    0.2758454,
+    -0.020382686,
+    0.0054334905,
+    -0.006010456,
+    0.3515595,
+    0.022196317,
+    2.1574738,
+    -0.9567373,
+    -0.5230534,
+    -0.6011168,
+    0.51744986,
+    0.1323464,
+    -0.8579046,
+    -0.15650782,
+    -1.7326092,
+    -0.9643042,
+    0.4065113,
+    -0.029295646,
+    -0.040752478,
+    -0.1207008,
+    -0.1207008,
+    -0.1207008,
+    -0.043940593,
+    0.015043762,
+    0.10670062,
+    0.10670062,
+    0.10670062,
+    -0.24279961,
+    0.0037804677,
+    -0.21292888,
+    -0.21292888,
+    -0.21292888,
+    -0.1140509,
+    -0.45980576,
+    -0.27085638,
+    -0.27085638,
+    -0.27085638,
+    2.1825454,
+    1.5512332,
+    1.9375703,
+    0.051246107,
+    1.354466,
+    1.8797683,
+    0.7818063,
+    1.7069739,
+    1.6903217,
+    0.38359755,
+    0.328086,
+    -0.4395057,
+    0.24705306,
+    3.3318887,
+    1.185266,
+    3.5835598,
+];
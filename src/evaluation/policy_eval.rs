@@ -188,26 +188,28 @@ fn features_for_move_colortr<Us: ColorTr, Them: ColorTr, const S: usize>(
     group_data: &GroupData<S>,
 ) {
     // If it's the first move, give every move equal probability
-    if position.half_moves_played() < 2 {
+    if position.half_moves_played() < 2 && position.rules().opening_swap() {
         return;
     }
 
     match mv {
         Move::Place(role, square) => {
-            let our_flat_lead =
-                Us::flats(group_data).count() as i8 - Them::flats(group_data).count() as i8;
-
-            let our_flat_lead_after_move = match *role {
-                Flat => our_flat_lead + 1,
-                Wall => our_flat_lead,
-                Cap => our_flat_lead,
+            // Flat lead is counted in half flats, to account for komi
+            let our_half_flat_lead = 2
+                * (Us::flats(group_data).count() as i8 - Them::flats(group_data).count() as i8)
+                - Us::color().multiplier() as i8 * position.komi().half_komi();
+
+            let our_half_flat_lead_after_move = match *role {
+                Flat => our_half_flat_lead + 2,
+                Wall => our_half_flat_lead,
+                Cap => our_half_flat_lead,
             };
 
             // Apply special bonuses if the game ends on this move
             if Us::stones_left(position) == 1 && Us::caps_left(position) == 0
                 || group_data.all_pieces().count() as usize == S * S - 1
             {
-                match our_flat_lead_after_move {
+                match our_half_flat_lead_after_move {
                     n if n < 0 => policy_features.place_to_loss[0] = 1.0,
                     0 => policy_features.place_to_draw[0] = 1.0,
                     n if n > 0 => policy_features.place_to_win[0] = 1.0,
@@ -218,22 +220,22 @@ fn features_for_move_colortr<Us: ColorTr, Them: ColorTr, const S: usize>(
             else if Them::stones_left(position) == 1 && Them::caps_left(position) == 0
                 || group_data.all_pieces().count() as usize == S * S - 2
             {
-                match our_flat_lead_after_move {
-                    n if n < 1 => policy_features.place_to_allow_opponent_to_end[0] = 1.0,
-                    1 => policy_features.place_to_allow_opponent_to_end[1] = 1.0,
-                    n if n > 1 => policy_features.place_to_allow_opponent_to_end[2] = 1.0,
+                match our_half_flat_lead_after_move {
+                    n if n < 2 => policy_features.place_to_allow_opponent_to_end[0] = 1.0,
+                    2 => policy_features.place_to_allow_opponent_to_end[1] = 1.0,
+                    n if n > 2 => policy_features.place_to_allow_opponent_to_end[2] = 1.0,
                     _ => unreachable!(),
                 }
             } else if Us::stones_left(position) == 2 && Us::caps_left(position) == 0 {
                 policy_features.two_flats_left[0] = 1.0;
-                policy_features.two_flats_left[1] = our_flat_lead_after_move as f32;
+                policy_features.two_flats_left[1] = our_half_flat_lead_after_move as f32 / 2.0;
             } else if Us::stones_left(position) == 3 && Us::caps_left(position) == 0 {
                 policy_features.three_flats_left[0] = 1.0;
-                policy_features.three_flats_left[1] = our_flat_lead_after_move as f32;
+                policy_features.three_flats_left[1] = our_half_flat_lead_after_move as f32 / 2.0;
             }
 
             let their_open_critical_squares =
-                Them::critical_squares(&*group_data) & (!group_data.all_pieces());
+                Them::critical_squares(group_data) & (!group_data.all_pieces());
 
             // Apply PSQT
             match role {
@@ -329,7 +331,7 @@ fn features_for_move_colortr<Us: ColorTr, Them: ColorTr, const S: usize>(
             }
 
             if *role == Flat || *role == Cap {
-                if Us::is_critical_square(&*group_data, *square) {
+                if Us::is_critical_square(group_data, *square) {
                     policy_features.place_our_critical_square[0] += 1.0;
                 } else if !their_open_critical_squares.is_empty() {
                     if their_open_critical_squares == BitBoard::empty().set(square.0) {
@@ -394,7 +396,7 @@ fn features_for_move_colortr<Us: ColorTr, Them: ColorTr, const S: usize>(
                     }
                 }
             } else if *role == Cap {
-                if Us::is_critical_square(&*group_data, *square) {
+                if Us::is_critical_square(group_data, *square) {
                     policy_features.place_our_critical_square[0] += 1.0;
                 } else if !their_open_critical_squares.is_empty() {
                     if their_open_critical_squares == BitBoard::empty().set(square.0) {
@@ -535,12 +537,12 @@ fn features_for_move_colortr<Us: ColorTr, Them: ColorTr, const S: usize>(
             {
                 if Us::piece_is_ours(piece) {
                     our_pieces += 1;
-                    if Us::is_critical_square(&*group_data, destination_square)
+                    if Us::is_critical_square(group_data, destination_square)
                         && piece.is_road_piece()
                     {
                         captures_our_critical_square = Some(destination_square);
                     }
-                    if Them::is_critical_square(&*group_data, destination_square) {
+                    if Them::is_critical_square(group_data, destination_square) {
                         captures_their_critical_square = Some(destination_square);
                     }
                     if let Some(MovementSynopsis {
@@ -702,7 +704,7 @@ fn features_for_move_colortr<Us: ColorTr, Them: ColorTr, const S: usize>(
             }
 
             let their_open_critical_squares =
-                Them::critical_squares(&*group_data) & (!group_data.all_pieces());
+                Them::critical_squares(group_data) & (!group_data.all_pieces());
 
             if !their_open_critical_squares.is_empty() {
                 if their_pieces_captured == 0 && captures_their_critical_square.is_none() {
@@ -12,27 +12,24 @@ pub(crate) fn static_eval_game_phase<const S: usize>(
     group_data: &GroupData<S>,
     value_features: &mut ValueFeatures,
 ) {
-    let mut white_flat_count = 0;
-    let mut black_flat_count = 0;
+    for piece in [
+        WhiteFlat, BlackFlat, WhiteWall, BlackWall, WhiteCap, BlackCap,
+    ] {
+        let color_factor = piece.color().multiplier() as f32;
+        let psqt = match piece.role() {
+            Flat => &mut value_features.flat_psqt,
+            Wall => &mut value_features.wall_psqt,
+            Cap => &mut value_features.cap_psqt,
+        };
+        for square in position.top_stones(piece) {
+            psqt[square_symmetries::<S>()[square.0 as usize]] += color_factor;
+        }
+    }
 
     for square in squares_iterator::<S>() {
         let stack = &position[square];
         if let Some(piece) = position[square].top_stone() {
             let i = square.0 as usize;
-            match piece {
-                WhiteFlat => {
-                    value_features.flat_psqt[square_symmetries::<S>()[i]] += 1.0;
-                    white_flat_count += 1;
-                }
-                BlackFlat => {
-                    value_features.flat_psqt[square_symmetries::<S>()[i]] -= 1.0;
-                    black_flat_count += 1;
-                }
-                WhiteWall => value_features.wall_psqt[square_symmetries::<S>()[i]] += 1.0,
-                BlackWall => value_features.wall_psqt[square_symmetries::<S>()[i]] -= 1.0,
-                WhiteCap => value_features.cap_psqt[square_symmetries::<S>()[i]] += 1.0,
-                BlackCap => value_features.cap_psqt[square_symmetries::<S>()[i]] -= 1.0,
-            }
             if stack.height > 1 {
                 let controlling_player = piece.color();
                 let color_factor = piece.color().multiplier() as f32;
@@ -82,8 +79,10 @@ pub(crate) fn static_eval_game_phase<const S: usize>(
         }
     }
 
-    // Give the side to move a bonus/malus depending on flatstone lead
-    let white_flatstone_lead = white_flat_count - black_flat_count;
+    // Give the side to move a bonus/malus depending on flatstone lead, including komi
+    let white_flatstone_lead = position.top_stones(WhiteFlat).count() as f32
+        - position.top_stones(BlackFlat).count() as f32
+        - position.komi().as_f32();
 
     // Bonus/malus depending on the number of groups each side has
     let mut seen_groups = vec![false; S * S + 1]; // TODO: Can be an array with full const-generics
@@ -101,14 +100,10 @@ pub(crate) fn static_eval_game_phase<const S: usize>(
         })
         .sum::<isize>() as f32;
 
-    let opening_scale_factor = f32::min(
-        f32::max((24.0 - position.half_moves_played() as f32) / 12.0, 0.0),
-        1.0,
-    );
-    let endgame_scale_factor = f32::min(
-        f32::max((position.half_moves_played() as f32 - 24.0) / 24.0, 0.0),
-        1.0,
-    );
+    let opening_scale_factor =
+        ((24.0 - position.half_moves_played() as f32) / 12.0).clamp(0.0, 1.0);
+    let endgame_scale_factor =
+        ((position.half_moves_played() as f32 - 24.0) / 24.0).clamp(0.0, 1.0);
     let middlegame_scale_factor = 1.0 - opening_scale_factor - endgame_scale_factor;
 
     debug_assert!(middlegame_scale_factor <= 1.0);
@@ -116,17 +111,17 @@ pub(crate) fn static_eval_game_phase<const S: usize>(
 
     value_features.side_to_move[0] =
         position.side_to_move().multiplier() as f32 * opening_scale_factor;
-    value_features.flatstone_lead[0] = white_flatstone_lead as f32 * opening_scale_factor;
+    value_features.flatstone_lead[0] = white_flatstone_lead * opening_scale_factor;
     value_features.i_number_of_groups[0] = number_of_groups * opening_scale_factor;
 
     value_features.side_to_move[1] =
         position.side_to_move().multiplier() as f32 * middlegame_scale_factor;
-    value_features.flatstone_lead[1] = white_flatstone_lead as f32 * middlegame_scale_factor;
+    value_features.flatstone_lead[1] = white_flatstone_lead * middlegame_scale_factor;
     value_features.i_number_of_groups[1] = number_of_groups * middlegame_scale_factor;
 
     value_features.side_to_move[2] =
         position.side_to_move().multiplier() as f32 * endgame_scale_factor;
-    value_features.flatstone_lead[2] = white_flatstone_lead as f32 * endgame_scale_factor;
+    value_features.flatstone_lead[2] = white_flatstone_lead * endgame_scale_factor;
     value_features.i_number_of_groups[2] = number_of_groups * endgame_scale_factor;
 
     for critical_square in group_data.critical_squares(Color::White) {
@@ -34,12 +34,16 @@ pub use search::mcts;
 
 #[cfg(any(feature = "aws-lambda-runtime", feature = "aws-lambda-client"))]
 pub mod aws;
+pub mod dataset;
+pub mod endgame;
 pub mod minmax;
 pub mod move_gen;
+pub mod opening_book;
 pub mod position;
 pub mod search;
 #[cfg(test)]
 mod tests;
+pub mod tinue;
 #[cfg(feature = "constant-tuning")]
 pub mod tune;
 
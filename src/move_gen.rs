@@ -1,15 +1,15 @@
+use board_game_traits::{Color, Position as PositionTrait};
+
 use crate::position::color_trait::ColorTr;
-use crate::position::Move;
-use crate::position::{
-    squares_iterator, Direction, Movement, Piece, Position, Role::*, Square, StackMovement,
-};
+use crate::position::{Direction, Movement, Piece, Position, Role::*, Square, StackMovement};
+use crate::position::{IllegalMoveError, Move};
 
 impl<const S: usize> Position<S> {
     pub(crate) fn generate_moves_colortr<Us: ColorTr, Them: ColorTr>(
         &self,
         moves: &mut Vec<<Position<S> as board_game_traits::Position>::Move>,
     ) {
-        for square in squares_iterator::<S>() {
+        for square in self.empty_squares() | self.pieces_of_color(Us::color()) {
             match self[square].top_stone() {
                 None => {
                     if Us::stones_left(self) > 0 {
@@ -28,7 +28,7 @@ impl<const S: usize> Position<S> {
                                 direction,
                                 square,
                                 square,
-                                self[square].len() as u8,
+                                self[square].len(),
                                 StackMovement::new(),
                                 &mut movements,
                             );
@@ -37,7 +37,7 @@ impl<const S: usize> Position<S> {
                                 direction,
                                 square,
                                 square,
-                                self[square].len() as u8,
+                                self[square].len(),
                                 StackMovement::new(),
                                 &mut movements,
                             );
@@ -133,3 +133,137 @@ impl<const S: usize> Position<S> {
         }
     }
 }
+
+impl<const S: usize> Position<S> {
+    /// Check whether `mv` is legal in the position, without generating all legal moves.
+    /// Agrees with `generate_moves`, so moves are legal even if the game is already over.
+    pub(crate) fn check_legality(&self, mv: &Move) -> Result<(), IllegalMoveError> {
+        match mv {
+            Move::Place(role, square) => {
+                if square.0 as usize >= S * S {
+                    return Err(IllegalMoveError::SquareOutsideBoard);
+                }
+                let is_opening_move = self.half_moves_played() < 2 && self.rules().opening_swap();
+                if is_opening_move && *role != Flat {
+                    return Err(IllegalMoveError::OpeningMoveNotFlat);
+                }
+                if !self[*square].is_empty() {
+                    return Err(IllegalMoveError::OccupiedSquare);
+                }
+                let color_to_place = if is_opening_move {
+                    !self.side_to_move()
+                } else {
+                    self.side_to_move()
+                };
+                let (stones_left, caps_left) = match color_to_place {
+                    Color::White => (self.white_reserves_left(), self.white_caps_left()),
+                    Color::Black => (self.black_reserves_left(), self.black_caps_left()),
+                };
+                match role {
+                    Flat | Wall if stones_left == 0 => Err(IllegalMoveError::NoStonesLeft),
+                    Cap if caps_left == 0 => Err(IllegalMoveError::NoCapstonesLeft),
+                    _ => Ok(()),
+                }
+            }
+            Move::Move(square, direction, stack_movement) => {
+                if square.0 as usize >= S * S {
+                    return Err(IllegalMoveError::SquareOutsideBoard);
+                }
+                if self.half_moves_played() < 2 && self.rules().opening_swap() {
+                    return Err(IllegalMoveError::OpeningMoveNotFlat);
+                }
+                let stack = &self[*square];
+                let moving_piece = stack.top_stone().ok_or(IllegalMoveError::EmptySquare)?;
+                if moving_piece.color() != self.side_to_move() {
+                    return Err(IllegalMoveError::OpponentsStack);
+                }
+                if stack_movement.is_empty() {
+                    return Err(IllegalMoveError::EmptyStackMovement);
+                }
+
+                let mut pieces_carried = stack.len();
+                let mut to = *square;
+                for (i, Movement { pieces_to_take }) in stack_movement.into_iter().enumerate() {
+                    if i == 0 {
+                        if pieces_to_take > stack.len() {
+                            return Err(IllegalMoveError::NotEnoughPieces {
+                                taken: pieces_to_take,
+                                stack_height: stack.len(),
+                            });
+                        }
+                        if pieces_to_take as usize > S {
+                            return Err(IllegalMoveError::CarryLimitExceeded {
+                                taken: pieces_to_take,
+                                limit: S as u8,
+                            });
+                        }
+                    }
+                    if pieces_to_take == 0 || (i > 0 && pieces_to_take >= pieces_carried) {
+                        return Err(IllegalMoveError::MustDropPiece);
+                    }
+                    to = to
+                        .go_direction::<S>(*direction)
+                        .ok_or(IllegalMoveError::SpreadOffBoard)?;
+                    match self[to].top_stone().map(Piece::role) {
+                        Some(Cap) => return Err(IllegalMoveError::BlockedByCapstone),
+                        Some(Wall)
+                            if moving_piece.role() != Cap
+                                || pieces_to_take != 1
+                                || i != stack_movement.len() - 1 =>
+                        {
+                            return Err(IllegalMoveError::BlockedByWall)
+                        }
+                        _ => (),
+                    }
+                    pieces_carried = pieces_to_take;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Count the number of leaf nodes in the game tree of `position` to the given depth.
+/// Finished games count as a single leaf node, regardless of the remaining depth.
+/// Used to verify the move generator against known node counts.
+pub fn perft<const S: usize>(position: &mut Position<S>, depth: u16) -> u64 {
+    if depth == 0 || position.game_result().is_some() {
+        1
+    } else {
+        let mut moves = vec![];
+        position.generate_moves(&mut moves);
+        moves
+            .into_iter()
+            .map(|mv| perft_after_move(position, mv, depth))
+            .sum()
+    }
+}
+
+/// Perft for each legal move in `position`, in move generation order.
+/// The node counts sum to `perft(position, depth)`, which helps finding the move where a move generator goes wrong.
+pub fn divide<const S: usize>(position: &mut Position<S>, depth: u16) -> Vec<(Move, u64)> {
+    if depth == 0 || position.game_result().is_some() {
+        return vec![];
+    }
+    let mut moves = vec![];
+    position.generate_moves(&mut moves);
+    moves
+        .into_iter()
+        .map(|mv| (mv.clone(), perft_after_move(position, mv, depth)))
+        .collect()
+}
+
+fn perft_after_move<const S: usize>(position: &mut Position<S>, mv: Move, depth: u16) -> u64 {
+    #[cfg(debug_assertions)]
+    let old_position = position.clone();
+    let reverse_move = position.do_move(mv.clone());
+    let num_moves = perft(position, depth - 1);
+    position.reverse_move(reverse_move);
+    #[cfg(debug_assertions)]
+    assert_eq!(
+        *position, old_position,
+        "Failed to restore old board after {:?} on\n{:?}",
+        mv, old_position
+    );
+    num_moves
+}
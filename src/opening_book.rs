@@ -0,0 +1,310 @@
+//! An opening book, for picking moves in known positions without searching.
+//!
+//! Positions are keyed by `Position::canonical_hash`, so that a move stored for one position is also found in all its rotations and reflections.
+//! Komi is not part of the key, so a book should only be used with the komi it was built for.
+//!
+//! The book can be stored as a text file. The first line is `size <n>`, followed by one line for each position:
+//! its TPS, and then each move with its weight and score, separated by semicolons:
+//!
+//! ```text
+//! size 5
+//! x5/x5/x5/x5/x5 1 1; a1 120 0.5; a2 12 0.45
+//! ```
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use board_game_traits::{Color, GameResult, Position as PositionTrait};
+use pgn_traits::PgnPosition;
+use rand::Rng;
+
+use crate::position::{with_position, AnyMove, AnyPosition, Move, Position, SUPPORTED_SIZES};
+use crate::ptn::{AnyGame, Game};
+use crate::search::{AnyTree, MonteCarloTree, Score};
+
+/// A move stored in the book
+#[derive(Clone, PartialEq, Debug)]
+pub struct BookMove {
+    pub mv: Move,
+    /// How often the move should be played, relative to the other moves in the position
+    pub weight: u64,
+    /// Winning probability for the side to move, after playing the move
+    pub score: Score,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+struct BookPosition {
+    /// TPS of the canonical form of the position
+    tps: String,
+    /// Moves in the canonical form of the position
+    moves: Vec<BookMove>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct OpeningBook {
+    size: usize,
+    positions: HashMap<u64, BookPosition>,
+}
+
+impl OpeningBook {
+    pub fn new(size: usize) -> Self {
+        OpeningBook {
+            size,
+            positions: HashMap::new(),
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Number of positions in the book
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Add a move to the book. If the move is already in the book, the weights are added together,
+    /// and the scores are averaged by weight.
+    /// Panics if the position has a different size than the book.
+    pub fn add_move<const S: usize>(
+        &mut self,
+        position: &Position<S>,
+        mv: &Move,
+        weight: u64,
+        score: Score,
+    ) {
+        assert_eq!(
+            S, self.size,
+            "Cannot add {}s position to {}s book",
+            S, self.size
+        );
+        let (canonical_position, _) = position.canonical();
+        // If the position is symmetric, several moves are equivalent. Store them as the same move
+        let canonical_move = position
+            .canonical_transforms()
+            .into_iter()
+            .map(|transform| transform.apply_move::<S>(mv))
+            .min_by_key(|canonical_move| canonical_move.to_string::<S>())
+            .unwrap();
+        let book_position = self
+            .positions
+            .entry(canonical_position.zobrist_hash())
+            .or_insert_with(|| BookPosition {
+                tps: canonical_position.to_fen(),
+                moves: vec![],
+            });
+        match book_position
+            .moves
+            .iter_mut()
+            .find(|book_move| book_move.mv == canonical_move)
+        {
+            Some(book_move) if book_move.weight + weight > 0 => {
+                book_move.score = (book_move.score * book_move.weight as Score
+                    + score * weight as Score)
+                    / (book_move.weight + weight) as Score;
+                book_move.weight += weight;
+            }
+            Some(_) => (),
+            None => book_position.moves.push(BookMove {
+                mv: canonical_move,
+                weight,
+                score,
+            }),
+        }
+    }
+
+    /// Add the first `max_plies` moves of a game, each with weight 1 and scored by the game's result.
+    /// Games without a result are skipped.
+    pub fn add_game<const S: usize>(&mut self, game: &Game<Position<S>>, max_plies: usize) {
+        let game_result = match game.game_result {
+            Some(game_result) => game_result,
+            None => return,
+        };
+        let mut position = game.start_position.clone();
+        for ptn_move in game.moves.iter().take(max_plies) {
+            let score = match (game_result, position.side_to_move()) {
+                (GameResult::WhiteWin, Color::White) | (GameResult::BlackWin, Color::Black) => 1.0,
+                (GameResult::WhiteWin, Color::Black) | (GameResult::BlackWin, Color::White) => 0.0,
+                (GameResult::Draw, _) => 0.5,
+            };
+            self.add_move(&position, &ptn_move.mv, 1, score);
+            position.do_move(ptn_move.mv.clone());
+        }
+    }
+
+    /// Add every move from the root of a search tree with at least `min_visits` visits, weighted by visits.
+    /// Panics if no search iterations have been run.
+    pub fn add_analysis<const S: usize>(
+        &mut self,
+        position: &Position<S>,
+        tree: &MonteCarloTree<S>,
+        min_visits: u64,
+    ) {
+        for (mv, visits, score) in tree.move_visits_and_scores() {
+            if visits >= min_visits {
+                self.add_move(position, &mv, visits, score);
+            }
+        }
+    }
+
+    /// Add a game whose board size is only known at runtime. See `add_game`.
+    pub fn add_any_game(&mut self, game: &AnyGame, max_plies: usize) {
+        with_position!(&game.start_position, start_position, S => self.add_game(
+            &Game {
+                start_position: start_position.clone(),
+                moves: game.moves.clone(),
+                game_result: game.game_result,
+                tags: vec![],
+            },
+            max_plies,
+        ))
+    }
+
+    /// Add the root moves of a search tree whose board size is only known at runtime. See `add_analysis`.
+    pub fn add_any_analysis(&mut self, position: &AnyPosition, tree: &AnyTree, min_visits: u64) {
+        for (mv, visits, score) in tree.move_visits_and_scores() {
+            if visits >= min_visits {
+                with_position!(position, position, S => {
+                    assert_eq!(mv.size(), S, "Cannot add {}s move to {}s position", mv.size(), S);
+                    self.add_move(position, mv.mv(), visits, score)
+                });
+            }
+        }
+    }
+
+    /// The book moves for a position. Returns an empty vector if the position is not in the book,
+    /// or has a different size than the book.
+    pub fn moves<const S: usize>(&self, position: &Position<S>) -> Vec<BookMove> {
+        if S != self.size {
+            return vec![];
+        }
+        let (_, transform) = position.canonical();
+        let inverse = transform.inverse();
+        self.positions
+            .get(&position.canonical_hash())
+            .map(|book_position| {
+                book_position
+                    .moves
+                    .iter()
+                    .map(|book_move| BookMove {
+                        mv: inverse.apply_move::<S>(&book_move.mv),
+                        ..book_move.clone()
+                    })
+                    // Guard against hash collisions
+                    .filter(|book_move| position.is_legal(&book_move.mv))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Pick a random book move, with probability proportional to its weight, along with its score.
+    pub fn choose_move<const S: usize, R: Rng>(
+        &self,
+        position: &Position<S>,
+        rng: &mut R,
+    ) -> Option<(Move, Score)> {
+        let moves = self.moves(position);
+        let total_weight: u64 = moves.iter().map(|book_move| book_move.weight).sum();
+        if total_weight == 0 {
+            return None;
+        }
+        let mut choice = rng.gen_range(0..total_weight);
+        for book_move in moves {
+            if choice < book_move.weight {
+                return Some((book_move.mv, book_move.score));
+            }
+            choice -= book_move.weight;
+        }
+        unreachable!()
+    }
+
+    /// Pick a random book move for a position whose size is only known at runtime. See `choose_move`.
+    pub fn choose_any_move<R: Rng>(
+        &self,
+        position: &AnyPosition,
+        rng: &mut R,
+    ) -> Option<(AnyMove, Score)> {
+        with_position!(position, position, S => self
+            .choose_move(position, rng)
+            .map(|(mv, score)| (AnyMove::from_move::<S>(mv), score)))
+    }
+
+    pub fn read<R: BufRead>(reader: R) -> io::Result<Self> {
+        let invalid_data = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        let mut lines = reader.lines();
+        let header = lines.next().transpose()?.unwrap_or_default();
+        let size = match header.split_whitespace().collect::<Vec<_>>().as_slice() {
+            ["size", size] => match size.parse() {
+                Ok(size) if SUPPORTED_SIZES.contains(&size) => size,
+                _ => return Err(invalid_data(format!("Invalid size in \"{}\"", header))),
+            },
+            _ => {
+                return Err(invalid_data(format!(
+                    "Expected \"size <n>\" as the first line, got \"{}\"",
+                    header
+                )))
+            }
+        };
+        let mut book = OpeningBook::new(size);
+
+        for line in lines {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let mut fields = line.split(';');
+            let tps = fields.next().unwrap().trim();
+            let any_position = AnyPosition::from_fen(size, tps)
+                .map_err(|err| invalid_data(format!("Invalid TPS \"{}\": {}", tps, err)))?;
+            for field in fields {
+                let parse_error = || invalid_data(format!("Invalid book move \"{}\"", field));
+                let words: Vec<&str> = field.split_whitespace().collect();
+                let (move_string, weight, score) = match words.as_slice() {
+                    [mv, weight, score] => (
+                        *mv,
+                        weight.parse().map_err(|_| parse_error())?,
+                        score.parse().map_err(|_| parse_error())?,
+                    ),
+                    _ => return Err(parse_error()),
+                };
+                with_position!(&any_position, position, S => {
+                    let mv = position
+                        .move_from_san(move_string)
+                        .map_err(|err| invalid_data(err.to_string()))?;
+                    if !position.is_legal(&mv) {
+                        return Err(invalid_data(format!(
+                            "Illegal book move {} in {}",
+                            move_string, tps
+                        )));
+                    }
+                    book.add_move(position, &mv, weight, score);
+                });
+            }
+        }
+        Ok(book)
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "size {}", self.size)?;
+        let mut book_positions: Vec<&BookPosition> = self.positions.values().collect();
+        book_positions.sort_by(|a, b| a.tps.cmp(&b.tps));
+        for book_position in book_positions {
+            write!(writer, "{}", book_position.tps)?;
+            for book_move in book_position.moves.iter() {
+                let mv = AnyMove::new(self.size, book_move.mv.clone()).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("Unsupported board size {}", self.size),
+                    )
+                })?;
+                write!(writer, "; {} {} {}", mv, book_move.weight, book_move.score)?;
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+}
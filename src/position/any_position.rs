@@ -0,0 +1,484 @@
+use std::fmt;
+use std::time;
+
+use board_game_traits::{Color, EvalPosition, GameResult, Position as PositionTrait};
+use pgn_traits::PgnPosition;
+
+use crate::evaluation::parameters;
+use crate::move_gen;
+use crate::position::{
+    GameOutcome, IllegalMoveError, Komi, Move, Position, ReverseMove, RuleSet, Square,
+};
+use crate::search::{self, AnyMctsSetting, Score};
+use crate::tinue::{self, TinueResult};
+
+/// Board sizes that can be used with `AnyPosition`.
+pub const SUPPORTED_SIZES: [usize; 6] = [3, 4, 5, 6, 7, 8];
+
+/// Match on an `AnyPosition`, binding the inner `Position<S>` to `$inner` and the size to the constant `$size`.
+#[macro_export]
+macro_rules! with_position {
+    ($position:expr, $inner:ident, $size:ident => $body:expr) => {
+        match $position {
+            $crate::position::AnyPosition::Size3($inner) => $crate::position::with_position!(@arm 3, $size => $body),
+            $crate::position::AnyPosition::Size4($inner) => $crate::position::with_position!(@arm 4, $size => $body),
+            $crate::position::AnyPosition::Size5($inner) => $crate::position::with_position!(@arm 5, $size => $body),
+            $crate::position::AnyPosition::Size6($inner) => $crate::position::with_position!(@arm 6, $size => $body),
+            $crate::position::AnyPosition::Size7($inner) => $crate::position::with_position!(@arm 7, $size => $body),
+            $crate::position::AnyPosition::Size8($inner) => $crate::position::with_position!(@arm 8, $size => $body),
+        }
+    };
+    (@arm $s:literal, $size:ident => $body:expr) => {{
+        #[allow(dead_code)]
+        const $size: usize = $s;
+        $body
+    }};
+}
+
+/// Run `$body` with `$size` bound to the runtime board size as a constant, or evaluate `$unsupported` for unsupported sizes.
+#[macro_export]
+macro_rules! with_size {
+    ($runtime_size:expr, $size:ident => $body:expr, _ => $unsupported:expr) => {
+        match $runtime_size {
+            3 => $crate::position::with_position!(@arm 3, $size => $body),
+            4 => $crate::position::with_position!(@arm 4, $size => $body),
+            5 => $crate::position::with_position!(@arm 5, $size => $body),
+            6 => $crate::position::with_position!(@arm 6, $size => $body),
+            7 => $crate::position::with_position!(@arm 7, $size => $body),
+            8 => $crate::position::with_position!(@arm 8, $size => $body),
+            _ => $unsupported,
+        }
+    };
+}
+
+fn unsupported_size_error(size: usize) -> pgn_traits::Error {
+    pgn_traits::Error::new(
+        pgn_traits::ErrorKind::Other,
+        format!("Unsupported board size {}", size),
+    )
+}
+
+/// A move on a board whose size is only known at runtime.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct AnyMove {
+    size: usize,
+    mv: Move,
+}
+
+impl AnyMove {
+    /// Returns `None` if the board size is not supported.
+    pub fn new(size: usize, mv: Move) -> Option<Self> {
+        SUPPORTED_SIZES
+            .contains(&size)
+            .then_some(AnyMove { size, mv })
+    }
+
+    /// Wrap a move for a board size known at compile time. Fails to compile for unsupported sizes.
+    pub fn from_move<const S: usize>(mv: Move) -> Self {
+        const { assert!(S >= 3 && S <= 8, "Unsupported board size") };
+        AnyMove { size: S, mv }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn mv(&self) -> &Move {
+        &self.mv
+    }
+
+    pub fn into_inner(self) -> Move {
+        self.mv
+    }
+}
+
+impl fmt::Display for AnyMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let string = with_size!(self.size, S => self.mv.to_string::<S>(), _ => unreachable!());
+        write!(f, "{}", string)
+    }
+}
+
+/// The rules for a board size that is only known at runtime. See `RuleSet`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AnyRuleSet {
+    Size3(RuleSet<3>),
+    Size4(RuleSet<4>),
+    Size5(RuleSet<5>),
+    Size6(RuleSet<6>),
+    Size7(RuleSet<7>),
+    Size8(RuleSet<8>),
+}
+
+/// Apply the same `RuleSet` method regardless of size, binding the inner rule set to `$inner`
+macro_rules! with_rules {
+    ($rules:expr, $inner:ident => $body:expr) => {
+        match $rules {
+            AnyRuleSet::Size3($inner) => $body,
+            AnyRuleSet::Size4($inner) => $body,
+            AnyRuleSet::Size5($inner) => $body,
+            AnyRuleSet::Size6($inner) => $body,
+            AnyRuleSet::Size7($inner) => $body,
+            AnyRuleSet::Size8($inner) => $body,
+        }
+    };
+}
+
+/// Apply the same `RuleSet` builder method regardless of size
+macro_rules! map_rules {
+    ($rules:expr, $inner:ident => $body:expr) => {
+        match $rules {
+            AnyRuleSet::Size3($inner) => AnyRuleSet::Size3($body),
+            AnyRuleSet::Size4($inner) => AnyRuleSet::Size4($body),
+            AnyRuleSet::Size5($inner) => AnyRuleSet::Size5($body),
+            AnyRuleSet::Size6($inner) => AnyRuleSet::Size6($body),
+            AnyRuleSet::Size7($inner) => AnyRuleSet::Size7($body),
+            AnyRuleSet::Size8($inner) => AnyRuleSet::Size8($body),
+        }
+    };
+}
+
+macro_rules! impl_from_rules {
+    ($($s:literal => $variant:ident),*) => {
+        $(
+            impl From<RuleSet<$s>> for AnyRuleSet {
+                fn from(rules: RuleSet<$s>) -> Self {
+                    AnyRuleSet::$variant(rules)
+                }
+            }
+        )*
+    };
+}
+
+impl_from_rules!(3 => Size3, 4 => Size4, 5 => Size5, 6 => Size6, 7 => Size7, 8 => Size8);
+
+impl AnyRuleSet {
+    /// The standard rules for the board size. Returns `None` if the size is not supported
+    pub fn default_for_size(size: usize) -> Option<Self> {
+        with_size!(size, S => Some(RuleSet::<S>::default().into()), _ => None)
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            AnyRuleSet::Size3(_) => 3,
+            AnyRuleSet::Size4(_) => 4,
+            AnyRuleSet::Size5(_) => 5,
+            AnyRuleSet::Size6(_) => 6,
+            AnyRuleSet::Size7(_) => 7,
+            AnyRuleSet::Size8(_) => 8,
+        }
+    }
+
+    /// Returns `None` if it is more than `MAX_STARTING_STONES`
+    pub fn add_starting_stones(self, starting_stones: u8) -> Option<Self> {
+        Some(map_rules!(self, rules => rules.add_starting_stones(starting_stones)?))
+    }
+
+    /// Returns `None` if it is more than `MAX_STARTING_CAPSTONES`
+    pub fn add_starting_capstones(self, starting_capstones: u8) -> Option<Self> {
+        Some(map_rules!(self, rules => rules.add_starting_capstones(starting_capstones)?))
+    }
+
+    pub fn add_komi(self, komi: Komi) -> Self {
+        map_rules!(self, rules => rules.add_komi(komi))
+    }
+
+    pub fn without_opening_swap(self) -> Self {
+        map_rules!(self, rules => rules.without_opening_swap())
+    }
+
+    pub fn without_repetition_draws(self) -> Self {
+        map_rules!(self, rules => rules.without_repetition_draws())
+    }
+
+    pub fn starting_stones(&self) -> u8 {
+        with_rules!(self, rules => rules.starting_stones())
+    }
+
+    pub fn starting_capstones(&self) -> u8 {
+        with_rules!(self, rules => rules.starting_capstones())
+    }
+
+    pub fn komi(&self) -> Komi {
+        with_rules!(self, rules => rules.komi())
+    }
+
+    pub fn opening_swap(&self) -> bool {
+        with_rules!(self, rules => rules.opening_swap())
+    }
+
+    pub fn repetition_draws(&self) -> bool {
+        with_rules!(self, rules => rules.repetition_draws())
+    }
+}
+
+/// A position of any supported size, for code that only knows the board size at runtime.
+/// Wraps `Position<S>`, and forwards move generation, TPS/PTN conversion and search to it.
+#[derive(Clone, PartialEq, Eq)]
+#[allow(clippy::large_enum_variant)]
+pub enum AnyPosition {
+    Size3(Position<3>),
+    Size4(Position<4>),
+    Size5(Position<5>),
+    Size6(Position<6>),
+    Size7(Position<7>),
+    Size8(Position<8>),
+}
+
+macro_rules! impl_from_position {
+    ($($s:literal => $variant:ident),*) => {
+        $(
+            impl From<Position<$s>> for AnyPosition {
+                fn from(position: Position<$s>) -> Self {
+                    AnyPosition::$variant(position)
+                }
+            }
+        )*
+    };
+}
+
+impl_from_position!(3 => Size3, 4 => Size4, 5 => Size5, 6 => Size6, 7 => Size7, 8 => Size8);
+
+/// Prints the inner position's board
+impl fmt::Debug for AnyPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        with_position!(self, position, S => fmt::Debug::fmt(position, f))
+    }
+}
+
+impl AnyPosition {
+    /// Returns `None` if the size is not supported
+    pub fn start_position(size: usize) -> Option<Self> {
+        Self::start_position_with_komi(size, Komi::default())
+    }
+
+    pub fn start_position_with_komi(size: usize, komi: Komi) -> Option<Self> {
+        with_size!(size, S => Some(<Position<S>>::start_position_with_komi(komi).into()), _ => None)
+    }
+
+    /// The start position for the rules, with the rules' board size
+    pub fn start_position_with_rules(rules: AnyRuleSet) -> Self {
+        with_rules!(rules, rules => Position::start_position_with_rules(rules).into())
+    }
+
+    pub fn from_fen(size: usize, tps: &str) -> Result<Self, pgn_traits::Error> {
+        Self::from_fen_with_komi(size, tps, Komi::default())
+    }
+
+    pub fn from_fen_with_komi(
+        size: usize,
+        tps: &str,
+        komi: Komi,
+    ) -> Result<Self, pgn_traits::Error> {
+        with_size!(size,
+            S => Ok(<Position<S>>::from_fen_with_komi(tps, komi)?.into()),
+            _ => Err(unsupported_size_error(size))
+        )
+    }
+
+    pub fn from_fen_with_rules(tps: &str, rules: AnyRuleSet) -> Result<Self, pgn_traits::Error> {
+        with_rules!(rules, rules => Ok(Position::from_fen_with_rules(tps, rules)?.into()))
+    }
+
+    pub fn size(&self) -> usize {
+        with_position!(self, _position, S => S)
+    }
+
+    pub fn rules(&self) -> AnyRuleSet {
+        with_position!(self, position, S => (*position.rules()).into())
+    }
+
+    pub fn side_to_move(&self) -> Color {
+        with_position!(self, position, S => position.side_to_move())
+    }
+
+    pub fn half_moves_played(&self) -> usize {
+        with_position!(self, position, S => position.half_moves_played())
+    }
+
+    pub fn komi(&self) -> Komi {
+        with_position!(self, position, S => position.komi())
+    }
+
+    pub fn game_result(&self) -> Option<GameResult> {
+        with_position!(self, position, S => position.game_result())
+    }
+
+    /// See `Position::game_outcome`
+    pub fn game_outcome(&self) -> Option<GameOutcome> {
+        with_position!(self, position, S => position.game_outcome())
+    }
+
+    pub fn generate_moves(&self, moves: &mut Vec<AnyMove>) {
+        with_position!(self, position, S => {
+            let mut inner_moves = vec![];
+            position.generate_moves(&mut inner_moves);
+            moves.extend(inner_moves.into_iter().map(AnyMove::from_move::<S>));
+        })
+    }
+
+    /// Panics if the move is for a different board size
+    pub fn do_move(&mut self, mv: AnyMove) -> ReverseMove {
+        assert_eq!(
+            mv.size,
+            self.size(),
+            "Tried to play a {}s move on a {}s board",
+            mv.size,
+            self.size()
+        );
+        with_position!(self, position, S => position.do_move(mv.mv))
+    }
+
+    /// Returns false for moves for a different board size
+    pub fn is_legal(&self, mv: &AnyMove) -> bool {
+        mv.size == self.size() && with_position!(self, position, S => position.is_legal(&mv.mv))
+    }
+
+    /// Play a move if it is legal. See `Position::try_do_move`.
+    /// Panics if the move is for a different board size
+    pub fn try_do_move(&mut self, mv: AnyMove) -> Result<ReverseMove, IllegalMoveError> {
+        assert_eq!(
+            mv.size,
+            self.size(),
+            "Tried to play a {}s move on a {}s board",
+            mv.size,
+            self.size()
+        );
+        with_position!(self, position, S => position.try_do_move(mv.mv))
+    }
+
+    pub fn reverse_move(&mut self, reverse_move: ReverseMove) {
+        with_position!(self, position, S => position.reverse_move(reverse_move))
+    }
+
+    pub fn to_fen(&self) -> String {
+        with_position!(self, position, S => position.to_fen())
+    }
+
+    /// See `Position::canonical_hash`
+    pub fn canonical_hash(&self) -> u64 {
+        with_position!(self, position, S => position.canonical_hash())
+    }
+
+    pub fn move_from_san(&self, input: &str) -> Result<AnyMove, pgn_traits::Error> {
+        with_position!(self, position, S => Ok(AnyMove::from_move::<S>(position.move_from_san(input)?)))
+    }
+
+    pub fn move_to_san(&self, mv: &AnyMove) -> String {
+        with_position!(self, position, S => position.move_to_san(&mv.mv))
+    }
+
+    /// The square's name on this position's board, like `a1`
+    pub fn square_to_string(&self, square: Square) -> String {
+        with_position!(self, _position, S => square.to_string::<S>())
+    }
+
+    /// Static evaluation of the position, from white's perspective. See `EvalPosition`.
+    pub fn static_eval(&self) -> f32 {
+        with_position!(self, position, S => position.static_eval())
+    }
+
+    /// Every legal move with its policy score and the policy features it was scored from, best moves first.
+    /// Used for debugging the policy evaluation.
+    pub fn move_heuristics(&self) -> Vec<(AnyMove, Score, Vec<f32>)> {
+        with_position!(self, position, S => {
+            let group_data = position.group_data();
+            let mut moves = vec![];
+            position.generate_moves_with_probabilities(
+                &group_data,
+                &mut vec![],
+                &mut moves,
+                &mut vec![],
+                search::MctsSetting::<S>::default().policy_baseline(),
+            );
+            moves.sort_by(|(_, score1), (_, score2)| score1.partial_cmp(score2).unwrap().reverse());
+
+            let mut feature_sets =
+                vec![vec![0.0; parameters::num_policy_features::<S>()]; moves.len()];
+            let mut policy_feature_sets: Vec<_> = feature_sets
+                .iter_mut()
+                .map(|feature_set| parameters::PolicyFeatures::new::<S>(feature_set))
+                .collect();
+            let simple_moves: Vec<Move> = moves.iter().map(|(mv, _)| mv.clone()).collect();
+            position.features_for_moves(&mut policy_feature_sets, &simple_moves, &group_data);
+
+            moves
+                .into_iter()
+                .zip(feature_sets)
+                .map(|((mv, score), features)| (AnyMove::from_move::<S>(mv), score, features))
+                .collect()
+        })
+    }
+
+    /// Count leaf nodes of the game tree to the given depth. See `move_gen::perft`.
+    pub fn perft(&mut self, depth: u16) -> u64 {
+        with_position!(self, position, S => move_gen::perft(position, depth))
+    }
+
+    /// Perft for each legal move. See `move_gen::divide`.
+    pub fn divide(&mut self, depth: u16) -> Vec<(AnyMove, u64)> {
+        with_position!(self, position, S => move_gen::divide(position, depth)
+            .into_iter()
+            .map(|(mv, nodes)| (AnyMove::from_move::<S>(mv), nodes))
+            .collect())
+    }
+
+    /// Run Monte Carlo Tree Search for `nodes` nodes. See `search::mcts`.
+    pub fn mcts(&self, nodes: u64) -> (AnyMove, Score) {
+        with_position!(self, position, S => {
+            let (mv, score) = search::mcts(position.clone(), nodes);
+            (AnyMove::from_move::<S>(mv), score)
+        })
+    }
+
+    /// Search for a forced road win for the side to move. See `tinue::find_tinue`.
+    pub fn find_tinue(&self, max_plies: u16, max_nodes: u64) -> TinueResult<AnyMove> {
+        with_position!(self, position, S => match tinue::find_tinue(position, max_plies, max_nodes) {
+            TinueResult::Win(line) => {
+                TinueResult::Win(line.into_iter().map(AnyMove::from_move::<S>).collect())
+            }
+            TinueResult::NoWin => TinueResult::NoWin,
+            TinueResult::Unknown => TinueResult::Unknown,
+        })
+    }
+
+    /// Play a move, calculating for a maximum duration. See `search::play_move_time`.
+    /// Panics if the settings are for a different board size.
+    pub fn play_move_time(
+        &self,
+        max_time: time::Duration,
+        settings: AnyMctsSetting,
+    ) -> (AnyMove, Score) {
+        match (self, settings) {
+            (AnyPosition::Size3(position), AnyMctsSetting::Size3(settings)) => {
+                let (mv, score) = search::play_move_time(position.clone(), max_time, settings);
+                (AnyMove::from_move::<3>(mv), score)
+            }
+            (AnyPosition::Size4(position), AnyMctsSetting::Size4(settings)) => {
+                let (mv, score) = search::play_move_time(position.clone(), max_time, settings);
+                (AnyMove::from_move::<4>(mv), score)
+            }
+            (AnyPosition::Size5(position), AnyMctsSetting::Size5(settings)) => {
+                let (mv, score) = search::play_move_time(position.clone(), max_time, settings);
+                (AnyMove::from_move::<5>(mv), score)
+            }
+            (AnyPosition::Size6(position), AnyMctsSetting::Size6(settings)) => {
+                let (mv, score) = search::play_move_time(position.clone(), max_time, settings);
+                (AnyMove::from_move::<6>(mv), score)
+            }
+            (AnyPosition::Size7(position), AnyMctsSetting::Size7(settings)) => {
+                let (mv, score) = search::play_move_time(position.clone(), max_time, settings);
+                (AnyMove::from_move::<7>(mv), score)
+            }
+            (AnyPosition::Size8(position), AnyMctsSetting::Size8(settings)) => {
+                let (mv, score) = search::play_move_time(position.clone(), max_time, settings);
+                (AnyMove::from_move::<8>(mv), score)
+            }
+            (position, settings) => panic!(
+                "Got {}s search settings for a {}s position",
+                settings.size(),
+                position.size()
+            ),
+        }
+    }
+}
@@ -54,9 +54,7 @@ impl BitBoard {
     }
     #[inline]
     pub const fn full() -> Self {
-        BitBoard {
-            board: u64::max_value(),
-        }
+        BitBoard { board: u64::MAX }
     }
 
     pub fn all_lines<const S: usize>() -> Vec<Self> {
@@ -136,6 +134,30 @@ impl BitBoard {
         BitBoard::from_u64(self.board & (mask << i as u64))
     }
 
+    /// The squares orthogonally adjacent to any square in the bitboard
+    #[inline]
+    pub fn adjacent<const S: usize>(self) -> Self {
+        let board = self.board;
+        let north = board >> S;
+        let south = (board << S) & Self::lower_n_bits((S * S) as u8).board;
+        let west = (board & !Self::full().file::<S>(0).board) >> 1;
+        let east = (board & !Self::full().file::<S>(S as u8 - 1).board) << 1;
+        BitBoard::from_u64(north | south | west | east)
+    }
+
+    /// The squares in `mask` that are connected to a square in the bitboard, through other squares in `mask`
+    #[inline]
+    pub fn flood_fill<const S: usize>(self, mask: Self) -> Self {
+        let mut filled = self & mask;
+        loop {
+            let next = (filled | filled.adjacent::<S>()) & mask;
+            if next == filled {
+                return filled;
+            }
+            filled = next;
+        }
+    }
+
     #[inline]
     pub fn is_empty(self) -> bool {
         self.board == 0
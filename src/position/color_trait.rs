@@ -19,8 +19,6 @@ pub(crate) trait ColorTr {
 
     fn road_stones<const S: usize>(group_data: &GroupData<S>) -> BitBoard;
 
-    fn blocking_stones<const S: usize>(group_data: &GroupData<S>) -> BitBoard;
-
     fn flats<const S: usize>(group_data: &GroupData<S>) -> BitBoard;
 
     fn walls<const S: usize>(group_data: &GroupData<S>) -> BitBoard;
@@ -61,10 +59,6 @@ impl ColorTr for WhiteTr {
         group_data.white_road_pieces()
     }
 
-    fn blocking_stones<const S: usize>(group_data: &GroupData<S>) -> BitBoard {
-        group_data.white_blocking_pieces()
-    }
-
     fn flats<const S: usize>(group_data: &GroupData<S>) -> BitBoard {
         group_data.white_flat_stones
     }
@@ -125,10 +119,6 @@ impl ColorTr for BlackTr {
         group_data.black_road_pieces()
     }
 
-    fn blocking_stones<const S: usize>(group_data: &GroupData<S>) -> BitBoard {
-        group_data.black_blocking_pieces()
-    }
-
     fn flats<const S: usize>(group_data: &GroupData<S>) -> BitBoard {
         group_data.black_flat_stones
     }
@@ -0,0 +1,69 @@
+use std::fmt;
+use std::str::FromStr;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Flat count bonus given to black at the end of a game that is decided on flats.
+/// Stored internally in half flats, so that komis like 2.5 can be represented exactly.
+#[derive(Default, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Komi {
+    half_komi: i8,
+}
+
+impl Komi {
+    /// Returns `None` if the komi is outside the supported range of -10 to 10 flats
+    pub fn from_half_komi(half_komi: i8) -> Option<Self> {
+        if (-20..=20).contains(&half_komi) {
+            Some(Komi { half_komi })
+        } else {
+            None
+        }
+    }
+
+    pub fn half_komi(self) -> i8 {
+        self.half_komi
+    }
+
+    pub fn as_f32(self) -> f32 {
+        self.half_komi as f32 / 2.0
+    }
+}
+
+impl fmt::Display for Komi {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.half_komi % 2 == 0 {
+            write!(f, "{}", self.half_komi / 2)
+        } else {
+            write!(f, "{:.1}", self.as_f32())
+        }
+    }
+}
+
+impl FromStr for Komi {
+    type Err = pgn_traits::Error;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let parse_error =
+            || pgn_traits::Error::new_parse_error(format!("Invalid komi \"{}\"", input));
+
+        let (integer_part, has_half) = match input.trim().split_once('.') {
+            None => (input.trim(), false),
+            Some((integer_part, "5")) => (integer_part, true),
+            Some((integer_part, decimals)) if decimals.chars().all(|ch| ch == '0') => {
+                (integer_part, false)
+            }
+            Some(_) => return Err(parse_error()),
+        };
+        let komi = i8::from_str(integer_part).map_err(|_| parse_error())?;
+        let half_komi = match (has_half, integer_part.starts_with('-')) {
+            (false, _) => komi.checked_mul(2),
+            (true, false) => komi.checked_mul(2).and_then(|k| k.checked_add(1)),
+            (true, true) => komi.checked_mul(2).and_then(|k| k.checked_sub(1)),
+        };
+        half_komi
+            .and_then(Komi::from_half_komi)
+            .ok_or_else(parse_error)
+    }
+}
@@ -4,7 +4,7 @@ use std::cmp::Ordering;
 use std::fmt::Write;
 use std::hash::{Hash, Hasher};
 use std::mem;
-use std::ops::{Index, IndexMut};
+use std::ops::Index;
 use std::{fmt, ops};
 
 use board_game_traits::GameResult::{BlackWin, Draw, WhiteWin};
@@ -21,25 +21,40 @@ use color_trait::{BlackTr, WhiteTr};
 use utils::AbstractBoard;
 pub use utils::{
     squares_iterator, Direction, Movement, Piece, Piece::*, Role, Role::*, Square, Stack,
-    StackMovement,
+    StackMovement, MAX_STACK_HEIGHT,
 };
 
-pub use mv::{Move, ReverseMove};
+pub use crate::{with_position, with_size};
+pub use any_position::{AnyMove, AnyPosition, AnyRuleSet, SUPPORTED_SIZES};
+pub use komi::Komi;
+pub use mv::{IllegalMoveError, Move, ReverseMove};
+pub use outcome::{FlatCount, GameEndReason, GameOutcome};
+pub use rules::{RuleSet, MAX_STARTING_CAPSTONES, MAX_STARTING_STONES};
+pub use transform::Transform;
 
 use crate::evaluation::parameters::{
-    ValueFeatures, POLICY_PARAMS_4S, POLICY_PARAMS_5S, POLICY_PARAMS_6S, VALUE_PARAMS_4S,
-    VALUE_PARAMS_5S, VALUE_PARAMS_6S,
+    ValueFeatures, POLICY_PARAMS_3S, POLICY_PARAMS_4S, POLICY_PARAMS_5S, POLICY_PARAMS_6S,
+    POLICY_PARAMS_7S, POLICY_PARAMS_8S, VALUE_PARAMS_3S, VALUE_PARAMS_4S, VALUE_PARAMS_5S,
+    VALUE_PARAMS_6S, VALUE_PARAMS_7S, VALUE_PARAMS_8S,
 };
 use crate::evaluation::value_eval;
 use crate::position::color_trait::ColorTr;
 use crate::search;
 
+mod any_position;
 pub(crate) mod bitboard;
 pub(crate) mod color_trait;
+mod komi;
 mod mv;
+mod outcome;
+mod rules;
+#[cfg(feature = "serde")]
+mod serialization;
+mod transform;
 mod utils;
 
 lazy_static! {
+    pub(crate) static ref ZOBRIST_KEYS_3S: Box<ZobristKeys<3>> = ZobristKeys::new();
     pub(crate) static ref ZOBRIST_KEYS_4S: Box<ZobristKeys<4>> = ZobristKeys::new();
     pub(crate) static ref ZOBRIST_KEYS_5S: Box<ZobristKeys<5>> = ZobristKeys::new();
     pub(crate) static ref ZOBRIST_KEYS_6S: Box<ZobristKeys<6>> = ZobristKeys::new();
@@ -75,15 +90,19 @@ pub const fn starting_capstones<const S: usize>() -> u8 {
 
 pub(crate) const fn num_square_symmetries<const S: usize>() -> usize {
     match S {
+        3 => 3,
         4 => 3,
         5 => 6,
         6 => 6,
+        7 => 10,
+        8 => 10,
         _ => 0,
     }
 }
 
 pub(crate) const fn square_symmetries<const S: usize>() -> &'static [usize] {
     match S {
+        3 => &[0, 1, 0, 1, 2, 1, 0, 1, 0],
         4 => &[0, 1, 1, 0, 1, 2, 2, 1, 1, 2, 2, 1, 0, 1, 1, 0],
         5 => &[
             0, 1, 2, 1, 0, 1, 3, 4, 3, 1, 2, 4, 5, 4, 2, 1, 3, 4, 3, 1, 0, 1, 2, 1, 0,
@@ -92,6 +111,15 @@ pub(crate) const fn square_symmetries<const S: usize>() -> &'static [usize] {
             0, 1, 2, 2, 1, 0, 1, 3, 4, 4, 3, 1, 2, 4, 5, 5, 4, 2, 2, 4, 5, 5, 4, 2, 1, 3, 4, 4, 3,
             1, 0, 1, 2, 2, 1, 0,
         ],
+        7 => &[
+            0, 1, 2, 3, 2, 1, 0, 1, 4, 5, 6, 5, 4, 1, 2, 5, 7, 8, 7, 5, 2, 3, 6, 8, 9, 8, 6, 3, 2,
+            5, 7, 8, 7, 5, 2, 1, 4, 5, 6, 5, 4, 1, 0, 1, 2, 3, 2, 1, 0,
+        ],
+        8 => &[
+            0, 1, 2, 3, 3, 2, 1, 0, 1, 4, 5, 6, 6, 5, 4, 1, 2, 5, 7, 8, 8, 7, 5, 2, 3, 6, 8, 9, 9,
+            8, 6, 3, 3, 6, 8, 9, 9, 8, 6, 3, 2, 5, 7, 8, 8, 7, 5, 2, 1, 4, 5, 6, 6, 5, 4, 1, 0, 1,
+            2, 3, 3, 2, 1, 0,
+        ],
         _ => &[],
     }
 }
@@ -248,12 +276,14 @@ impl<const S: usize> GroupData<S> {
 #[derive(PartialEq, Eq, Debug)]
 pub struct ZobristKeys<const S: usize> {
     top_stones: AbstractBoard<[u64; 6], S>,
-    stones_in_stack: [AbstractBoard<[u64; 256], S>; 8],
+    /// Keys for each byte of `Stack::bitboard`
+    stones_in_stack: [AbstractBoard<[u64; 256], S>; 16],
     to_move: [u64; 2],
 }
 
 pub fn zobrist_top_stones<const S: usize>(square: Square, piece: Piece) -> u64 {
     match S {
+        3 => ZOBRIST_KEYS_3S.top_stones[square][piece as u16 as usize],
         4 => ZOBRIST_KEYS_4S.top_stones[square][piece as u16 as usize],
         5 => ZOBRIST_KEYS_5S.top_stones[square][piece as u16 as usize],
         6 => ZOBRIST_KEYS_6S.top_stones[square][piece as u16 as usize],
@@ -269,6 +299,7 @@ pub fn zobrist_stones_in_stack<const S: usize>(
     stack_slice: usize,
 ) -> u64 {
     match S {
+        3 => ZOBRIST_KEYS_3S.stones_in_stack[place_in_stack][square][stack_slice],
         4 => ZOBRIST_KEYS_4S.stones_in_stack[place_in_stack][square][stack_slice],
         5 => ZOBRIST_KEYS_5S.stones_in_stack[place_in_stack][square][stack_slice],
         6 => ZOBRIST_KEYS_6S.stones_in_stack[place_in_stack][square][stack_slice],
@@ -280,6 +311,7 @@ pub fn zobrist_stones_in_stack<const S: usize>(
 
 pub fn zobrist_to_move<const S: usize>(color: Color) -> u64 {
     match S {
+        3 => ZOBRIST_KEYS_3S.to_move[color.disc()],
         4 => ZOBRIST_KEYS_4S.to_move[color.disc()],
         5 => ZOBRIST_KEYS_5S.to_move[color.disc()],
         6 => ZOBRIST_KEYS_6S.to_move[color.disc()],
@@ -296,23 +328,37 @@ impl<const S: usize> ZobristKeys<S> {
         for word in random_vec.iter_mut() {
             *word = rng.gen();
         }
-        let zobrist = unsafe { mem::transmute(Box::from_raw(random_vec.as_mut_ptr())) };
+        let zobrist = unsafe {
+            mem::transmute::<Box<u64>, Box<ZobristKeys<S>>>(Box::from_raw(random_vec.as_mut_ptr()))
+        };
 
         mem::forget(random_vec);
         zobrist
     }
 }
 
-/// Complete representation of a Tak position
+/// Complete representation of a Tak position.
+/// With the `serde` feature, it is serialized as TPS along with its `RuleSet`.
 #[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(
+        into = "serialization::SerializedPosition<S>",
+        try_from = "serialization::SerializedPosition<S>"
+    )
+)]
 pub struct Position<const S: usize> {
     cells: AbstractBoard<Stack, S>,
+    top_stones: [BitBoard; 6], // Squares with each type of piece on top, indexed by `Piece as usize`
+    edge_connections: [[BitBoard; 4]; 2], // Road pieces connected to each edge, indexed by `Color as usize` and then in `edges` order
     to_move: Color,
     white_stones_left: u8,
     black_stones_left: u8,
     white_caps_left: u8,
     black_caps_left: u8,
     half_moves_played: usize,
+    rules: RuleSet<S>,
     moves: Vec<Move>,
     hash: u64,              // Zobrist hash of current position
     hash_history: Vec<u64>, // Zobrist hashes of previous board states, up to the last irreversible move. Does not include the corrent position
@@ -327,6 +373,7 @@ impl<const S: usize> PartialEq for Position<S> {
             && self.white_caps_left == other.white_caps_left
             && self.black_caps_left == other.black_caps_left
             && self.half_moves_played == other.half_moves_played
+            && self.rules == other.rules
     }
 }
 
@@ -341,6 +388,7 @@ impl<const S: usize> Hash for Position<S> {
         self.white_caps_left.hash(state);
         self.black_caps_left.hash(state);
         self.half_moves_played.hash(state);
+        self.rules.hash(state);
     }
 }
 
@@ -352,26 +400,9 @@ impl<const S: usize> Index<Square> for Position<S> {
     }
 }
 
-impl<const S: usize> IndexMut<Square> for Position<S> {
-    fn index_mut(&mut self, square: Square) -> &mut Self::Output {
-        &mut self.cells[square]
-    }
-}
-
 impl<const S: usize> Default for Position<S> {
     fn default() -> Self {
-        Position {
-            cells: Default::default(),
-            to_move: Color::White,
-            white_stones_left: starting_stones::<S>(),
-            black_stones_left: starting_stones::<S>(),
-            white_caps_left: starting_capstones::<S>(),
-            black_caps_left: starting_capstones::<S>(),
-            half_moves_played: 0,
-            moves: vec![],
-            hash: zobrist_to_move::<S>(Color::White),
-            hash_history: vec![],
-        }
+        Self::start_position_with_rules(RuleSet::default())
     }
 }
 
@@ -409,6 +440,7 @@ impl<const S: usize> fmt::Debug for Position<S> {
             self.white_caps_left, self.black_caps_left
         )?;
         writeln!(f, "{} to move.", self.side_to_move())?;
+        writeln!(f, "Komi: {}.", self.rules.komi())?;
         writeln!(
             f,
             "Hash: {}, hash history: {:?}",
@@ -419,6 +451,229 @@ impl<const S: usize> fmt::Debug for Position<S> {
 }
 
 impl<const S: usize> Position<S> {
+    pub fn start_position_with_rules(rules: RuleSet<S>) -> Self {
+        Position {
+            cells: Default::default(),
+            top_stones: [BitBoard::empty(); 6],
+            edge_connections: [[BitBoard::empty(); 4]; 2],
+            to_move: Color::White,
+            white_stones_left: rules.starting_stones(),
+            black_stones_left: rules.starting_stones(),
+            white_caps_left: rules.starting_capstones(),
+            black_caps_left: rules.starting_capstones(),
+            half_moves_played: 0,
+            rules,
+            moves: vec![],
+            hash: zobrist_to_move::<S>(Color::White),
+            hash_history: vec![],
+        }
+    }
+
+    pub fn start_position_with_komi(komi: Komi) -> Self {
+        Self::start_position_with_rules(RuleSet::default().add_komi(komi))
+    }
+
+    /// Parse a TPS string, for a game played with the given komi
+    pub fn from_fen_with_komi(tps: &str, komi: Komi) -> Result<Self, pgn_traits::Error> {
+        Self::from_fen_with_rules(tps, RuleSet::default().add_komi(komi))
+    }
+
+    /// Parse a TPS string, for a game played with the given rules
+    pub fn from_fen_with_rules(fen: &str, rules: RuleSet<S>) -> Result<Self, pgn_traits::Error> {
+        let fen_words: Vec<&str> = fen.split_whitespace().collect();
+
+        if fen_words.len() < 3 {
+            return Err(pgn_traits::Error::new_parse_error(format!(
+                "Couldn't parse TPS string \"{}\", missing move counter.",
+                fen
+            )));
+        }
+        if fen_words.len() > 3 {
+            return Err(pgn_traits::Error::new_parse_error(format!(
+                "Couldn't parse TPS string \"{}\", unexpected \"{}\"",
+                fen, fen_words[3]
+            )));
+        }
+
+        let fen_rows: Vec<&str> = fen_words[0].split('/').collect();
+        if fen_rows.len() != S {
+            return Err(pgn_traits::Error::new_parse_error(format!(
+                "Couldn't parse TPS string \"{}\", had {} rows instead of {}.",
+                fen,
+                fen_rows.len(),
+                S
+            )));
+        }
+
+        let rows: Vec<[Stack; S]> = fen_rows
+            .into_iter()
+            .map(parse_row)
+            .collect::<Result<_, _>>()
+            .map_err(|e| {
+                pgn_traits::Error::new_caused_by(
+                    pgn_traits::ErrorKind::ParseError,
+                    format!("Couldn't parse TPS string \"{}\"", fen),
+                    e,
+                )
+            })?;
+        let to_move = match fen_words[1] {
+            "1" => Color::White,
+            "2" => Color::Black,
+            s => {
+                return Err(pgn_traits::Error::new_parse_error(format!(
+                    "Error parsing TPS \"{}\": Got bad side to move \"{}\"",
+                    fen, s
+                )))
+            }
+        };
+
+        let half_moves_played = match fen_words[2].parse::<usize>() {
+            Ok(n) => match to_move {
+                Color::White => (n - 1) * 2,
+                Color::Black => (n - 1) * 2 + 1,
+            },
+            Err(e) => {
+                return Err(pgn_traits::Error::new_caused_by(
+                    pgn_traits::ErrorKind::ParseError,
+                    format!(
+                        "Error parsing TPS \"{}\": Got bad move number \"{}\"",
+                        fen, fen_words[2]
+                    ),
+                    e,
+                ))
+            }
+        };
+
+        let stacks = utils::squares_iterator::<S>()
+            .map(|square| rows[square.rank::<S>() as usize][square.file::<S>() as usize]);
+
+        return Self::from_stacks(stacks, half_moves_played, rules).map_err(|e| {
+            pgn_traits::Error::new_caused_by(
+                pgn_traits::ErrorKind::ParseError,
+                format!("Couldn't parse TPS string \"{}\"", fen),
+                e,
+            )
+        });
+
+        fn parse_row<const S: usize>(row_str: &str) -> Result<[Stack; S], pgn_traits::Error> {
+            let mut column_id = 0;
+            let mut row = [Stack::default(); S];
+            let mut row_str_iter = row_str.chars().peekable();
+            while column_id < S as u8 {
+                match row_str_iter.peek() {
+                    None => {
+                        return Err(pgn_traits::Error::new_parse_error(format!(
+                            "Couldn't parse row \"{}\": not enough pieces",
+                            row_str
+                        )))
+                    }
+                    Some('x') => {
+                        row_str_iter.next();
+                        if let Some(n) = row_str_iter.peek().and_then(|ch| ch.to_digit(10)) {
+                            row_str_iter.next();
+                            column_id += n as u8;
+                        } else {
+                            column_id += 1;
+                        }
+                        if let Some(',') | None = row_str_iter.peek() {
+                            row_str_iter.next();
+                        } else {
+                            return Err(pgn_traits::Error::new_parse_error(format!(
+                                "Expected ',' on row \"{}\", found {:?}",
+                                row_str,
+                                row_str_iter.next()
+                            )));
+                        }
+                    }
+                    Some('1') | Some('2') => {
+                        let stack = &mut row[column_id as usize];
+                        loop {
+                            match row_str_iter.next() {
+                                Some('1' | '2') if stack.len() == MAX_STACK_HEIGHT => {
+                                    return Err(pgn_traits::Error::new_parse_error(format!(
+                                        "Stack on row \"{}\" has more than {} pieces",
+                                        row_str, MAX_STACK_HEIGHT
+                                    )))
+                                }
+                                Some('1') => stack.push(Piece::from_role_color(Flat, Color::White)),
+                                Some('2') => stack.push(Piece::from_role_color(Flat, Color::Black)),
+                                Some('S') => {
+                                    let piece = stack.pop().unwrap();
+                                    stack.push(Piece::from_role_color(Wall, piece.color()));
+                                }
+                                Some('C') => {
+                                    let piece = stack.pop().unwrap();
+                                    stack.push(Piece::from_role_color(Cap, piece.color()));
+                                }
+                                Some(',') | None => {
+                                    column_id += 1;
+                                    break;
+                                }
+                                Some(ch) => {
+                                    return Err(pgn_traits::Error::new_parse_error(format!(
+                                        "Expected '1', '2', 'S' or 'C' on row \"{}\", found {}",
+                                        row_str, ch
+                                    )))
+                                }
+                            }
+                        }
+                    }
+                    Some(x) => {
+                        return Err(pgn_traits::Error::new_parse_error(format!(
+                            "Unexpected '{}' in row \"{}\".",
+                            x, row_str
+                        )))
+                    }
+                }
+            }
+            Ok(row)
+        }
+    }
+
+    /// Create a position from the contents of each square, in `squares_iterator` order.
+    /// The side to move is determined by the number of half moves played.
+    pub(crate) fn from_stacks(
+        stacks: impl IntoIterator<Item = Stack>,
+        half_moves_played: usize,
+        rules: RuleSet<S>,
+    ) -> Result<Self, pgn_traits::Error> {
+        let mut position = Position::start_position_with_rules(rules);
+        for (square, stack) in utils::squares_iterator::<S>().zip(stacks) {
+            for piece in stack.into_iter() {
+                let reserves = match piece {
+                    WhiteFlat | WhiteWall => &mut position.white_stones_left,
+                    WhiteCap => &mut position.white_caps_left,
+                    BlackFlat | BlackWall => &mut position.black_stones_left,
+                    BlackCap => &mut position.black_caps_left,
+                };
+                *reserves = reserves.checked_sub(1).ok_or_else(|| {
+                    pgn_traits::Error::new(
+                        pgn_traits::ErrorKind::IllegalPosition,
+                        format!("Too many {:?} pieces", piece),
+                    )
+                })?;
+            }
+            position.cells[square] = stack;
+            position.update_top_stones(square);
+        }
+        position.update_all_edge_connections();
+        position.half_moves_played = half_moves_played;
+        position.to_move = match half_moves_played % 2 {
+            0 => Color::White,
+            _ => Color::Black,
+        };
+        position.hash = position.zobrist_hash_from_scratch();
+        Ok(position)
+    }
+
+    pub fn rules(&self) -> &RuleSet<S> {
+        &self.rules
+    }
+
+    pub fn komi(&self) -> Komi {
+        self.rules.komi()
+    }
+
     pub fn white_reserves_left(&self) -> u8 {
         self.white_stones_left
     }
@@ -435,7 +690,17 @@ impl<const S: usize> Position<S> {
         self.black_caps_left
     }
 
-    #[cfg(test)]
+    /// Replace the stack on a square, keeping the bitboards and the zobrist hash in sync.
+    /// Does not change the pieces left in each player's reserves.
+    pub fn set_stack(&mut self, square: Square, stack: Stack) {
+        self.hash ^= self.zobrist_hash_for_square(square);
+        self.cells[square] = stack;
+        self.hash ^= self.zobrist_hash_for_square(square);
+        self.update_top_stones(square);
+        self.update_all_edge_connections();
+    }
+
+    /// Zobrist hash of the position, including the side to move
     pub fn zobrist_hash(&self) -> u64 {
         self.hash
     }
@@ -450,8 +715,24 @@ impl<const S: usize> Position<S> {
         &self.moves
     }
 
+    /// Check whether a move is legal, without generating every legal move.
+    /// As with `generate_moves`, moves are still legal if the game is already over.
+    pub fn is_legal(&self, mv: &Move) -> bool {
+        self.check_legality(mv).is_ok()
+    }
+
+    /// Play a move if it is legal, or return the reason why it is illegal.
+    /// `do_move` should be preferred for moves that are known to be legal, for example from `generate_moves`.
+    pub fn try_do_move(&mut self, mv: Move) -> Result<ReverseMove, IllegalMoveError> {
+        self.check_legality(&mv)?;
+        Ok(self.do_move(mv))
+    }
+
+    /// Pass the turn to the other player, without changing the board
     pub fn null_move(&mut self) {
+        self.hash ^= zobrist_to_move::<S>(self.to_move);
         self.to_move = !self.to_move;
+        self.hash ^= zobrist_to_move::<S>(self.to_move);
     }
 
     pub(crate) fn zobrist_hash_from_scratch(&self) -> u64 {
@@ -473,73 +754,109 @@ impl<const S: usize> Position<S> {
             for i in 0..(stack.len() as usize + 6) / 8 {
                 hash ^= zobrist_stones_in_stack::<S>(
                     square,
-                    i as usize,
-                    stack.bitboard.board as usize >> (i * 8) & 255,
+                    i,
+                    (stack.bitboard >> (i * 8)) as usize & 255,
                 )
             }
         }
         hash
     }
 
-    fn is_critical_square_from_scratch(
-        &self,
-        groups: &AbstractBoard<u8, S>,
-        amount_in_group: &[(u8, GroupEdgeConnection)],
-        square: Square,
-        color: Color,
-    ) -> bool {
-        let sum_of_connections = square
-            .neighbours::<S>()
-            .filter(|neighbour| self[*neighbour].top_stone().map(Piece::color) == Some(color))
-            .map(|neighbour| amount_in_group[groups[neighbour] as usize].1)
-            .fold(
-                GroupEdgeConnection::default().connect_square::<S>(square),
-                |acc, connection| acc | connection,
-            );
+    pub fn flip_board_y(&self) -> Position<S> {
+        self.transform(
+            Transform::mirror()
+                .then(Transform::rotation())
+                .then(Transform::rotation()),
+        )
+    }
 
-        sum_of_connections.is_winning()
+    pub fn flip_board_x(&self) -> Position<S> {
+        self.transform(Transform::mirror())
     }
 
-    pub fn flip_board_y(&self) -> Position<S> {
-        let mut new_board = self.clone();
-        for x in 0..S as u8 {
-            for y in 0..S as u8 {
-                new_board[Square(y * S as u8 + x)] = self[Square((S as u8 - y - 1) * S as u8 + x)];
-            }
-        }
-        new_board
+    pub fn rotate_board(&self) -> Position<S> {
+        self.transform(Transform::rotation())
     }
 
-    pub fn flip_board_x(&self) -> Position<S> {
+    /// Returns the position with the board rotated and/or mirrored.
+    /// The moves played so far are transformed as well, but earlier positions are not kept for repetition detection.
+    pub fn transform(&self, transform: Transform) -> Position<S> {
         let mut new_board = self.clone();
-        for x in 0..S as u8 {
-            for y in 0..S as u8 {
-                new_board[Square(y * S as u8 + x)] = self[Square(y * S as u8 + (S as u8 - x - 1))];
-            }
+        for square in utils::squares_iterator::<S>() {
+            new_board.cells[transform.apply_square::<S>(square)] = self[square];
         }
+        new_board.update_all_top_stones();
+        for mv in new_board.moves.iter_mut() {
+            *mv = transform.apply_move::<S>(mv);
+        }
+        new_board.hash = new_board.zobrist_hash_from_scratch();
+        new_board.hash_history.clear();
         new_board
     }
 
-    pub fn rotate_board(&self) -> Position<S> {
-        let mut new_board = self.clone();
-        for x in 0..S as u8 {
-            for y in 0..S as u8 {
-                let new_x = y;
-                let new_y = S as u8 - x - 1;
-                new_board[Square(y * S as u8 + x)] = self[Square(new_y * S as u8 + new_x)];
+    /// The zobrist hash of the position after applying `transform`, without constructing it
+    fn zobrist_hash_with_transform(&self, transform: Transform) -> u64 {
+        let mut hash = zobrist_to_move::<S>(self.to_move);
+        for square in utils::squares_iterator::<S>() {
+            let stack = &self[square];
+            if let Some(top_stone) = stack.top_stone {
+                let new_square = transform.apply_square::<S>(square);
+                hash ^= zobrist_top_stones::<S>(new_square, top_stone);
+                for i in 0..(stack.len() as usize + 6) / 8 {
+                    hash ^= zobrist_stones_in_stack::<S>(
+                        new_square,
+                        i,
+                        (stack.bitboard >> (i * 8)) as usize & 255,
+                    )
+                }
             }
         }
-        new_board
+        hash
+    }
+
+    /// The symmetry of the position that has the lowest zobrist hash
+    fn canonical_transform(&self) -> Transform {
+        Transform::all()
+            .iter()
+            .copied()
+            .min_by_key(|transform| self.zobrist_hash_with_transform(*transform))
+            .unwrap()
+    }
+
+    /// Every transform that maps the position to its canonical form.
+    /// There is more than one if the position is symmetric, for example the start position.
+    pub fn canonical_transforms(&self) -> Vec<Transform> {
+        let canonical_hash = self.canonical_hash();
+        Transform::all()
+            .iter()
+            .copied()
+            .filter(|transform| self.zobrist_hash_with_transform(*transform) == canonical_hash)
+            .collect()
+    }
+
+    /// Returns the canonical form of the position, which is the same for all 8 rotations and reflections of the board,
+    /// and the transform that maps this position to it.
+    /// Moves in this position can be mapped to the canonical position with `Transform::apply_move`, and back with `Transform::inverse`.
+    pub fn canonical(&self) -> (Position<S>, Transform) {
+        let transform = self.canonical_transform();
+        (self.transform(transform), transform)
+    }
+
+    /// The zobrist hash of the canonical form of the position.
+    /// Equal for all rotations and reflections of the board, and cheaper than calling `canonical`.
+    pub fn canonical_hash(&self) -> u64 {
+        self.zobrist_hash_with_transform(self.canonical_transform())
     }
 
     pub fn flip_colors(&self) -> Position<S> {
         let mut new_board = self.clone();
         for square in utils::squares_iterator::<S>() {
-            new_board[square] = Stack::default();
+            new_board.cells[square] = Stack::default();
             for piece in self[square] {
-                new_board[square].push(piece.flip_color());
+                new_board.cells[square].push(piece.flip_color());
             }
         }
+        new_board.update_all_top_stones();
         mem::swap(
             &mut new_board.white_stones_left,
             &mut new_board.black_stones_left,
@@ -574,73 +891,156 @@ impl<const S: usize> Position<S> {
             .collect()
     }
 
-    fn count_all_pieces(&self) -> u8 {
-        squares_iterator::<S>()
-            .map(|square| self[square].len())
-            .sum()
+    /// Squares with the given piece on top
+    #[inline]
+    pub(crate) fn top_stones(&self, piece: Piece) -> BitBoard {
+        self.top_stones[piece as usize]
     }
 
-    #[inline(never)]
-    pub fn group_data(&self) -> GroupData<S> {
-        let mut group_data = GroupData::default();
+    /// Squares where the top stone is a road piece of the given color
+    #[inline]
+    pub(crate) fn road_pieces(&self, color: Color) -> BitBoard {
+        match color {
+            Color::White => self.top_stones(WhiteFlat) | self.top_stones(WhiteCap),
+            Color::Black => self.top_stones(BlackFlat) | self.top_stones(BlackCap),
+        }
+    }
 
-        for square in utils::squares_iterator::<S>() {
-            match self[square].top_stone() {
-                Some(WhiteFlat) => {
-                    group_data.white_flat_stones = group_data.white_flat_stones.set(square.0)
-                }
-                Some(BlackFlat) => {
-                    group_data.black_flat_stones = group_data.black_flat_stones.set(square.0)
-                }
-                Some(WhiteWall) => group_data.white_walls = group_data.white_walls.set(square.0),
-                Some(BlackWall) => group_data.black_walls = group_data.black_walls.set(square.0),
-                Some(WhiteCap) => group_data.white_caps = group_data.white_caps.set(square.0),
-                Some(BlackCap) => group_data.black_caps = group_data.black_caps.set(square.0),
-                None => (),
+    /// Squares where the top stone is of the given color
+    #[inline]
+    pub(crate) fn pieces_of_color(&self, color: Color) -> BitBoard {
+        match color {
+            Color::White => {
+                self.top_stones(WhiteFlat) | self.top_stones(WhiteWall) | self.top_stones(WhiteCap)
+            }
+            Color::Black => {
+                self.top_stones(BlackFlat) | self.top_stones(BlackWall) | self.top_stones(BlackCap)
             }
         }
+    }
 
-        let mut highest_component_id = 1;
+    #[inline]
+    pub(crate) fn empty_squares(&self) -> BitBoard {
+        !(self.pieces_of_color(Color::White) | self.pieces_of_color(Color::Black))
+            & BitBoard::lower_n_bits((S * S) as u8)
+    }
 
-        connected_components_graph(
-            group_data.white_road_pieces(),
-            &mut group_data.groups,
-            &mut highest_component_id,
-        );
-        connected_components_graph(
-            group_data.black_road_pieces(),
-            &mut group_data.groups,
-            &mut highest_component_id,
-        );
+    /// Update the top stone bitboards after the stack on `square` has changed
+    #[inline]
+    fn update_top_stones(&mut self, square: Square) {
+        for bitboard in self.top_stones.iter_mut() {
+            *bitboard = bitboard.clear(square.0);
+        }
+        if let Some(piece) = self[square].top_stone() {
+            self.top_stones[piece as usize] = self.top_stones[piece as usize].set(square.0);
+        }
+    }
 
+    fn update_all_top_stones(&mut self) {
         for square in utils::squares_iterator::<S>() {
-            group_data.amount_in_group[group_data.groups[square] as usize].0 += 1;
-            if self[square].top_stone().map(Piece::is_road_piece) == Some(true) {
-                group_data.amount_in_group[group_data.groups[square] as usize].1 = group_data
-                    .amount_in_group[group_data.groups[square] as usize]
-                    .1
-                    .connect_square::<S>(square);
-            }
+            self.update_top_stones(square);
         }
+        self.update_all_edge_connections();
+    }
+
+    /// The edges of the board, in the order used by `edge_connections`: first rank, last rank, first file and last file
+    #[inline]
+    fn edges() -> [BitBoard; 4] {
+        let full = BitBoard::full();
+        [
+            full.rank::<S>(0),
+            full.rank::<S>(S as u8 - 1),
+            full.file::<S>(0),
+            full.file::<S>(S as u8 - 1),
+        ]
+    }
 
-        for square in utils::squares_iterator::<S>() {
-            if self.is_critical_square_from_scratch(
-                &group_data.groups,
-                &group_data.amount_in_group,
-                square,
-                Color::White,
-            ) {
-                group_data.white_critical_squares = group_data.white_critical_squares.set(square.0);
+    fn update_all_edge_connections(&mut self) {
+        self.edge_connections = self.edge_connections_from_scratch();
+    }
+
+    fn edge_connections_from_scratch(&self) -> [[BitBoard; 4]; 2] {
+        [Color::White, Color::Black].map(|color| {
+            let road_pieces = self.road_pieces(color);
+            Self::edges().map(|edge| (road_pieces & edge).flood_fill::<S>(road_pieces))
+        })
+    }
+
+    /// Update the edge connections after a move, given the road pieces before the move.
+    /// An edge is only flood filled again if it lost a connected piece, or if a new piece touches it or its connected pieces
+    #[inline]
+    fn update_edge_connections(&mut self, old_road_pieces: [BitBoard; 2]) {
+        for color in [Color::White, Color::Black] {
+            let road_pieces = self.road_pieces(color);
+            let removed = old_road_pieces[color as usize] & !road_pieces;
+            let added = road_pieces & !old_road_pieces[color as usize];
+            if removed.is_empty() && added.is_empty() {
+                continue;
+            }
+            for (connection, edge) in self.edge_connections[color as usize]
+                .iter_mut()
+                .zip(Self::edges())
+            {
+                if !(*connection & removed).is_empty() {
+                    *connection = (road_pieces & edge).flood_fill::<S>(road_pieces);
+                } else {
+                    let new_connections = added & (edge | connection.adjacent::<S>());
+                    if !new_connections.is_empty() {
+                        *connection = (*connection | new_connections).flood_fill::<S>(road_pieces);
+                    }
+                }
             }
-            if self.is_critical_square_from_scratch(
-                &group_data.groups,
-                &group_data.amount_in_group,
-                square,
-                Color::Black,
-            ) {
-                group_data.black_critical_squares = group_data.black_critical_squares.set(square.0);
+        }
+    }
+
+    #[inline]
+    fn all_road_pieces(&self) -> [BitBoard; 2] {
+        [
+            self.road_pieces(Color::White),
+            self.road_pieces(Color::Black),
+        ]
+    }
+
+    fn count_all_pieces(&self) -> u32 {
+        squares_iterator::<S>()
+            .map(|square| self[square].len() as u32)
+            .sum()
+    }
+
+    #[inline(never)]
+    pub fn group_data(&self) -> GroupData<S> {
+        let mut group_data = GroupData {
+            white_flat_stones: self.top_stones(WhiteFlat),
+            black_flat_stones: self.top_stones(BlackFlat),
+            white_walls: self.top_stones(WhiteWall),
+            black_walls: self.top_stones(BlackWall),
+            white_caps: self.top_stones(WhiteCap),
+            black_caps: self.top_stones(BlackCap),
+            ..GroupData::default()
+        };
+
+        // Label each group of connected road pieces with an id, starting from 1
+        let mut id = 1;
+        for color in [Color::White, Color::Black] {
+            let mut road_pieces = self.road_pieces(color);
+            while let Some(square) = road_pieces.into_iter().next() {
+                let group = BitBoard::empty().set(square.0).flood_fill::<S>(road_pieces);
+                let mut edge_connection = GroupEdgeConnection::default();
+                for square in group {
+                    group_data.groups[square] = id;
+                    edge_connection = edge_connection.connect_square::<S>(square);
+                }
+                group_data.amount_in_group[id as usize] = (group.count(), edge_connection);
+                road_pieces &= !group;
+                id += 1;
             }
         }
+        group_data.amount_in_group[0].0 = (S * S) as u8
+            - (self.road_pieces(Color::White) | self.road_pieces(Color::Black)).count();
+
+        group_data.white_critical_squares = self.critical_squares(Color::White);
+        group_data.black_critical_squares = self.critical_squares(Color::Black);
+
         group_data
     }
 
@@ -663,62 +1063,63 @@ impl<const S: usize> Position<S> {
             .chain(std::iter::once(self[square].top_stone()))
     }
 
-    pub(crate) fn game_result_with_group_data(
-        &self,
-        group_data: &GroupData<S>,
-    ) -> Option<GameResult> {
+    /// Squares where a road piece of the given color would complete a road
+    fn critical_squares(&self, color: Color) -> BitBoard {
+        let [first_rank, last_rank, first_file, last_file] = Self::edges();
+        let [connected_first_rank, connected_last_rank, connected_first_file, connected_last_file] =
+            self.edge_connections[color as usize];
+        // Squares on the edge, or next to a group connected to the edge
+        let next_to_edge = |edge: BitBoard, connected: BitBoard| connected.adjacent::<S>() | edge;
+        (next_to_edge(first_rank, connected_first_rank)
+            & next_to_edge(last_rank, connected_last_rank))
+            | (next_to_edge(first_file, connected_first_file)
+                & next_to_edge(last_file, connected_last_file))
+    }
+
+    /// Check if the player has completed a road
+    pub(crate) fn has_road(&self, color: Color) -> bool {
+        !self.road_squares(color).is_empty()
+    }
+
+    /// The squares of the player's groups that connect opposite edges. Empty if the player has no road
+    fn road_squares(&self, color: Color) -> BitBoard {
+        let [first_rank, last_rank, first_file, last_file] = self.edge_connections[color as usize];
+        (first_rank & last_rank) | (first_file & last_file)
+    }
+
+    fn is_repetition_draw(&self) -> bool {
         let repetitions = self
             .hash_history
             .iter()
             .filter(|hash| **hash == self.hash)
             .count();
+        self.rules.repetition_draws() && repetitions >= 2
+    }
 
-        if repetitions >= 2 {
+    fn game_result_from_scratch(&self) -> Option<GameResult> {
+        if self.is_repetition_draw() {
             return Some(GameResult::Draw);
         }
 
-        if group_data
-            .amount_in_group
-            .iter()
-            .any(|(_, group_connection)| group_connection.is_winning())
-        {
-            let highest_component_id = group_data
-                .amount_in_group
-                .iter()
-                .enumerate()
-                .skip(1)
-                .find(|(_i, v)| (**v).0 == 0)
-                .map(|(i, _v)| i)
-                .unwrap_or(S * S + 1) as u8;
-
-            if let Some(square) = self.is_win_by_road(&group_data.groups, highest_component_id) {
-                debug_assert!(self[square].top_stone().unwrap().is_road_piece());
-                return if self[square].top_stone().unwrap().color() == Color::White {
-                    Some(GameResult::WhiteWin)
-                } else {
-                    Some(GameResult::BlackWin)
+        // If both players have a road, the player who just moved wins.
+        // If only the side to move has a road, the last move was a suicide
+        for color in [!self.side_to_move(), self.side_to_move()] {
+            if self.has_road(color) {
+                return match color {
+                    Color::White => Some(WhiteWin),
+                    Color::Black => Some(BlackWin),
                 };
-            };
-            unreachable!(
-                "Board has winning connection, but isn't winning\n{:?}",
-                self
-            )
+            }
         }
 
         if (self.white_stones_left == 0 && self.white_caps_left == 0)
             || (self.black_stones_left == 0 && self.black_caps_left == 0)
-            || utils::squares_iterator::<S>().all(|square| !self[square].is_empty())
+            || self.empty_squares().is_empty()
         {
-            // Count points
-            let mut white_points = 0;
-            let mut black_points = 0;
-            for square in utils::squares_iterator::<S>() {
-                match self[square].top_stone() {
-                    Some(WhiteFlat) => white_points += 1,
-                    Some(BlackFlat) => black_points += 1,
-                    _ => (),
-                }
-            }
+            // Count points, in half flats to account for komi
+            let white_points = 2 * self.top_stones(WhiteFlat).count() as i16;
+            let black_points = 2 * self.top_stones(BlackFlat).count() as i16
+                + self.rules.komi().half_komi() as i16;
             match white_points.cmp(&black_points) {
                 Ordering::Greater => Some(WhiteWin),
                 Ordering::Less => Some(BlackWin),
@@ -729,35 +1130,33 @@ impl<const S: usize> Position<S> {
         }
     }
 
-    /// Check if either side has completed a road
-    /// Returns one of the winning squares in the road
-    pub(crate) fn is_win_by_road(
-        &self,
-        components: &AbstractBoard<u8, S>,
-        highest_component_id: u8,
-    ) -> Option<Square> {
-        // If the side to move is already winning,
-        // the last move was either a suicide, or a double win
-        let mut suicide_win_square = None;
-
-        // TODO: Include highest id?
-        for id in 1..highest_component_id {
-            if (components.raw[0].iter().any(|&cell| cell == id)
-                && components.raw[S - 1].iter().any(|&cell| cell == id))
-                || ((0..S).any(|y| components.raw[y][0] == id)
-                    && (0..S).any(|y| components.raw[y][S - 1] == id))
-            {
-                let square = utils::squares_iterator::<S>()
-                    .find(|&sq| components[sq] == id)
-                    .unwrap();
-                if self[square].top_stone.unwrap().color() == self.side_to_move() {
-                    suicide_win_square = Some(square)
+    /// The result of the game, along with how it ended. Returns `None` if the game is still ongoing.
+    pub fn game_outcome(&self) -> Option<GameOutcome> {
+        let result = self.game_result()?;
+        let reason = if self.is_repetition_draw() {
+            GameEndReason::Repetition
+        } else {
+            let road_squares = match result {
+                WhiteWin => self.road_squares(Color::White),
+                BlackWin => self.road_squares(Color::Black),
+                Draw => BitBoard::default(),
+            };
+            if !road_squares.is_empty() {
+                GameEndReason::Road(road_squares.into_iter().collect())
+            } else {
+                let flat_count = FlatCount {
+                    white_flats: self.top_stones(WhiteFlat).count(),
+                    black_flats: self.top_stones(BlackFlat).count(),
+                    komi: self.komi(),
+                };
+                if self.empty_squares().is_empty() {
+                    GameEndReason::BoardFill(flat_count)
                 } else {
-                    return Some(square);
+                    GameEndReason::ReservesDepleted(flat_count)
                 }
             }
-        }
-        suicide_win_square
+        };
+        Some(GameOutcome { result, reason })
     }
 
     pub(crate) fn static_eval_with_params_and_data(
@@ -777,18 +1176,24 @@ impl<const S: usize> Position<S> {
 
     pub fn value_params() -> &'static [f32] {
         match S {
+            3 => &VALUE_PARAMS_3S,
             4 => &VALUE_PARAMS_4S,
             5 => &VALUE_PARAMS_5S,
             6 => &VALUE_PARAMS_6S,
+            7 => &VALUE_PARAMS_7S,
+            8 => &VALUE_PARAMS_8S,
             _ => unimplemented!("{}s is not supported.", S),
         }
     }
 
     pub fn policy_params() -> &'static [f32] {
         match S {
+            3 => &POLICY_PARAMS_3S,
             4 => &POLICY_PARAMS_4S,
             5 => &POLICY_PARAMS_5S,
             6 => &POLICY_PARAMS_6S,
+            7 => &POLICY_PARAMS_7S,
+            8 => &POLICY_PARAMS_8S,
             _ => unimplemented!("{}s is not supported.", S),
         }
     }
@@ -871,15 +1276,16 @@ impl<const S: usize> PositionTrait for Position<S> {
 
     /// Adds all legal moves to the provided vector. Some notes on the interpretation of the rules:
     /// * Suicide moves are considered legal, and are generated like any other move.
-    /// This includes moves that complete a road for the opponent without creating an own road,
-    /// and moves that fill the board when that would result in an immediate loss.
+    ///   This includes moves that complete a road for the opponent without creating an own road,
+    ///   and moves that fill the board when that would result in an immediate loss.
     ///
     /// * Capstones are not counted towards a flat win, but all capstones must also be placed to trigger a flat win.
+    ///   Black's flat count is increased by the position's komi.
     ///
-    /// * A game is considered a draw after a three-fold repetition of the same position.
+    /// * A game is considered a draw after a three-fold repetition of the same position, unless disabled by the position's rule set.
     fn generate_moves(&self, moves: &mut Vec<Self::Move>) {
         match self.half_moves_played() {
-            0 | 1 => {
+            0 | 1 if self.rules.opening_swap() => {
                 for square in utils::squares_iterator::<S>() {
                     if self[square].is_empty() {
                         moves.push(Move::Place(Flat, square));
@@ -895,17 +1301,19 @@ impl<const S: usize> PositionTrait for Position<S> {
 
     fn do_move(&mut self, mv: Self::Move) -> Self::ReverseMove {
         self.hash_history.push(self.hash);
+        let old_road_pieces = self.all_road_pieces();
         let reverse_move = match mv {
             Move::Place(role, to) => {
                 debug_assert!(self[to].is_empty());
                 // On the first move, the players place the opponent's color
-                let color_to_place = if self.half_moves_played() > 1 {
+                let color_to_place = if self.half_moves_played() > 1 || !self.rules.opening_swap() {
                     self.side_to_move()
                 } else {
                     !self.side_to_move()
                 };
                 let piece = Piece::from_role_color(role, color_to_place);
-                self[to].push(piece);
+                self.cells[to].push(piece);
+                self.update_top_stones(to);
 
                 match (color_to_place, role) {
                     (Color::White, Flat) => self.white_stones_left -= 1,
@@ -944,8 +1352,8 @@ impl<const S: usize> PositionTrait for Position<S> {
 
                     for _ in pieces_to_leave..self[from].len() {
                         let piece = self[from].get(pieces_to_leave).unwrap();
-                        self[to].push(piece);
-                        self[from].remove(pieces_to_leave);
+                        self.cells[to].push(piece);
+                        self.cells[from].remove(pieces_to_leave);
                     }
 
                     from = to;
@@ -953,6 +1361,7 @@ impl<const S: usize> PositionTrait for Position<S> {
 
                 for sq in <MoveIterator<S>>::new(square, direction, stack_movement) {
                     self.hash ^= self.zobrist_hash_for_square(sq);
+                    self.update_top_stones(sq);
                 }
 
                 let mut movements = StackMovement::new();
@@ -978,16 +1387,19 @@ impl<const S: usize> PositionTrait for Position<S> {
         };
 
         debug_assert_eq!(
-            2 * (starting_stones::<S>() + starting_capstones::<S>())
-                - self.white_stones_left
-                - self.black_stones_left
-                - self.white_caps_left
-                - self.black_caps_left,
+            2 * (self.rules.starting_stones() as u32 + self.rules.starting_capstones() as u32)
+                - self.white_stones_left as u32
+                - self.black_stones_left as u32
+                - self.white_caps_left as u32
+                - self.black_caps_left as u32,
             self.count_all_pieces(),
             "Wrong number of stones on board:\n{:?}",
             self
         );
 
+        self.update_edge_connections(old_road_pieces);
+        debug_assert_eq!(self.edge_connections, self.edge_connections_from_scratch());
+
         self.moves.push(mv);
         self.half_moves_played += 1;
 
@@ -999,13 +1411,18 @@ impl<const S: usize> PositionTrait for Position<S> {
     }
 
     fn reverse_move(&mut self, reverse_move: Self::ReverseMove) {
+        let old_road_pieces = self.all_road_pieces();
         match reverse_move {
             ReverseMove::Place(square) => {
-                let piece = self[square].pop().unwrap();
+                let piece = self.cells[square].pop().unwrap();
+                self.update_top_stones(square);
 
                 self.hash ^= zobrist_top_stones::<S>(square, piece);
 
-                debug_assert!(piece.color() != self.side_to_move() || self.half_moves_played() < 3);
+                debug_assert!(
+                    piece.color() != self.side_to_move()
+                        || (self.half_moves_played() < 3 && self.rules.opening_swap())
+                );
 
                 match piece {
                     WhiteFlat | WhiteWall => self.white_stones_left += 1,
@@ -1029,25 +1446,29 @@ impl<const S: usize> PositionTrait for Position<S> {
 
                     for _ in pieces_to_leave..self[square].len() {
                         let piece = self[square].get(pieces_to_leave).unwrap();
-                        self[to].push(piece);
-                        self[square].remove(pieces_to_leave);
+                        self.cells[to].push(piece);
+                        self.cells[square].remove(pieces_to_leave);
                     }
                     square = to;
                 }
 
                 if flattens_wall {
                     match self[from].top_stone().unwrap().color() {
-                        Color::White => self[from].replace_top(WhiteWall),
-                        Color::Black => self[from].replace_top(BlackWall),
+                        Color::White => self.cells[from].replace_top(WhiteWall),
+                        Color::Black => self.cells[from].replace_top(BlackWall),
                     };
                 };
 
                 for square in <MoveIterator<S>>::new(from, direction, stack_movement) {
                     self.hash ^= self.zobrist_hash_for_square(square);
+                    self.update_top_stones(square);
                 }
             }
         }
 
+        self.update_edge_connections(old_road_pieces);
+        debug_assert_eq!(self.edge_connections, self.edge_connections_from_scratch());
+
         self.moves.pop();
         self.hash_history.pop();
         self.half_moves_played -= 1;
@@ -1058,7 +1479,7 @@ impl<const S: usize> PositionTrait for Position<S> {
     }
 
     fn game_result(&self) -> Option<GameResult> {
-        self.game_result_with_group_data(&self.group_data())
+        self.game_result_from_scratch()
     }
 }
 
@@ -1127,159 +1548,10 @@ impl<const S: usize> pgn_traits::PgnPosition for Position<S> {
         ("1/2-1/2", Some(GameResult::Draw)),
     ];
 
-    const POSSIBLE_MOVE_ANNOTATIONS: &'static [&'static str] = &["''", "'", "*", "!", "?"];
+    const POSSIBLE_MOVE_ANNOTATIONS: &'static [&'static str] = &["''", "'", "\"", "*", "!", "?"];
 
     fn from_fen(fen: &str) -> Result<Self, pgn_traits::Error> {
-        let fen_words: Vec<&str> = fen.split_whitespace().collect();
-
-        if fen_words.len() < 3 {
-            return Err(pgn_traits::Error::new_parse_error(format!(
-                "Couldn't parse TPS string \"{}\", missing move counter.",
-                fen
-            )));
-        }
-        if fen_words.len() > 3 {
-            return Err(pgn_traits::Error::new_parse_error(format!(
-                "Couldn't parse TPS string \"{}\", unexpected \"{}\"",
-                fen, fen_words[3]
-            )));
-        }
-
-        let fen_rows: Vec<&str> = fen_words[0].split('/').collect();
-        if fen_rows.len() != S {
-            return Err(pgn_traits::Error::new_parse_error(format!(
-                "Couldn't parse TPS string \"{}\", had {} rows instead of {}.",
-                fen,
-                fen_rows.len(),
-                S
-            )));
-        }
-
-        let rows: Vec<[Stack; S]> = fen_rows
-            .into_iter()
-            .map(parse_row)
-            .collect::<Result<_, _>>()
-            .map_err(|e| {
-                pgn_traits::Error::new_caused_by(
-                    pgn_traits::ErrorKind::ParseError,
-                    format!("Couldn't parse TPS string \"{}\"", fen),
-                    e,
-                )
-            })?;
-        let mut position = Position::default();
-        for square in utils::squares_iterator::<S>() {
-            let (file, rank) = (square.file::<S>(), square.rank::<S>());
-            let stack = rows[rank as usize][file as usize];
-            for piece in stack.into_iter() {
-                match piece {
-                    WhiteFlat | WhiteWall => position.white_stones_left -= 1,
-                    WhiteCap => position.white_caps_left -= 1,
-                    BlackFlat | BlackWall => position.black_stones_left -= 1,
-                    BlackCap => position.black_caps_left -= 1,
-                }
-            }
-            position[square] = stack;
-        }
-
-        match fen_words[1] {
-            "1" => position.to_move = Color::White,
-            "2" => position.to_move = Color::Black,
-            s => {
-                return Err(pgn_traits::Error::new_parse_error(format!(
-                    "Error parsing TPS \"{}\": Got bad side to move \"{}\"",
-                    fen, s
-                )))
-            }
-        }
-
-        match fen_words[2].parse::<usize>() {
-            Ok(n) => match position.side_to_move() {
-                Color::White => position.half_moves_played = (n - 1) * 2,
-                Color::Black => position.half_moves_played = (n - 1) * 2 + 1,
-            },
-            Err(e) => {
-                return Err(pgn_traits::Error::new_caused_by(
-                    pgn_traits::ErrorKind::ParseError,
-                    format!(
-                        "Error parsing TPS \"{}\": Got bad move number \"{}\"",
-                        fen, fen_words[2]
-                    ),
-                    e,
-                ))
-            }
-        }
-
-        position.hash = position.zobrist_hash_from_scratch();
-
-        return Ok(position);
-
-        fn parse_row<const S: usize>(row_str: &str) -> Result<[Stack; S], pgn_traits::Error> {
-            let mut column_id = 0;
-            let mut row = [Stack::default(); S];
-            let mut row_str_iter = row_str.chars().peekable();
-            while column_id < S as u8 {
-                match row_str_iter.peek() {
-                    None => {
-                        return Err(pgn_traits::Error::new_parse_error(format!(
-                            "Couldn't parse row \"{}\": not enough pieces",
-                            row_str
-                        )))
-                    }
-                    Some('x') => {
-                        row_str_iter.next();
-                        if let Some(n) = row_str_iter.peek().and_then(|ch| ch.to_digit(10)) {
-                            row_str_iter.next();
-                            column_id += n as u8;
-                        } else {
-                            column_id += 1;
-                        }
-                        if let Some(',') | None = row_str_iter.peek() {
-                            row_str_iter.next();
-                        } else {
-                            return Err(pgn_traits::Error::new_parse_error(format!(
-                                "Expected ',' on row \"{}\", found {:?}",
-                                row_str,
-                                row_str_iter.next()
-                            )));
-                        }
-                    }
-                    Some('1') | Some('2') => {
-                        let stack = &mut row[column_id as usize];
-                        loop {
-                            match row_str_iter.next() {
-                                Some('1') => stack.push(Piece::from_role_color(Flat, Color::White)),
-                                Some('2') => stack.push(Piece::from_role_color(Flat, Color::Black)),
-                                Some('S') => {
-                                    let piece = stack.pop().unwrap();
-                                    stack.push(Piece::from_role_color(Wall, piece.color()));
-                                }
-                                Some('C') => {
-                                    let piece = stack.pop().unwrap();
-                                    stack.push(Piece::from_role_color(Cap, piece.color()));
-                                }
-                                Some(',') | None => {
-                                    column_id += 1;
-                                    break;
-                                }
-                                Some(ch) => {
-                                    return Err(pgn_traits::Error::new_parse_error(format!(
-                                        "Expected '1', '2', 'S' or 'C' on row \"{}\", found {}",
-                                        row_str, ch
-                                    )))
-                                }
-                            }
-                        }
-                    }
-                    Some(x) => {
-                        return Err(pgn_traits::Error::new_parse_error(format!(
-                            "Unexpected '{}' in row \"{}\".",
-                            x, row_str
-                        )))
-                    }
-                }
-            }
-            Ok(row)
-        }
+        Self::from_fen_with_rules(fen, RuleSet::default())
     }
 
     fn to_fen(&self) -> String {
@@ -1333,30 +1605,3 @@ impl<const S: usize> pgn_traits::PgnPosition for Position<S> {
         self.move_to_san(mv)
     }
 }
-
-pub(crate) fn connected_components_graph<const S: usize>(
-    road_pieces: BitBoard,
-    components: &mut AbstractBoard<u8, S>,
-    id: &mut u8,
-) {
-    for square in utils::squares_iterator::<S>() {
-        if components[square] == 0 && road_pieces.get(square.0) {
-            connect_component(road_pieces, components, square, *id);
-            *id += 1;
-        }
-    }
-}
-
-fn connect_component<const S: usize>(
-    road_pieces: BitBoard,
-    components: &mut AbstractBoard<u8, S>,
-    square: Square,
-    id: u8,
-) {
-    components[square] = id;
-    for neighbour in square.neighbours::<S>() {
-        if road_pieces.get(neighbour.0) && components[neighbour] == 0 {
-            connect_component(road_pieces, components, neighbour, id);
-        }
-    }
-}
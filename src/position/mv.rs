@@ -1,3 +1,5 @@
+use std::error;
+use std::fmt;
 use std::fmt::Write;
 use std::iter;
 use std::str::FromStr;
@@ -236,3 +238,76 @@ pub enum ReverseMove {
     Place(Square),
     Move(Square, Direction, StackMovement, bool),
 }
+
+/// The reason a move is illegal in a position. Returned by `Position::try_do_move`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum IllegalMoveError {
+    /// The move refers to a square outside the board
+    SquareOutsideBoard,
+    /// The first move of each player must place a flatstone
+    OpeningMoveNotFlat,
+    OccupiedSquare,
+    NoStonesLeft,
+    NoCapstonesLeft,
+    EmptySquare,
+    OpponentsStack,
+    EmptyStackMovement,
+    /// Tried to pick up more pieces than the stack contains
+    NotEnoughPieces {
+        taken: u8,
+        stack_height: u8,
+    },
+    /// Tried to pick up more pieces than the board size
+    CarryLimitExceeded {
+        taken: u8,
+        limit: u8,
+    },
+    /// Every square the stack moves over must get at least one piece
+    MustDropPiece,
+    /// The stack would be spread past the edge of the board
+    SpreadOffBoard,
+    /// Walls can only be flattened by a capstone moving alone, at the end of its spread
+    BlockedByWall,
+    BlockedByCapstone,
+}
+
+impl fmt::Display for IllegalMoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use IllegalMoveError::*;
+        match self {
+            SquareOutsideBoard => write!(f, "square is outside the board"),
+            OpeningMoveNotFlat => write!(
+                f,
+                "the first move of each player must place a flatstone of the opponent's color"
+            ),
+            OccupiedSquare => write!(f, "cannot place a stone on an occupied square"),
+            NoStonesLeft => write!(f, "no stones left to place"),
+            NoCapstonesLeft => write!(f, "no capstones left to place"),
+            EmptySquare => write!(f, "cannot move from an empty square"),
+            OpponentsStack => write!(f, "cannot move a stack controlled by the opponent"),
+            EmptyStackMovement => write!(f, "the move does not move any pieces"),
+            NotEnoughPieces {
+                taken,
+                stack_height,
+            } => write!(
+                f,
+                "cannot take {} pieces from a stack of height {}",
+                taken, stack_height
+            ),
+            CarryLimitExceeded { taken, limit } => write!(
+                f,
+                "cannot carry {} pieces, the carry limit is {}",
+                taken, limit
+            ),
+            MustDropPiece => write!(f, "must drop at least one piece on every square"),
+            SpreadOffBoard => write!(f, "cannot spread past the edge of the board"),
+            BlockedByWall => write!(
+                f,
+                "a wall is in the way, and can only be flattened by a lone capstone"
+            ),
+            BlockedByCapstone => write!(f, "a capstone is in the way"),
+        }
+    }
+}
+
+impl error::Error for IllegalMoveError {}
@@ -0,0 +1,81 @@
+use std::fmt;
+
+use board_game_traits::GameResult;
+
+use crate::position::{Komi, Square};
+
+/// The result of a finished game, and how it ended. Returned by `Position::game_outcome`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct GameOutcome {
+    pub result: GameResult,
+    pub reason: GameEndReason,
+}
+
+/// Why a game ended
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum GameEndReason {
+    /// The winner completed a road. Contains every square of the winner's groups that connect opposite edges.
+    Road(Vec<Square>),
+    /// The game was decided on flats, because the board was filled
+    BoardFill(FlatCount),
+    /// The game was decided on flats, because one player placed all their stones and capstones
+    ReservesDepleted(FlatCount),
+    /// The same position occurred for the third time
+    Repetition,
+}
+
+/// The flatstones each player controls at the end of a game decided on flats.
+/// Komi is not included in `black_flats`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct FlatCount {
+    pub white_flats: u8,
+    pub black_flats: u8,
+    pub komi: Komi,
+}
+
+impl GameOutcome {
+    /// The result in PTN notation, where road and flat wins are written as `R-0` and `F-0`.
+    pub fn ptn_result_string(&self) -> &'static str {
+        match (self.result, &self.reason) {
+            (GameResult::Draw, _) => "1/2-1/2",
+            (GameResult::WhiteWin, GameEndReason::Road(_)) => "R-0",
+            (GameResult::BlackWin, GameEndReason::Road(_)) => "0-R",
+            (GameResult::WhiteWin, _) => "F-0",
+            (GameResult::BlackWin, _) => "0-F",
+        }
+    }
+}
+
+impl fmt::Display for GameOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.result {
+            GameResult::WhiteWin => write!(f, "White wins")?,
+            GameResult::BlackWin => write!(f, "Black wins")?,
+            GameResult::Draw => write!(f, "Draw")?,
+        }
+        match &self.reason {
+            GameEndReason::Road(_) => write!(f, " by road"),
+            GameEndReason::BoardFill(flat_count) => {
+                write!(f, " on flats, {}, after the board was filled", flat_count)
+            }
+            GameEndReason::ReservesDepleted(flat_count) => {
+                write!(
+                    f,
+                    " on flats, {}, after a player ran out of stones",
+                    flat_count
+                )
+            }
+            GameEndReason::Repetition => write!(f, " by threefold repetition"),
+        }
+    }
+}
+
+impl fmt::Display for FlatCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} to {}", self.white_flats, self.black_flats)?;
+        if self.komi != Komi::default() {
+            write!(f, " with komi {}", self.komi)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::position::{starting_capstones, starting_stones, Komi, MAX_STACK_HEIGHT};
+
+/// The most stones, not including capstones, that each player can start with
+pub const MAX_STARTING_STONES: u8 = 60;
+pub const MAX_STARTING_CAPSTONES: u8 = 4;
+
+// Every piece in the game must fit in a single stack
+const _: () = assert!(
+    2 * (MAX_STARTING_STONES as u16 + MAX_STARTING_CAPSTONES as u16) <= MAX_STACK_HEIGHT as u16
+);
+
+/// The rules a game is played with. The default is the standard rules for the board size, without komi.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RuleSet<const S: usize> {
+    starting_stones: u8,
+    starting_capstones: u8,
+    komi: Komi,
+    opening_swap: bool,
+    repetition_draws: bool,
+}
+
+impl<const S: usize> Default for RuleSet<S> {
+    fn default() -> Self {
+        RuleSet {
+            starting_stones: starting_stones::<S>(),
+            starting_capstones: starting_capstones::<S>(),
+            komi: Komi::default(),
+            opening_swap: true,
+            repetition_draws: true,
+        }
+    }
+}
+
+impl<const S: usize> RuleSet<S> {
+    /// Number of stones, not including capstones, that each player starts with.
+    /// Returns `None` if it is more than `MAX_STARTING_STONES`
+    pub fn add_starting_stones(mut self, starting_stones: u8) -> Option<Self> {
+        if starting_stones > MAX_STARTING_STONES {
+            return None;
+        }
+        self.starting_stones = starting_stones;
+        Some(self)
+    }
+
+    /// Returns `None` if the number of capstones is more than `MAX_STARTING_CAPSTONES`
+    pub fn add_starting_capstones(mut self, starting_capstones: u8) -> Option<Self> {
+        if starting_capstones > MAX_STARTING_CAPSTONES {
+            return None;
+        }
+        self.starting_capstones = starting_capstones;
+        Some(self)
+    }
+
+    pub fn add_komi(mut self, komi: Komi) -> Self {
+        self.komi = komi;
+        self
+    }
+
+    /// Play without the opening swap, so that the first move of each player places their own stone
+    pub fn without_opening_swap(mut self) -> Self {
+        self.opening_swap = false;
+        self
+    }
+
+    /// Never end the game on a threefold repetition
+    pub fn without_repetition_draws(mut self) -> Self {
+        self.repetition_draws = false;
+        self
+    }
+
+    pub fn starting_stones(&self) -> u8 {
+        self.starting_stones
+    }
+
+    pub fn starting_capstones(&self) -> u8 {
+        self.starting_capstones
+    }
+
+    pub fn komi(&self) -> Komi {
+        self.komi
+    }
+
+    /// Whether each player places a flatstone of the opponent's color on their first move
+    pub fn opening_swap(&self) -> bool {
+        self.opening_swap
+    }
+
+    /// Whether a threefold repetition of the same position is a draw
+    pub fn repetition_draws(&self) -> bool {
+        self.repetition_draws
+    }
+}
@@ -0,0 +1,31 @@
+use std::convert::TryFrom;
+
+use pgn_traits::PgnPosition;
+use serde::{Deserialize, Serialize};
+
+use crate::position::{Position, RuleSet};
+
+/// Serialized form of `Position`. The board is stored as TPS, so the move history is not preserved.
+/// In particular, repetitions from before the position was serialized are not detected after deserializing.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub(crate) struct SerializedPosition<const S: usize> {
+    tps: String,
+    rules: RuleSet<S>,
+}
+
+impl<const S: usize> From<Position<S>> for SerializedPosition<S> {
+    fn from(position: Position<S>) -> Self {
+        SerializedPosition {
+            tps: position.to_fen(),
+            rules: *position.rules(),
+        }
+    }
+}
+
+impl<const S: usize> TryFrom<SerializedPosition<S>> for Position<S> {
+    type Error = pgn_traits::Error;
+
+    fn try_from(serialized: SerializedPosition<S>) -> Result<Self, Self::Error> {
+        Position::from_fen_with_rules(&serialized.tps, serialized.rules)
+    }
+}
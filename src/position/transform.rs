@@ -0,0 +1,114 @@
+use crate::position::utils::Direction::{East, North, South, West};
+use crate::position::{Direction, Move, Square};
+
+/// One of the 8 symmetries of the board: an optional mirroring along the vertical axis,
+/// followed by 0 to 3 clockwise quarter turns.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Transform {
+    mirror: bool,
+    rotations: u8,
+}
+
+impl Transform {
+    pub fn identity() -> Self {
+        Transform::default()
+    }
+
+    /// All 8 symmetries, starting with the identity
+    pub fn all() -> [Transform; 8] {
+        let mut transforms = [Transform::identity(); 8];
+        for (i, transform) in transforms.iter_mut().enumerate() {
+            *transform = Transform {
+                mirror: i >= 4,
+                rotations: i as u8 % 4,
+            };
+        }
+        transforms
+    }
+
+    /// Mirror the board, so that the a-file and the last file swap places
+    pub fn mirror() -> Self {
+        Transform {
+            mirror: true,
+            rotations: 0,
+        }
+    }
+
+    /// Rotate the board a quarter turn clockwise
+    pub fn rotation() -> Self {
+        Transform {
+            mirror: false,
+            rotations: 1,
+        }
+    }
+
+    /// Apply `self`, then `other`
+    pub fn then(self, other: Transform) -> Self {
+        // Mirroring after a rotation is the same as mirroring before the opposite rotation
+        let rotations = if other.mirror {
+            4 - self.rotations
+        } else {
+            self.rotations
+        };
+        Transform {
+            mirror: self.mirror != other.mirror,
+            rotations: (rotations + other.rotations) % 4,
+        }
+    }
+
+    /// The transform that undoes this one
+    pub fn inverse(self) -> Self {
+        if self.mirror {
+            self
+        } else {
+            Transform {
+                mirror: false,
+                rotations: (4 - self.rotations) % 4,
+            }
+        }
+    }
+
+    pub fn apply_square<const S: usize>(self, square: Square) -> Square {
+        let (mut file, mut rank) = (square.file::<S>(), square.rank::<S>());
+        if self.mirror {
+            file = S as u8 - file - 1;
+        }
+        for _ in 0..self.rotations {
+            let old_file = file;
+            file = S as u8 - rank - 1;
+            rank = old_file;
+        }
+        Square::from_rank_file::<S>(rank, file)
+    }
+
+    pub fn apply_direction(self, mut direction: Direction) -> Direction {
+        if self.mirror {
+            direction = match direction {
+                West => East,
+                East => West,
+                _ => direction,
+            };
+        }
+        for _ in 0..self.rotations {
+            direction = match direction {
+                North => East,
+                East => South,
+                South => West,
+                West => North,
+            };
+        }
+        direction
+    }
+
+    /// Map a move in a position to the same move in the transformed position
+    pub fn apply_move<const S: usize>(self, mv: &Move) -> Move {
+        match mv {
+            Move::Place(role, square) => Move::Place(*role, self.apply_square::<S>(*square)),
+            Move::Move(square, direction, stack_movement) => Move::Move(
+                self.apply_square::<S>(*square),
+                self.apply_direction(*direction),
+                *stack_movement,
+            ),
+        }
+    }
+}
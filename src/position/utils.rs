@@ -7,11 +7,11 @@ use board_game_traits::Color;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
-use crate::position::bitboard::BitBoard;
 use crate::position::color_trait::{BlackTr, ColorTr, WhiteTr};
 use crate::position::utils::Direction::*;
 use crate::position::Piece::{BlackCap, BlackFlat, BlackWall, WhiteCap, WhiteFlat, WhiteWall};
 use crate::position::Role::{Cap, Flat, Wall};
+use crate::position::MAX_BOARD_SIZE;
 
 /// A location on the board. Can be used to index a `Board`.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -21,7 +21,7 @@ pub struct Square(pub u8);
 impl Square {
     pub fn from_rank_file<const S: usize>(rank: u8, file: u8) -> Self {
         debug_assert!(rank < S as u8 && file < S as u8);
-        Square(rank * S as u8 + file as u8)
+        Square(rank * S as u8 + file)
     }
 
     pub fn rank<const S: usize>(self) -> u8 {
@@ -231,12 +231,17 @@ impl ops::Not for Piece {
     }
 }
 
+/// The most pieces a stack can hold. Only the top stone's role is stored,
+/// and the colors of the pieces below it are stored as bits in a `u128`.
+pub const MAX_STACK_HEIGHT: u8 = 129;
+
 /// The contents of a square on the board, consisting of zero or more pieces
 #[derive(Copy, Clone, PartialEq, Eq, Debug, Default, Hash)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Stack {
     pub(crate) top_stone: Option<Piece>,
-    pub(crate) bitboard: BitBoard,
+    /// The colors of the pieces below the top stone, from the bottom up. White pieces are set.
+    pub(crate) bitboard: u128,
     pub(crate) height: u8,
 }
 
@@ -247,7 +252,7 @@ impl Stack {
             None
         } else if i == self.height - 1 {
             self.top_stone
-        } else if self.bitboard.get(i) {
+        } else if self.bitboard & 1 << i != 0 {
             Some(WhiteFlat)
         } else {
             Some(BlackFlat)
@@ -262,8 +267,9 @@ impl Stack {
     ///
     /// Any piece already on the stack will be flattened, including capstones
     pub fn push(&mut self, piece: Piece) {
+        debug_assert!(self.height < MAX_STACK_HEIGHT);
         if self.height > 0 && self.top_stone.unwrap().color() == Color::White {
-            self.bitboard = self.bitboard.set(self.height - 1);
+            self.bitboard |= 1 << (self.height - 1);
         }
         self.top_stone = Some(piece);
         self.height += 1;
@@ -276,12 +282,12 @@ impl Stack {
         debug_assert_ne!(self.height, 0);
         let old_piece = self.top_stone;
         if self.height > 1 {
-            let piece = if self.bitboard.get(self.height - 2) {
+            let piece = if self.bitboard & 1 << (self.height - 2) != 0 {
                 Piece::WhiteFlat
             } else {
                 Piece::BlackFlat
             };
-            self.bitboard = self.bitboard.clear(self.height - 2);
+            self.bitboard &= !(1 << (self.height - 2));
             self.top_stone = Some(piece);
         } else {
             self.top_stone = None;
@@ -298,17 +304,14 @@ impl Stack {
         if i == self.height - 1 {
             self.pop().expect("Tried to remove from empty stack")
         } else {
-            let piece = if self.bitboard.get(i) {
+            let piece = if self.bitboard & 1 << i != 0 {
                 Piece::WhiteFlat
             } else {
                 Piece::BlackFlat
             };
-            let pieces_below = self.bitboard & BitBoard::lower_n_bits(i);
-            let pieces_above = self.bitboard & !BitBoard::lower_n_bits(i + 1);
-            self.bitboard = pieces_below
-                | BitBoard {
-                    board: pieces_above.board >> 1,
-                };
+            let pieces_below = self.bitboard & ((1 << i) - 1);
+            let pieces_above = self.bitboard >> i >> 1;
+            self.bitboard = pieces_below | pieces_above << i;
             self.height -= 1;
             piece
         }
@@ -409,7 +412,7 @@ impl StackMovement {
             "Stack movement cannot grow any more: {:#b}",
             self.data
         );
-        debug_assert!(movement.pieces_to_take < 8);
+        debug_assert!(movement.pieces_to_take as usize <= MAX_BOARD_SIZE);
         self.data |= (movement.pieces_to_take as u32) << (length * 4);
         self.data &= (1_u32 << 28).overflowing_sub(1).0;
         self.data |= (length + 1) << 28;
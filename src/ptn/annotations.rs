@@ -0,0 +1,260 @@
+//! Typed move annotations, and structured tags in move comments.
+//!
+//! Comment tags are written as `[%tag value]` commands at the start of the comment, like in PGN:
+//!
+//! ```text
+//! 12. c3' {[%eval 0.62] [%depth 14] [%best d3] [%clk 0:04:32] Strong move}
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::ptn::{ParseError, PtnPosition};
+
+/// Marks for moves that threaten to win
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TakMark {
+    /// `'`, the move threatens to win on the next move
+    Tak,
+    /// `"`, or `''`, the move leads to a forced win
+    Tinue,
+}
+
+/// The commentator's judgement of a move
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum MoveQuality {
+    /// `??`
+    Blunder,
+    /// `?`
+    Mistake,
+    /// `?!`
+    Dubious,
+    /// `!?`
+    Interesting,
+    /// `!`
+    Good,
+    /// `!!`
+    Brilliant,
+}
+
+impl MoveQuality {
+    const ALL: [MoveQuality; 6] = [
+        MoveQuality::Blunder,
+        MoveQuality::Mistake,
+        MoveQuality::Dubious,
+        MoveQuality::Interesting,
+        MoveQuality::Good,
+        MoveQuality::Brilliant,
+    ];
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            MoveQuality::Blunder => "??",
+            MoveQuality::Mistake => "?",
+            MoveQuality::Dubious => "?!",
+            MoveQuality::Interesting => "!?",
+            MoveQuality::Good => "!",
+            MoveQuality::Brilliant => "!!",
+        }
+    }
+}
+
+/// The annotations written directly after a move, like `c3'!`
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Annotations {
+    pub tak_mark: Option<TakMark>,
+    pub quality: Option<MoveQuality>,
+}
+
+impl Annotations {
+    pub fn is_empty(&self) -> bool {
+        self.tak_mark.is_none() && self.quality.is_none()
+    }
+
+    /// Split a move string like `c3'!` into the move itself and its annotations.
+    /// The legacy `*` marker is accepted, but discarded.
+    pub fn strip_from_move(move_string: &str) -> Result<(&str, Annotations), pgn_traits::Error> {
+        let mut move_string = move_string;
+        let mut annotations = Annotations::default();
+        let mut quality_symbol = String::new();
+        loop {
+            if let Some(rest) = move_string
+                .strip_suffix("''")
+                .or_else(|| move_string.strip_suffix('"'))
+            {
+                annotations.tak_mark = Some(TakMark::Tinue);
+                move_string = rest;
+            } else if let Some(rest) = move_string.strip_suffix('\'') {
+                annotations.tak_mark = Some(TakMark::Tak);
+                move_string = rest;
+            } else if let Some(rest) = move_string.strip_suffix('*') {
+                move_string = rest;
+            } else if let Some(rest) = move_string.strip_suffix(['!', '?']) {
+                quality_symbol.insert_str(0, &move_string[rest.len()..]);
+                move_string = rest;
+            } else {
+                break;
+            }
+        }
+        if !quality_symbol.is_empty() {
+            annotations.quality = Some(
+                MoveQuality::ALL
+                    .iter()
+                    .copied()
+                    .find(|quality| quality.symbol() == quality_symbol)
+                    .ok_or_else(|| {
+                        pgn_traits::Error::new_parse_error(format!(
+                            "Unknown move annotation {}",
+                            quality_symbol
+                        ))
+                    })?,
+            );
+        }
+        Ok((move_string, annotations))
+    }
+}
+
+impl fmt::Display for Annotations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.tak_mark {
+            Some(TakMark::Tak) => write!(f, "'")?,
+            Some(TakMark::Tinue) => write!(f, "\"")?,
+            None => (),
+        }
+        if let Some(quality) = self.quality {
+            write!(f, "{}", quality.symbol())?;
+        }
+        Ok(())
+    }
+}
+
+/// Engine analysis and clock times, stored as tags in a move's comment
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CommentTags<Move> {
+    /// `[%eval]`, the winning probability for the player who made the move, between 0 and 1
+    pub eval: Option<f32>,
+    /// `[%depth]`, the search depth of the eval
+    pub depth: Option<u32>,
+    /// `[%best]`, the engine's preferred move in the position before this move
+    pub best_move: Option<Move>,
+    /// `[%clk]`, the time left on the mover's clock after the move
+    pub clock: Option<Duration>,
+}
+
+impl<Move> Default for CommentTags<Move> {
+    fn default() -> Self {
+        CommentTags {
+            eval: None,
+            depth: None,
+            best_move: None,
+            clock: None,
+        }
+    }
+}
+
+impl<Move> CommentTags<Move> {
+    pub fn is_empty(&self) -> bool {
+        self.eval.is_none()
+            && self.depth.is_none()
+            && self.best_move.is_none()
+            && self.clock.is_none()
+    }
+
+    /// Split a comment into its tags and its free text. Unknown tags are kept in the text.
+    /// `position` is the position before the move, used to read the best move.
+    pub fn parse_comment<B: PtnPosition<Move = Move>>(
+        comment: &str,
+        position: &B,
+    ) -> Result<(CommentTags<Move>, String), ParseError> {
+        let mut tags = CommentTags::default();
+        let mut text = String::new();
+        let mut rest = comment;
+        while let Some(start) = rest.find("[%") {
+            let end = match rest[start..].find(']') {
+                Some(end) => start + end,
+                None => break,
+            };
+            text.push_str(&rest[..start]);
+            let command = &rest[start + 2..end];
+            let (tag, value) = command.split_once(' ').unwrap_or((command, ""));
+            let value = value.trim();
+            let parse_error = || {
+                pgn_traits::Error::new_parse_error(format!(
+                    "Invalid value \"{}\" for comment tag {}",
+                    value, tag
+                ))
+            };
+            match tag {
+                "eval" => tags.eval = Some(f32::from_str(value).map_err(|_| parse_error())?),
+                "depth" => tags.depth = Some(u32::from_str(value).map_err(|_| parse_error())?),
+                "best" => tags.best_move = Some(position.move_from_san(value)?),
+                "clk" => tags.clock = Some(parse_clock(value).ok_or_else(parse_error)?),
+                _ => text.push_str(&rest[start..=end]),
+            }
+            rest = &rest[end + 1..];
+        }
+        text.push_str(rest);
+        Ok((tags, text.trim().to_string()))
+    }
+
+    /// Write the tags and the free text as a comment, without the surrounding braces.
+    /// `position` is the position before the move, used to write the best move.
+    pub fn write_comment<B: PtnPosition<Move = Move>>(&self, text: &str, position: &B) -> String {
+        let mut commands = vec![];
+        if let Some(eval) = self.eval {
+            commands.push(format!("[%eval {}]", eval));
+        }
+        if let Some(depth) = self.depth {
+            commands.push(format!("[%depth {}]", depth));
+        }
+        if let Some(best_move) = &self.best_move {
+            commands.push(format!("[%best {}]", position.move_to_san(best_move)));
+        }
+        if let Some(clock) = self.clock {
+            commands.push(format!("[%clk {}]", format_clock(clock)));
+        }
+        if !text.is_empty() {
+            commands.push(text.to_string());
+        }
+        commands.join(" ")
+    }
+}
+
+/// Parse a clock time written as `h:mm:ss`, `mm:ss` or `ss`, with optional fractional seconds
+fn parse_clock(s: &str) -> Option<Duration> {
+    let mut fields = s.rsplit(':');
+    let seconds = f64::from_str(fields.next()?).ok()?;
+    let mut total_seconds = seconds;
+    for (field, factor) in fields.by_ref().zip([60.0, 3600.0]) {
+        total_seconds += u64::from_str(field).ok()? as f64 * factor;
+    }
+    if fields.next().is_some() || !total_seconds.is_finite() || total_seconds < 0.0 {
+        return None;
+    }
+    Some(Duration::from_millis(
+        (total_seconds * 1000.0).round() as u64
+    ))
+}
+
+fn format_clock(clock: Duration) -> String {
+    let seconds = clock.as_secs();
+    let millis = clock.subsec_millis();
+    let mut s = format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    );
+    if millis != 0 {
+        s.push_str(&format!(".{:03}", millis));
+    }
+    s
+}
@@ -0,0 +1,79 @@
+use std::io;
+
+use board_game_traits::GameResult;
+
+use crate::position::{with_position, with_size, AnyPosition, Move, Position};
+use crate::ptn::ptn_parser::PtnError;
+use crate::ptn::{ptn_parser, Game, ParseError, PtnMove};
+
+/// A PTN game for a board size that is only known at runtime. See `Game`.
+/// The moves are the same for every size, and are interpreted on the start position's board.
+#[derive(Clone, PartialEq, Debug)]
+pub struct AnyGame {
+    pub start_position: AnyPosition,
+    pub moves: Vec<PtnMove<Move>>,
+    pub game_result: Option<GameResult>,
+    pub tags: Vec<(String, String)>,
+}
+
+impl<const S: usize> From<Game<Position<S>>> for AnyGame
+where
+    AnyPosition: From<Position<S>>,
+{
+    fn from(game: Game<Position<S>>) -> Self {
+        AnyGame {
+            start_position: game.start_position.into(),
+            moves: game.moves,
+            game_result: game.game_result,
+            tags: game.tags,
+        }
+    }
+}
+
+impl AnyGame {
+    /// Parse every game in the input, on a board of the given size. See `ptn_parser::parse_ptn`.
+    pub fn parse_ptn(size: usize, input: &str) -> Result<Vec<Self>, ParseError> {
+        with_size!(size,
+            S => Ok(ptn_parser::parse_ptn::<Position<S>>(input)?
+                .into_iter()
+                .map(AnyGame::from)
+                .collect()),
+            _ => Err(format!("Unsupported board size {}", size).into())
+        )
+    }
+
+    /// Read games one at a time from PTN, on a board of the given size. See `ptn_parser::PtnReader`.
+    /// Returns `None` for unsupported sizes.
+    pub fn read_ptn<'a, R: io::BufRead + 'a>(
+        size: usize,
+        reader: R,
+    ) -> Option<Box<dyn Iterator<Item = Result<Self, PtnError>> + 'a>> {
+        with_size!(size,
+            S => Some(Box::new(
+                ptn_parser::PtnReader::<_, Position<S>>::new(reader)
+                    .map(|game| game.map(AnyGame::from)),
+            )),
+            _ => None
+        )
+    }
+
+    pub fn size(&self) -> usize {
+        self.start_position.size()
+    }
+
+    /// Write the game as PTN. See `Game::game_to_ptn`.
+    /// The size tag is added if it is missing, for example for games parsed from a move list.
+    pub fn game_to_ptn<W: io::Write>(&self, f: &mut W) -> io::Result<()> {
+        let mut tags = self.tags.clone();
+        if !tags.iter().any(|(tag, _)| tag.eq_ignore_ascii_case("Size")) {
+            tags.push(("Size".to_string(), self.size().to_string()));
+        }
+        with_position!(&self.start_position, position, S => Game {
+            start_position: position.clone(),
+            moves: self.moves.clone(),
+            game_result: self.game_result,
+            tags,
+        }
+        .game_to_ptn(f))
+    }
+}
@@ -1,23 +1,316 @@
+use crate::position::{GameOutcome, IllegalMoveError, Komi, Move, Position, ReverseMove, RuleSet};
 use board_game_traits::GameResult;
-use board_game_traits::Position;
+use board_game_traits::Position as PositionTrait;
+use pgn_traits::PgnPosition;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use std::error;
+use std::str::FromStr;
 
+mod annotations;
+mod any_game;
 pub mod ptn_parser;
 pub mod ptn_writer;
 
+pub use annotations::{Annotations, CommentTags, MoveQuality, TakMark};
+pub use any_game::AnyGame;
+
 type ParseError = Box<dyn error::Error + Send + Sync>;
 
 #[derive(Debug, Clone, PartialEq)]
-pub struct Game<B: Position> {
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "B: Serialize, B::Move: Serialize",
+        deserialize = "B: Deserialize<'de>, B::Move: Deserialize<'de>"
+    ))
+)]
+pub struct Game<B: PositionTrait> {
     pub start_position: B,
     pub moves: Vec<PtnMove<B::Move>>,
+    #[cfg_attr(feature = "serde", serde(with = "game_result_serde"))]
     pub game_result: Option<GameResult>,
     pub tags: Vec<(String, String)>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct PtnMove<Move> {
     pub mv: Move,
-    pub annotations: Vec<&'static str>,
+    pub annotations: Annotations,
+    /// The free text of the move's comment, without the comment tags
     pub comment: String,
+    #[cfg_attr(feature = "serde", serde(default = "CommentTags::default"))]
+    pub comment_tags: CommentTags<Move>,
+    /// Alternative lines, each starting with a move that could have been played instead of this one
+    #[cfg_attr(feature = "serde", serde(default = "Vec::new"))]
+    pub variations: Vec<Vec<PtnMove<Move>>>,
+}
+
+/// Navigation in the tree of moves and variations.
+///
+/// A node in the tree is identified by its path from the start position: the child taken at each ply,
+/// where child 0 is the continuation of the current line, and child `i` is the first move of the `i`th variation of that continuation.
+/// The empty path is the start position, and the main line is the path of all zeros.
+impl<B: PtnPosition + Clone> Game<B> {
+    /// The moves of the main line, ignoring all variations
+    pub fn mainline(&self) -> impl Iterator<Item = &B::Move> {
+        self.moves.iter().map(|ptn_move| &ptn_move.mv)
+    }
+
+    /// The move at the node, or `None` for the start position or if the path does not exist
+    pub fn node(&self, path: &[usize]) -> Option<&PtnMove<B::Move>> {
+        self.path_to_node(path)?.last().copied()
+    }
+
+    /// The moves that can be played from the node. The first child is the continuation of the node's line.
+    /// Returns an empty vector if the path does not exist.
+    pub fn children(&self, path: &[usize]) -> Vec<&PtnMove<B::Move>> {
+        let (line, next) = match locate_node(&self.moves, path) {
+            Some((_, line, next)) => (line, next),
+            None => return vec![],
+        };
+        match line.get(next) {
+            Some(continuation) => std::iter::once(continuation)
+                .chain(
+                    continuation
+                        .variations
+                        .iter()
+                        .filter_map(|line| line.first()),
+                )
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// Every move played from the start position to reach the node, including the node's own move
+    pub fn path_to_node(&self, path: &[usize]) -> Option<Vec<&PtnMove<B::Move>>> {
+        locate_node(&self.moves, path).map(|(moves, _, _)| moves)
+    }
+
+    /// The position after the node's move
+    pub fn position_at(&self, path: &[usize]) -> Option<B> {
+        let mut position = self.start_position.clone();
+        for ptn_move in self.path_to_node(path)? {
+            position.do_move(ptn_move.mv.clone());
+        }
+        Some(position)
+    }
+
+    /// Add a line of moves after the node, and return the path of its first move.
+    /// If the node has no continuation yet, the moves are appended to the node's line,
+    /// otherwise they are stored as a new variation of the continuation.
+    pub fn add_variation(
+        &mut self,
+        path: &[usize],
+        moves: Vec<PtnMove<B::Move>>,
+    ) -> Result<Vec<usize>, pgn_traits::Error> {
+        let mut position = self.position_at(path).ok_or_else(|| {
+            pgn_traits::Error::new(
+                pgn_traits::ErrorKind::Other,
+                format!("No node at path {:?}", path),
+            )
+        })?;
+        if moves.is_empty() {
+            return Err(pgn_traits::Error::new(
+                pgn_traits::ErrorKind::Other,
+                "Cannot add an empty variation",
+            ));
+        }
+        for ptn_move in moves.iter() {
+            if let Err(err) = position.try_do_move(ptn_move.mv.clone()) {
+                return Err(pgn_traits::Error::new_caused_by(
+                    pgn_traits::ErrorKind::IllegalMove,
+                    position.move_to_san(&ptn_move.mv),
+                    err,
+                ));
+            }
+        }
+
+        let (line, next) = locate_node_mut(&mut self.moves, path).unwrap();
+        let mut new_path = path.to_vec();
+        match line.get_mut(next) {
+            Some(continuation) => {
+                continuation.variations.push(moves);
+                new_path.push(continuation.variations.len());
+            }
+            None => {
+                line.extend(moves);
+                new_path.push(0);
+            }
+        }
+        Ok(new_path)
+    }
+}
+
+/// Follow a path through the tree. Returns the moves on the way,
+/// and the line containing the node, together with the index of the node's continuation in that line.
+#[allow(clippy::type_complexity)]
+fn locate_node<'a, M>(
+    moves: &'a [PtnMove<M>],
+    path: &[usize],
+) -> Option<(Vec<&'a PtnMove<M>>, &'a [PtnMove<M>], usize)> {
+    let mut line = moves;
+    let mut next = 0;
+    let mut moves_played = Vec::with_capacity(path.len());
+    for &child in path {
+        let continuation = line.get(next)?;
+        if child == 0 {
+            moves_played.push(continuation);
+            next += 1;
+        } else {
+            line = continuation.variations.get(child - 1)?;
+            moves_played.push(line.first()?);
+            next = 1;
+        }
+    }
+    Some((moves_played, line, next))
+}
+
+fn locate_node_mut<'a, M>(
+    moves: &'a mut Vec<PtnMove<M>>,
+    path: &[usize],
+) -> Option<(&'a mut Vec<PtnMove<M>>, usize)> {
+    let mut line = moves;
+    let mut next = 0;
+    for &child in path {
+        if child == 0 {
+            if next >= line.len() {
+                return None;
+            }
+            next += 1;
+        } else {
+            line = line.get_mut(next)?.variations.get_mut(child - 1)?;
+            next = 1;
+        }
+    }
+    Some((line, next))
+}
+
+/// Game results are serialized with their PTN notation, `1-0`, `0-1` or `1/2-1/2`
+#[cfg(feature = "serde")]
+mod game_result_serde {
+    use board_game_traits::GameResult;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        game_result: &Option<GameResult>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        game_result
+            .map(|game_result| match game_result {
+                GameResult::WhiteWin => "1-0",
+                GameResult::BlackWin => "0-1",
+                GameResult::Draw => "1/2-1/2",
+            })
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<GameResult>, D::Error> {
+        match Option::<String>::deserialize(deserializer)?.as_deref() {
+            None => Ok(None),
+            Some("1-0") => Ok(Some(GameResult::WhiteWin)),
+            Some("0-1") => Ok(Some(GameResult::BlackWin)),
+            Some("1/2-1/2") => Ok(Some(GameResult::Draw)),
+            Some(other) => Err(D::Error::custom(format!("Invalid game result {}", other))),
+        }
+    }
+}
+
+/// Positions whose game settings, like komi, are stored in PTN tags
+pub trait PtnPosition: PgnPosition {
+    /// Start position for a game with the given tags, including the `TPS` tag for games that don't start from an empty board.
+    /// Unknown tags are ignored.
+    fn start_position_from_tags(tags: &[(String, String)]) -> Result<Self, pgn_traits::Error>;
+
+    /// Tags required to recreate this position's game settings.
+    /// Settings that have their default value are not included.
+    fn game_setting_tags(&self) -> Vec<(String, String)>;
+
+    /// Play a move if it is legal, or return the reason why it is illegal
+    fn try_do_move(&mut self, mv: Self::Move) -> Result<Self::ReverseMove, IllegalMoveError>;
+
+    /// The result of the game and how it ended, if the game is over
+    fn game_outcome(&self) -> Option<GameOutcome>;
+
+    /// Number of plies played before this position, used for numbering moves
+    fn half_moves_played(&self) -> usize;
+}
+
+impl<const S: usize> PtnPosition for Position<S> {
+    /// Reads the rule set from the `Komi`, `Flats`, `Caps` and `Opening` tags.
+    /// Disabling repetition draws has no corresponding PTN tag.
+    fn start_position_from_tags(tags: &[(String, String)]) -> Result<Self, pgn_traits::Error> {
+        let mut rules = RuleSet::default();
+        let mut tps = None;
+        for (tag, value) in tags {
+            let parse_error = || {
+                pgn_traits::Error::new_parse_error(format!(
+                    "Invalid value \"{}\" for tag {}",
+                    value, tag
+                ))
+            };
+            match tag.to_ascii_lowercase().as_str() {
+                "komi" => rules = rules.add_komi(Komi::from_str(value)?),
+                "flats" => {
+                    rules = u8::from_str(value)
+                        .ok()
+                        .and_then(|stones| rules.add_starting_stones(stones))
+                        .ok_or_else(parse_error)?
+                }
+                "caps" => {
+                    rules = u8::from_str(value)
+                        .ok()
+                        .and_then(|capstones| rules.add_starting_capstones(capstones))
+                        .ok_or_else(parse_error)?
+                }
+                "opening" => match value.as_str() {
+                    "swap" => (),
+                    "no-swap" => rules = rules.without_opening_swap(),
+                    _ => return Err(parse_error()),
+                },
+                "tps" => tps = Some(value),
+                _ => (),
+            }
+        }
+        match tps {
+            Some(tps) => Position::from_fen_with_rules(tps, rules),
+            None => Ok(Position::start_position_with_rules(rules)),
+        }
+    }
+
+    fn game_setting_tags(&self) -> Vec<(String, String)> {
+        let rules = self.rules();
+        let default_rules = RuleSet::<S>::default();
+        let mut tags = vec![];
+        if rules.komi() != default_rules.komi() {
+            tags.push(("Komi".to_string(), rules.komi().to_string()));
+        }
+        if rules.starting_stones() != default_rules.starting_stones() {
+            tags.push(("Flats".to_string(), rules.starting_stones().to_string()));
+        }
+        if rules.starting_capstones() != default_rules.starting_capstones() {
+            tags.push(("Caps".to_string(), rules.starting_capstones().to_string()));
+        }
+        if !rules.opening_swap() {
+            tags.push(("Opening".to_string(), "no-swap".to_string()));
+        }
+        tags
+    }
+
+    fn try_do_move(&mut self, mv: Move) -> Result<ReverseMove, IllegalMoveError> {
+        Position::try_do_move(self, mv)
+    }
+
+    fn game_outcome(&self) -> Option<GameOutcome> {
+        Position::game_outcome(self)
+    }
+
+    fn half_moves_played(&self) -> usize {
+        Position::half_moves_played(self)
+    }
 }
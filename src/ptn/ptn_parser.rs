@@ -1,32 +1,203 @@
-use crate::ptn::{Game, ParseError, PtnMove};
+use crate::ptn::{Annotations, CommentTags, Game, ParseError, PtnMove, PtnPosition};
 use board_game_traits::GameResult;
-use pgn_traits::PgnPosition;
+use std::collections::VecDeque;
 use std::fmt::Debug;
+use std::io::BufRead;
 use std::str::FromStr;
+use std::{error, fmt, io};
 
-pub fn parse_ptn<B: PgnPosition + Debug + Clone>(input: &str) -> Result<Vec<Game<B>>, ParseError> {
-    let mut parser = ParserData { input };
-    let mut games = vec![];
-    loop {
-        if parser.input.chars().any(|ch| !ch.is_whitespace()) {
-            match parse_game(&mut parser) {
-                Ok(game) => games.push(game),
-                Err(err) => {
-                    eprintln!("Couldn't parse game: {}", err);
-                    if games.is_empty() {
-                        return Err(err);
-                    } else {
-                        return Ok(games);
+/// Parse every game in the input, failing on the first game that can't be parsed
+pub fn parse_ptn<B: PtnPosition + Debug + Clone>(input: &str) -> Result<Vec<Game<B>>, PtnError> {
+    PtnReader::new(input.as_bytes()).collect()
+}
+
+/// An error in a PTN file, with the location where it was detected
+#[derive(Debug)]
+pub struct PtnError {
+    /// 1-based number of the game in the file, counting games that failed to parse
+    pub game_number: usize,
+    /// 1-based line number in the file
+    pub line: usize,
+    /// 1-based column, in characters
+    pub column: usize,
+    pub error: ParseError,
+}
+
+impl fmt::Display for PtnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Error in game #{} at line {}, column {}: {}",
+            self.game_number, self.line, self.column, self.error
+        )
+    }
+}
+
+impl error::Error for PtnError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&*self.error)
+    }
+}
+
+/// Reads games one at a time from a PTN file of any size.
+///
+/// The input is split into games at tag sections, and after game results,
+/// so that a game that fails to parse is skipped, and reading continues with the game after it.
+/// Games without a result that are not followed by tags are parsed together with the next game,
+/// and an error in one of them also skips the rest of them.
+/// A comment that is never closed ends at the first tag after a blank line.
+pub struct PtnReader<R, B: PtnPosition> {
+    reader: R,
+    /// Number of lines read from the input
+    lines_read: usize,
+    /// First line of the next game, which was read while looking for the end of the previous game
+    next_game_line: Option<String>,
+    games_read: usize,
+    parsed_games: VecDeque<Result<Game<B>, PtnError>>,
+    done: bool,
+}
+
+impl<R: BufRead, B: PtnPosition + Debug + Clone> PtnReader<R, B> {
+    pub fn new(reader: R) -> Self {
+        PtnReader {
+            reader,
+            lines_read: 0,
+            next_game_line: None,
+            games_read: 0,
+            parsed_games: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    /// Read the text of the next game, up to the next tag section or the end of the line with the game result.
+    /// Returns the text and the line number it starts on, or `None` at the end of the input.
+    fn read_game_text(&mut self) -> io::Result<Option<(String, usize)>> {
+        let mut text = String::new();
+        let mut first_line = self.lines_read;
+        let mut has_moves = false;
+        let mut in_comment = false;
+        let mut variation_depth: usize = 0;
+        let mut has_result = false;
+        let mut after_blank_line = false;
+        let mut line_bytes = vec![];
+        loop {
+            let line = match self.next_game_line.take() {
+                Some(line) => line,
+                None => {
+                    line_bytes.clear();
+                    if self.reader.read_until(b'\n', &mut line_bytes)? == 0 {
+                        break;
                     }
+                    self.lines_read += 1;
+                    // Invalid UTF-8 is replaced, and will fail to parse unless it's in a comment
+                    String::from_utf8_lossy(&line_bytes).into_owned()
+                }
+            };
+            let trimmed = line.trim_start();
+            // Blank lines before the game are dropped, so that error locations are counted from `first_line`
+            if text.trim().is_empty() {
+                text.clear();
+                first_line = self.lines_read;
+            }
+            // A tag after a blank line also ends an unterminated comment, so that the next game can still be read
+            if trimmed.starts_with('[') && (!in_comment || after_blank_line) {
+                if has_moves {
+                    self.next_game_line = Some(line);
+                    break;
+                }
+            } else if !trimmed.is_empty() {
+                has_moves = true;
+                let mut word_start = None;
+                for (i, ch) in line.char_indices() {
+                    if !in_comment && !ch.is_whitespace() && !"{}()".contains(ch) {
+                        word_start.get_or_insert(i);
+                        continue;
+                    }
+                    // Results at the end of variations don't end the game
+                    if let Some(start) = word_start.take() {
+                        has_result |= variation_depth == 0 && is_game_result::<B>(&line[start..i]);
+                    }
+                    match ch {
+                        '{' => in_comment = true,
+                        '}' => in_comment = false,
+                        '(' if !in_comment => variation_depth += 1,
+                        ')' if !in_comment => variation_depth = variation_depth.saturating_sub(1),
+                        _ => (),
+                    }
+                }
+                if let Some(start) = word_start {
+                    has_result |= variation_depth == 0 && is_game_result::<B>(&line[start..]);
                 }
             }
+            text.push_str(&line);
+            if has_result {
+                break;
+            }
+            after_blank_line = trimmed.is_empty();
+        }
+        if text.trim().is_empty() {
+            Ok(None)
         } else {
-            return Ok(games);
+            Ok(Some((text, first_line)))
+        }
+    }
+
+    /// Parse all games in the text, stopping at the first error
+    fn parse_game_text(&mut self, text: &str, first_line: usize) {
+        let mut parser = ParserData::new(text);
+        loop {
+            parser.skip_whitespaces();
+            if parser.input.is_empty() {
+                return;
+            }
+            self.games_read += 1;
+            match parse_game(&mut parser) {
+                Ok(game) => self.parsed_games.push_back(Ok(game)),
+                Err(error) => {
+                    let (line, column) = parser.token_location();
+                    self.parsed_games.push_back(Err(PtnError {
+                        game_number: self.games_read,
+                        line: first_line + line - 1,
+                        column,
+                        error,
+                    }));
+                    return;
+                }
+            }
         }
     }
 }
 
-fn parse_game<B: PgnPosition + Debug + Clone>(
+impl<R: BufRead, B: PtnPosition + Debug + Clone> Iterator for PtnReader<R, B> {
+    type Item = Result<Game<B>, PtnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.parsed_games.is_empty() && !self.done {
+            match self.read_game_text() {
+                Ok(Some((text, first_line))) => self.parse_game_text(&text, first_line),
+                Ok(None) => self.done = true,
+                Err(error) => {
+                    // The reader can't be trusted after an IO error, so stop reading
+                    self.done = true;
+                    self.games_read += 1;
+                    self.parsed_games.push_back(Err(PtnError {
+                        game_number: self.games_read,
+                        line: self.lines_read + 1,
+                        column: 1,
+                        error: Box::new(error),
+                    }));
+                }
+            }
+        }
+        self.parsed_games.pop_front()
+    }
+}
+
+fn is_game_result<B: PtnPosition>(word: &str) -> bool {
+    B::POSSIBLE_GAME_RESULTS.iter().any(|(s, _)| *s == word)
+}
+
+fn parse_game<B: PtnPosition + Debug + Clone>(
     input: &mut ParserData,
 ) -> Result<Game<B>, ParseError> {
     let mut tags = vec![];
@@ -36,9 +207,9 @@ fn parse_game<B: PgnPosition + Debug + Clone>(
         input.skip_whitespaces();
         tags.push((tag.to_string(), value));
     }
-    let position = B::start_position();
+    let position = B::start_position_from_tags(&tags)?;
 
-    let (moves, game_result) = parse_moves(input, position.clone())?;
+    let (moves, game_result) = parse_moves(input, position.clone(), false)?;
 
     Ok(Game {
         start_position: position,
@@ -49,6 +220,7 @@ fn parse_game<B: PgnPosition + Debug + Clone>(
 }
 
 fn parse_tag<'a>(input: &mut ParserData<'a>) -> Result<(&'a str, String), pgn_traits::Error> {
+    input.mark_token();
     assert_eq!(input.take(), Some('['));
     let tag: &'a str = input.take_word();
 
@@ -64,13 +236,14 @@ fn parse_tag<'a>(input: &mut ParserData<'a>) -> Result<(&'a str, String), pgn_tr
     loop {
         match input.take() {
             Some('"') => break,
-            Some('\\') => match input.take().unwrap() {
-                '"' => value.push('"'),
-                '\\' => value.push('\\'),
-                ch => {
+            Some('\\') => match input.take() {
+                Some('"') => value.push('"'),
+                Some('\\') => value.push('\\'),
+                Some(ch) => {
                     value.push('\\');
                     value.push(ch);
                 }
+                None => break,
             },
             Some(ch) => value.push(ch),
             None => {
@@ -92,16 +265,39 @@ fn parse_tag<'a>(input: &mut ParserData<'a>) -> Result<(&'a str, String), pgn_tr
     }
 }
 
+/// Parse the moves of a line, starting from `start_position`.
+/// Variations are parsed recursively, and end at a closing parenthesis instead of a game result.
 #[allow(clippy::type_complexity)]
-fn parse_moves<B: PgnPosition + Debug + Clone>(
+fn parse_moves<B: PtnPosition + Debug + Clone>(
     input: &mut ParserData,
-    mut position: B,
+    start_position: B,
+    in_variation: bool,
 ) -> Result<(Vec<PtnMove<B::Move>>, Option<GameResult>), ParseError> {
+    let mut position = start_position.clone();
     let mut moves: Vec<PtnMove<B::Move>> = vec![];
-    let mut _ply_counter = 0; // Last ply seen
     loop {
         input.skip_whitespaces();
+        input.mark_token();
+        if input.peek() == Some(')') {
+            input.take();
+            if !in_variation {
+                return Err(Box::new(pgn_traits::Error::new_parse_error(
+                    "Closing parenthesis outside of a variation".to_string(),
+                )));
+            }
+            if moves.is_empty() {
+                return Err(Box::new(pgn_traits::Error::new_parse_error(
+                    "Empty variation".to_string(),
+                )));
+            }
+            return Ok((moves, None));
+        }
         if input.peek().is_none() || input.peek() == Some('[') {
+            if in_variation {
+                return Err(Box::new(pgn_traits::Error::new_parse_error(
+                    "Unexpected EOF, expected a closing parenthesis after variation.".to_string(),
+                )));
+            }
             // Games without a result aren't allowed by the spec,
             // but try to accept it anyway and return a `None` result
             if !moves.is_empty() {
@@ -112,60 +308,71 @@ fn parse_moves<B: PgnPosition + Debug + Clone>(
                 "Unexpected EOF, expected a move or a game result.".to_string(),
             )));
         }
+        if input.peek() == Some('(') {
+            input.take();
+            // The variation replaces the last move, so it starts from the position before it
+            let (last_move, earlier_moves) = moves.split_last_mut().ok_or_else(|| {
+                pgn_traits::Error::new_parse_error("Variation before the first move".to_string())
+            })?;
+            let mut variation_start = start_position.clone();
+            for ptn_move in earlier_moves.iter() {
+                variation_start.do_move(ptn_move.mv.clone());
+            }
+            let (variation, _) = parse_moves(input, variation_start, true)?;
+            last_move.variations.push(variation);
+            continue;
+        }
         let word = input.take_word();
 
         assert!(!word.is_empty());
 
         if let Some(num_string) = word.strip_suffix("...") {
-            let _num = u64::from_str(num_string)?;
-            _ply_counter = _num * 2 - 1;
+            u64::from_str(num_string)?;
         } else if let Some(num_string) = word.strip_suffix('.') {
-            let _num = u64::from_str(num_string)?;
-            _ply_counter = _num * 2 - 2;
+            u64::from_str(num_string)?;
         } else if let Some((_, result)) = B::POSSIBLE_GAME_RESULTS
             .iter()
             .find(|(s, _result)| *s == word)
         {
-            return Ok((moves, *result));
-        } else {
-            let mut move_string = word;
-            let mut annotations = vec![];
-            while let Some(annotation) = B::POSSIBLE_MOVE_ANNOTATIONS
-                .iter()
-                .find(|annotation| move_string.strip_suffix(*annotation).is_some())
-            {
-                move_string = move_string.strip_suffix(*annotation).unwrap();
-                annotations.insert(0, *annotation);
+            // Results at the end of variations are ignored, only the main line decides the game result
+            if !in_variation {
+                return Ok((moves, *result));
             }
+        } else {
+            let (move_string, annotations) = Annotations::strip_from_move(word)?;
 
             match position.move_from_san(move_string) {
                 Ok(mv) => {
-                    let mut legal_moves = vec![];
-                    position.generate_moves(&mut legal_moves);
-                    if !legal_moves.contains(&mv) {
-                        return Err(Box::new(pgn_traits::Error::new(
-                            pgn_traits::ErrorKind::IllegalMove,
-                            word,
-                        )));
-                    }
-                    position.do_move(mv.clone());
                     input.skip_whitespaces();
-                    if input.peek() == Some('{') {
+                    let (comment_tags, comment) = if input.peek() == Some('{') {
+                        input.mark_token();
                         input.take();
                         let comment = input.take_while(|ch| ch != '}');
-                        input.take();
-                        moves.push(PtnMove {
-                            mv,
-                            annotations,
-                            comment: comment.to_string(),
-                        })
+                        if input.take().is_none() {
+                            return Err(Box::new(pgn_traits::Error::new_parse_error(
+                                "Unexpected EOF, expected a closing brace after comment."
+                                    .to_string(),
+                            )));
+                        }
+                        // Tags are read before the move is played, because the best move is an alternative to it
+                        CommentTags::parse_comment(comment, &position)?
                     } else {
-                        moves.push(PtnMove {
-                            mv,
-                            annotations,
-                            comment: String::new(),
-                        });
+                        (CommentTags::default(), String::new())
+                    };
+                    if let Err(err) = position.try_do_move(mv.clone()) {
+                        return Err(Box::new(pgn_traits::Error::new_caused_by(
+                            pgn_traits::ErrorKind::IllegalMove,
+                            word,
+                            err,
+                        )));
                     }
+                    moves.push(PtnMove {
+                        mv,
+                        annotations,
+                        comment,
+                        comment_tags,
+                        variations: vec![],
+                    });
                 }
                 Err(err) => {
                     return Err(Box::new(pgn_traits::Error::new_parse_error(format!(
@@ -179,17 +386,43 @@ fn parse_moves<B: PgnPosition + Debug + Clone>(
 }
 
 struct ParserData<'a> {
+    full_input: &'a str,
     input: &'a str,
+    /// Byte offset of the start of the last token, used to locate errors
+    token_start: usize,
 }
 
 impl<'a> ParserData<'a> {
+    fn new(input: &'a str) -> Self {
+        ParserData {
+            full_input: input,
+            input,
+            token_start: 0,
+        }
+    }
+
+    fn mark_token(&mut self) {
+        self.token_start = self.full_input.len() - self.input.len();
+    }
+
+    /// The 1-based line and column of the start of the last token
+    fn token_location(&self) -> (usize, usize) {
+        let before_token = &self.full_input[..self.token_start];
+        let line = before_token.matches('\n').count() + 1;
+        let line_start = before_token.rfind('\n').map_or(0, |i| i + 1);
+        let column = before_token[line_start..].chars().count() + 1;
+        (line, column)
+    }
+
     fn skip_whitespaces(&mut self) {
         self.input = self.input.trim_start_matches(char::is_whitespace);
     }
 
+    /// Take the next word, which ends at a whitespace or at the parentheses around a variation
     fn take_word(&mut self) -> &'a str {
         self.skip_whitespaces();
-        self.take_while(|ch| !ch.is_whitespace())
+        self.mark_token();
+        self.take_while(|ch| !ch.is_whitespace() && ch != '(' && ch != ')')
     }
 
     fn take_while<F: Fn(char) -> bool>(&mut self, f: F) -> &'a str {
@@ -1,18 +1,34 @@
-use crate::ptn::{Game, PtnMove};
+use crate::ptn::{Game, PtnMove, PtnPosition};
 use board_game_traits::{Color, GameResult};
-use pgn_traits::PgnPosition;
 use std::io;
 use std::io::Write;
 
 const LINE_WIDTH: usize = 80;
 
-impl<B: PgnPosition + Clone> Game<B> {
+impl<B: PtnPosition + Clone> Game<B> {
     pub fn game_to_ptn<W: Write>(&self, f: &mut W) -> Result<(), io::Error> {
         // Write the required tags first, in the correct order
         // Fill in default value if they are not available
         // We must ensure that all required tags are included, and written in the correct order
         let mut tags = self.tags.clone();
 
+        let mut final_position = self.start_position.clone();
+        for PtnMove { mv, .. } in self.moves.iter() {
+            final_position.do_move(mv.clone());
+        }
+        // Write road and flat wins as R-0 and F-0, if the result was reached on the board
+        let result_string = match final_position.game_outcome() {
+            Some(outcome) if Some(outcome.result) == self.game_result => {
+                outcome.ptn_result_string()
+            }
+            _ => match self.game_result {
+                Some(GameResult::WhiteWin) => "1-0",
+                Some(GameResult::BlackWin) => "0-1",
+                Some(GameResult::Draw) => "1/2-1/2",
+                None => "*",
+            },
+        };
+
         for (required_tag, default_value) in B::REQUIRED_TAGS.iter() {
             let position = tags
                 .iter()
@@ -24,14 +40,6 @@ impl<B: PgnPosition + Clone> Game<B> {
             } else {
                 // If the result tag is required, but not provided, manually write it
                 if required_tag.eq_ignore_ascii_case("Result") {
-                    let result_string = match self.game_result {
-                        Some(GameResult::WhiteWin) => "1-0",
-                        Some(GameResult::BlackWin) => "0-1",
-                        Some(GameResult::Draw) => "1/2-1/2",
-                        None => "*",
-                    }
-                    .to_string();
-
                     writeln!(f, "[{} \"{}\"]", required_tag, result_string)?;
                 } else {
                     writeln!(f, "[{} \"{}\"]", required_tag, default_value)?;
@@ -39,10 +47,20 @@ impl<B: PgnPosition + Clone> Game<B> {
             }
         }
 
-        if self.start_position != B::start_position()
-            && !tags.iter().any(|(tag, _)| tag.eq_ignore_ascii_case("FEN"))
+        // Write game settings, unless they are provided as tags already
+        for (setting_tag, value) in self.start_position.game_setting_tags() {
+            if !tags
+                .iter()
+                .any(|(tag, _)| tag.eq_ignore_ascii_case(&setting_tag))
+            {
+                writeln!(f, "[{} \"{}\"]", setting_tag, value)?;
+            }
+        }
+
+        if self.start_position.to_fen() != B::start_position().to_fen()
+            && !tags.iter().any(|(tag, _)| tag.eq_ignore_ascii_case("TPS"))
         {
-            writeln!(f, "[FEN \"{}\"", self.start_position.to_fen())?;
+            writeln!(f, "[TPS \"{}\"]", self.start_position.to_fen())?;
         }
 
         // Write any remaining tags
@@ -52,77 +70,107 @@ impl<B: PgnPosition + Clone> Game<B> {
 
         writeln!(f)?;
 
-        let mut position = self.start_position.clone();
+        // Each unit is written on a single line, usually a move number with the white and black moves
+        let mut units = vec![];
+        line_to_ptn_units(
+            &self.start_position,
+            &self.moves,
+            self.start_position.half_moves_played(),
+            &mut units,
+        );
+        match units.last_mut() {
+            Some(last_unit) => {
+                last_unit.push(' ');
+                last_unit.push_str(result_string);
+            }
+            None => units.push(result_string.to_string()),
+        }
+
         let mut column_position = 0;
-        let mut buffer = String::new();
-
-        for (
-            i,
-            PtnMove {
-                mv,
-                comment,
-                annotations,
-            },
-        ) in self.moves.iter().enumerate()
-        {
-            if i == 0 && position.side_to_move() == Color::Black {
-                buffer.push_str(&format!("1... {}", position.move_to_san(mv)));
-            } else if position.side_to_move() == Color::White {
-                buffer.push_str(&format!(
-                    "{}. {}",
-                    (i + 1) / 2 + 1,
-                    position.move_to_san(mv),
-                ));
+        for unit in units {
+            if column_position == 0 {
+                write!(f, "{}", unit)?;
+                column_position = unit.len();
+            } else if column_position + unit.len() < LINE_WIDTH {
+                write!(f, " {}", unit)?;
+                column_position += unit.len() + 1;
             } else {
-                buffer.push_str(&position.move_to_san(mv));
+                write!(f, "\n{}", unit)?;
+                column_position = unit.len();
             }
+        }
 
-            for annotation in annotations {
-                buffer.push_str(annotation);
-            }
+        writeln!(f)?;
+        writeln!(f)?;
+        Ok(())
+    }
+}
 
-            if !comment.is_empty() {
-                buffer.push_str(" {");
-                buffer.push_str(comment);
-                buffer.push('}');
-            }
+/// Write a line of moves, starting from `position`, and its variations in parentheses.
+/// `first_ply` is the number of plies played before the line.
+fn line_to_ptn_units<B: PtnPosition + Clone>(
+    position: &B,
+    moves: &[PtnMove<B::Move>],
+    first_ply: usize,
+    units: &mut Vec<String>,
+) {
+    let mut position = position.clone();
+    let mut buffer = String::new();
+    // Black moves are numbered at the start of a line, and after variations
+    let mut needs_move_number = true;
+
+    for (
+        i,
+        PtnMove {
+            mv,
+            comment,
+            comment_tags,
+            annotations,
+            variations,
+        },
+    ) in moves.iter().enumerate()
+    {
+        let move_number = (first_ply + i) / 2 + 1;
+        if !buffer.is_empty() {
+            buffer.push(' ');
+        }
+        if position.side_to_move() == Color::White {
+            buffer.push_str(&format!("{}. {}", move_number, position.move_to_san(mv)));
+        } else if needs_move_number {
+            buffer.push_str(&format!("{}... {}", move_number, position.move_to_san(mv)));
+        } else {
+            buffer.push_str(&position.move_to_san(mv));
+        }
+        needs_move_number = false;
 
-            if i == self.moves.len() - 1 {
-                match self.game_result {
-                    None => buffer.push_str(" *"),
-                    Some(GameResult::WhiteWin) => buffer.push_str(" 1-0"),
-                    Some(GameResult::BlackWin) => buffer.push_str(" 0-1"),
-                    Some(GameResult::Draw) => buffer.push_str(" 1/2-1/2"),
-                }
-            }
+        buffer.push_str(&annotations.to_string());
 
-            if position.side_to_move() == Color::Black || i == self.moves.len() - 1 {
-                if column_position == 0 {
-                    write!(f, "{}", buffer)?;
-                    column_position = buffer.len();
-                } else if column_position + buffer.len() < LINE_WIDTH {
-                    write!(f, " {}", buffer)?;
-                    column_position += buffer.len() + 1;
-                } else {
-                    write!(f, "\n{}", buffer)?;
-                    column_position = buffer.len();
-                }
-                buffer.clear();
-            } else {
-                buffer.push(' ');
+        if !comment.is_empty() || !comment_tags.is_empty() {
+            buffer.push_str(" {");
+            buffer.push_str(&comment_tags.write_comment(comment, &position));
+            buffer.push('}');
+        }
+
+        if !variations.is_empty() {
+            units.push(std::mem::take(&mut buffer));
+            for variation in variations.iter().filter(|variation| !variation.is_empty()) {
+                let mut variation_units = vec![];
+                line_to_ptn_units(&position, variation, first_ply + i, &mut variation_units);
+                variation_units.first_mut().unwrap().insert(0, '(');
+                variation_units.last_mut().unwrap().push(')');
+                units.extend(variation_units);
             }
+            needs_move_number = true;
+        }
 
-            position.do_move(mv.clone());
+        if position.side_to_move() == Color::Black && !buffer.is_empty() {
+            units.push(std::mem::take(&mut buffer));
         }
 
-        assert!(
-            buffer.is_empty(),
-            "\"{}\" was not written to the ptn",
-            buffer
-        );
+        position.do_move(mv.clone());
+    }
 
-        writeln!(f)?;
-        writeln!(f)?;
-        Ok(())
+    if !buffer.is_empty() {
+        units.push(buffer);
     }
 }
@@ -0,0 +1,247 @@
+use std::time;
+
+use board_game_traits::Position as PositionTrait;
+
+use crate::position::{with_position, AnyMove, AnyPosition, Move};
+use crate::search::{BackgroundSearch, MctsSetting, MonteCarloTree, ProvenResult, Score};
+
+/// Search settings for a board size that is only known at runtime. See `MctsSetting`.
+#[derive(Clone, PartialEq, Debug)]
+pub enum AnyMctsSetting {
+    Size3(MctsSetting<3>),
+    Size4(MctsSetting<4>),
+    Size5(MctsSetting<5>),
+    Size6(MctsSetting<6>),
+    Size7(MctsSetting<7>),
+    Size8(MctsSetting<8>),
+}
+
+/// Apply the same `MctsSetting` builder method regardless of size
+macro_rules! map_setting {
+    ($setting:expr, $inner:ident => $body:expr) => {
+        match $setting {
+            AnyMctsSetting::Size3($inner) => AnyMctsSetting::Size3($body),
+            AnyMctsSetting::Size4($inner) => AnyMctsSetting::Size4($body),
+            AnyMctsSetting::Size5($inner) => AnyMctsSetting::Size5($body),
+            AnyMctsSetting::Size6($inner) => AnyMctsSetting::Size6($body),
+            AnyMctsSetting::Size7($inner) => AnyMctsSetting::Size7($body),
+            AnyMctsSetting::Size8($inner) => AnyMctsSetting::Size8($body),
+        }
+    };
+}
+
+impl AnyMctsSetting {
+    /// The default settings for the board size. Returns `None` if the size is not supported
+    pub fn default_for_size(size: usize) -> Option<Self> {
+        match size {
+            3 => Some(AnyMctsSetting::Size3(MctsSetting::default())),
+            4 => Some(AnyMctsSetting::Size4(MctsSetting::default())),
+            5 => Some(AnyMctsSetting::Size5(MctsSetting::default())),
+            6 => Some(AnyMctsSetting::Size6(MctsSetting::default())),
+            7 => Some(AnyMctsSetting::Size7(MctsSetting::default())),
+            8 => Some(AnyMctsSetting::Size8(MctsSetting::default())),
+            _ => None,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            AnyMctsSetting::Size3(_) => 3,
+            AnyMctsSetting::Size4(_) => 4,
+            AnyMctsSetting::Size5(_) => 5,
+            AnyMctsSetting::Size6(_) => 6,
+            AnyMctsSetting::Size7(_) => 7,
+            AnyMctsSetting::Size8(_) => 8,
+        }
+    }
+
+    pub fn add_search_params(self, search_params: Vec<f32>) -> Self {
+        map_setting!(self, setting => setting.add_search_params(search_params))
+    }
+
+    pub fn add_dirichlet(self, alpha: f32) -> Self {
+        map_setting!(self, setting => setting.add_dirichlet(alpha))
+    }
+
+    pub fn add_rollout_depth(self, rollout_depth: u16) -> Self {
+        map_setting!(self, setting => setting.add_rollout_depth(rollout_depth))
+    }
+
+    pub fn add_rollout_temperature(self, temperature: f64) -> Self {
+        map_setting!(self, setting => setting.add_rollout_temperature(temperature))
+    }
+
+    pub fn add_endgame_solver(self, max_nodes: u64) -> Self {
+        map_setting!(self, setting => setting.add_endgame_solver(max_nodes))
+    }
+
+    pub fn add_threads(self, threads: usize) -> Self {
+        map_setting!(self, setting => setting.add_threads(threads))
+    }
+
+    pub fn add_memory_limit(self, bytes: usize) -> Self {
+        map_setting!(self, setting => setting.add_memory_limit(bytes))
+    }
+}
+
+/// A `MonteCarloTree` for a board size that is only known at runtime.
+#[derive(Clone, PartialEq, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum AnyTree {
+    Size3(MonteCarloTree<3>),
+    Size4(MonteCarloTree<4>),
+    Size5(MonteCarloTree<5>),
+    Size6(MonteCarloTree<6>),
+    Size7(MonteCarloTree<7>),
+    Size8(MonteCarloTree<8>),
+}
+
+/// Match on an `AnyTree`, binding the inner tree to `$inner` and the size to the constant `$size`.
+macro_rules! with_tree {
+    ($tree:expr, $inner:ident, $size:ident => $body:expr) => {
+        match $tree {
+            AnyTree::Size3($inner) => with_position!(@arm 3, $size => $body),
+            AnyTree::Size4($inner) => with_position!(@arm 4, $size => $body),
+            AnyTree::Size5($inner) => with_position!(@arm 5, $size => $body),
+            AnyTree::Size6($inner) => with_position!(@arm 6, $size => $body),
+            AnyTree::Size7($inner) => with_position!(@arm 7, $size => $body),
+            AnyTree::Size8($inner) => with_position!(@arm 8, $size => $body),
+        }
+    };
+}
+
+impl AnyTree {
+    pub fn new(position: AnyPosition) -> Self {
+        match position {
+            AnyPosition::Size3(position) => AnyTree::Size3(MonteCarloTree::new(position)),
+            AnyPosition::Size4(position) => AnyTree::Size4(MonteCarloTree::new(position)),
+            AnyPosition::Size5(position) => AnyTree::Size5(MonteCarloTree::new(position)),
+            AnyPosition::Size6(position) => AnyTree::Size6(MonteCarloTree::new(position)),
+            AnyPosition::Size7(position) => AnyTree::Size7(MonteCarloTree::new(position)),
+            AnyPosition::Size8(position) => AnyTree::Size8(MonteCarloTree::new(position)),
+        }
+    }
+
+    /// Panics if the settings are for a different board size
+    pub fn with_settings(position: AnyPosition, settings: AnyMctsSetting) -> Self {
+        use AnyMctsSetting as Setting;
+        match (position, settings) {
+            (AnyPosition::Size3(position), Setting::Size3(settings)) => {
+                AnyTree::Size3(MonteCarloTree::with_settings(position, settings))
+            }
+            (AnyPosition::Size4(position), Setting::Size4(settings)) => {
+                AnyTree::Size4(MonteCarloTree::with_settings(position, settings))
+            }
+            (AnyPosition::Size5(position), Setting::Size5(settings)) => {
+                AnyTree::Size5(MonteCarloTree::with_settings(position, settings))
+            }
+            (AnyPosition::Size6(position), Setting::Size6(settings)) => {
+                AnyTree::Size6(MonteCarloTree::with_settings(position, settings))
+            }
+            (AnyPosition::Size7(position), Setting::Size7(settings)) => {
+                AnyTree::Size7(MonteCarloTree::with_settings(position, settings))
+            }
+            (AnyPosition::Size8(position), Setting::Size8(settings)) => {
+                AnyTree::Size8(MonteCarloTree::with_settings(position, settings))
+            }
+            (position, settings) => panic!(
+                "Got {}s search settings for a {}s position",
+                settings.size(),
+                position.size()
+            ),
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        with_tree!(self, _tree, S => S)
+    }
+
+    /// Run one iteration of MCTS
+    pub fn select(&mut self) -> f32 {
+        with_tree!(self, tree, S => tree.select())
+    }
+
+    /// Run `nodes` iterations of MCTS. See `MonteCarloTree::search`.
+    pub fn search(&mut self, nodes: u64) {
+        with_tree!(self, tree, S => tree.search(nodes))
+    }
+
+    /// Keep searching on background threads until stopped. See `MonteCarloTree::search_in_background`.
+    pub fn search_in_background(self) -> BackgroundSearch<Self> {
+        BackgroundSearch::new(self, |tree, stop| {
+            with_tree!(tree, tree, S => {
+                if tree.position().game_result().is_none() {
+                    tree.search_until_stopped(u64::MAX, stop)
+                }
+            })
+        })
+    }
+
+    /// Search for a maximum duration, and return the best move. See `MonteCarloTree::search_for_time`.
+    pub fn search_for_time(&mut self, max_time: time::Duration) -> (AnyMove, Score) {
+        with_tree!(self, tree, S => {
+            let (mv, score) = tree.search_for_time(max_time);
+            (AnyMove::from_move::<S>(mv), score)
+        })
+    }
+
+    /// Move the root of the tree forward, keeping the searched subtree. See `MonteCarloTree::advance`.
+    /// Panics if a move is for a different board size
+    pub fn advance(&mut self, moves: &[AnyMove]) {
+        with_tree!(self, tree, S => {
+            let moves: Vec<Move> = moves
+                .iter()
+                .map(|mv| {
+                    assert_eq!(mv.size(), S, "Tried to play a {}s move on a {}s board", mv.size(), S);
+                    mv.mv().clone()
+                })
+                .collect();
+            tree.advance(&moves)
+        })
+    }
+
+    /// Returns the best move, and its score (as winning probability) from the perspective of the side to move
+    /// Panics if no search iterations have been run
+    pub fn best_move(&self) -> (AnyMove, Score) {
+        with_tree!(self, tree, S => {
+            let (mv, score) = tree.best_move();
+            (AnyMove::from_move::<S>(mv), score)
+        })
+    }
+
+    /// See `MonteCarloTree::move_visits_and_scores`
+    pub fn move_visits_and_scores(&self) -> Vec<(AnyMove, u64, Score)> {
+        with_tree!(self, tree, S => tree
+            .move_visits_and_scores()
+            .into_iter()
+            .map(|(mv, visits, score)| (AnyMove::from_move::<S>(mv), visits, score))
+            .collect())
+    }
+
+    pub fn pv(&self) -> Vec<AnyMove> {
+        with_tree!(self, tree, S => tree.pv().map(AnyMove::from_move::<S>).collect())
+    }
+
+    /// Print human-readable information of the search's progress.
+    pub fn print_info(&self) {
+        with_tree!(self, tree, S => tree.print_info())
+    }
+
+    /// See `MonteCarloTree::proven_result`
+    pub fn proven_result(&self) -> Option<ProvenResult> {
+        with_tree!(self, tree, S => tree.proven_result())
+    }
+
+    /// See `MonteCarloTree::mem_usage`
+    pub fn mem_usage(&self) -> usize {
+        with_tree!(self, tree, S => tree.mem_usage())
+    }
+
+    pub fn visits(&self) -> u64 {
+        with_tree!(self, tree, S => tree.visits())
+    }
+
+    pub fn mean_action_value(&self) -> Score {
+        with_tree!(self, tree, S => tree.mean_action_value())
+    }
+}
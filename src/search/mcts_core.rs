@@ -1,9 +1,14 @@
-use std::ops;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::{fmt, mem, ops};
 
 use board_game_traits::{Color, GameResult, Position as PositionTrait};
 use rand::distributions::Distribution;
 use rand::Rng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
+use crate::endgame;
 use crate::evaluation::parameters;
 use crate::position::Move;
 /// This module contains the core of the MCTS search algorithm
@@ -11,19 +16,79 @@ use crate::position::{GroupData, Position};
 use crate::search::{cp_to_win_percentage, MctsSetting, Score};
 
 /// A Monte Carlo Search Tree, containing every node that has been seen in search.
-#[derive(Clone, PartialEq, Debug)]
+///
+/// The tree can be searched by several threads at once. Nodes are created exactly once, and their statistics are updated atomically.
 pub struct Tree {
-    pub children: Box<[TreeEdge]>,
-    pub total_action_value: f64,
-    pub is_terminal: bool,
+    pub children: OnceLock<Box<[TreeEdge]>>,
+    /// Sum of every score backed up through the node, stored as `f64` bits
+    total_action_value: AtomicU64,
+}
+
+/// A game result that has been proven by the search, from the perspective of the side to move,
+/// with the number of plies until the game ends with best play.
+///
+/// Finished games are proven with 0 plies. Nodes proven by the endgame solver use the solver's search depth,
+/// which is only an upper bound for the length of the proven line. Such results, and results proven from them,
+/// are not `exact`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ProvenResult {
+    Win { plies: u16, exact: bool },
+    Loss { plies: u16, exact: bool },
+    Draw,
+}
+
+impl ProvenResult {
+    fn from_game_result_for_us(game_result: GameResultForUs, plies: u16, exact: bool) -> Self {
+        match game_result {
+            GameResultForUs::Win => ProvenResult::Win { plies, exact },
+            GameResultForUs::Loss => ProvenResult::Loss { plies, exact },
+            GameResultForUs::Draw => ProvenResult::Draw,
+        }
+    }
+
+    pub fn score(self) -> Score {
+        match self {
+            ProvenResult::Win { .. } => 1.0,
+            ProvenResult::Loss { .. } => 0.0,
+            ProvenResult::Draw => 0.5,
+        }
+    }
+
+    /// Packs the result into a `u32`, where 0 means unproven
+    fn to_bits(proven_result: Option<Self>) -> u32 {
+        let bound_bit = |exact: bool| if exact { 0 } else { 1 << 18 };
+        match proven_result {
+            None => 0,
+            Some(ProvenResult::Win { plies, exact }) => 1 << 16 | bound_bit(exact) | plies as u32,
+            Some(ProvenResult::Loss { plies, exact }) => 2 << 16 | bound_bit(exact) | plies as u32,
+            Some(ProvenResult::Draw) => 3 << 16,
+        }
+    }
+
+    fn from_bits(bits: u32) -> Option<Self> {
+        let plies = bits as u16;
+        let exact = bits & 1 << 18 == 0;
+        match bits >> 16 & 3 {
+            0 => None,
+            1 => Some(ProvenResult::Win { plies, exact }),
+            2 => Some(ProvenResult::Loss { plies, exact }),
+            _ => Some(ProvenResult::Draw),
+        }
+    }
 }
 
-#[derive(Clone, PartialEq, Debug)]
 pub struct TreeEdge {
-    pub child: Option<Box<Tree>>,
+    pub child: OnceLock<Box<Tree>>,
     pub mv: Move,
-    pub mean_action_value: Score,
-    pub visits: u64,
+    /// Stored as `f32` bits
+    mean_action_value: AtomicU32,
+    visits: AtomicU64,
+    /// The number of threads currently searching below this edge
+    virtual_loss: AtomicU32,
+    /// The child node's proven result, if any. See `ProvenResult::to_bits`.
+    /// Kept on the edge, so that selection doesn't need to read every child node
+    proven_result: AtomicU32,
     pub heuristic_score: Score,
 }
 
@@ -34,6 +99,8 @@ pub struct TempVectors {
     moves: Vec<(Move, f32)>,
     value_scores: Vec<Score>,
     policy_score_sets: Vec<Box<[Score]>>,
+    /// Memory allocated for new nodes and edges, which the searching thread adds to the tree's memory usage
+    pub allocated_bytes: usize,
 }
 
 impl TempVectors {
@@ -43,6 +110,7 @@ impl TempVectors {
             moves: vec![],
             value_scores: vec![0.0; parameters::num_value_features::<S>()],
             policy_score_sets: vec![],
+            allocated_bytes: 0,
         }
     }
 }
@@ -50,107 +118,317 @@ impl TempVectors {
 impl TreeEdge {
     pub fn new(mv: Move, heuristic_score: Score, mean_action_value: Score) -> Self {
         TreeEdge {
-            child: None,
+            child: OnceLock::new(),
             mv,
-            mean_action_value,
-            visits: 0,
+            mean_action_value: AtomicU32::new(mean_action_value.to_bits()),
+            visits: AtomicU64::new(0),
+            virtual_loss: AtomicU32::new(0),
+            proven_result: AtomicU32::new(0),
             heuristic_score,
         }
     }
 
+    pub fn visits(&self) -> u64 {
+        self.visits.load(Ordering::Relaxed)
+    }
+
+    pub fn mean_action_value(&self) -> Score {
+        Score::from_bits(self.mean_action_value.load(Ordering::Relaxed))
+    }
+
+    /// Increment the edge's visits, returning the new count
+    fn add_visit(&self, concurrent: bool) -> u64 {
+        if concurrent {
+            self.visits.fetch_add(1, Ordering::Relaxed) + 1
+        } else {
+            let visits = self.visits() + 1;
+            self.visits.store(visits, Ordering::Relaxed);
+            visits
+        }
+    }
+
+    fn set_mean_action_value(&self, mean_action_value: Score) {
+        self.mean_action_value
+            .store(mean_action_value.to_bits(), Ordering::Relaxed)
+    }
+
+    /// The proven result of the child node, from the perspective of the child's side to move
+    pub fn proven_result(&self) -> Option<ProvenResult> {
+        ProvenResult::from_bits(self.proven_result.load(Ordering::Relaxed))
+    }
+
+    fn set_proven_result(&self, proven_result: ProvenResult) {
+        self.proven_result.store(
+            ProvenResult::to_bits(Some(proven_result)),
+            Ordering::Relaxed,
+        )
+    }
+
+    /// Forget the child's proven result, for example after its children have changed
+    pub(crate) fn clear_proven_result(&mut self) {
+        *self.proven_result.get_mut() = 0;
+    }
+
+    /// Try to prove the child node from its children's proven results.
+    /// The node is a win if any child is a loss for the opponent, choosing the shortest win.
+    /// If every child is proven, the node is a draw if any child is a draw, and otherwise a loss, choosing the longest loss.
+    /// A loss is only exact if every child's win is exact.
+    pub(crate) fn update_proven_result(&self) -> Option<ProvenResult> {
+        let children = self.child.get()?.children.get()?;
+        // Prefer exact wins over bounds of the same length
+        let mut shortest_win: Option<(u16, bool)> = None;
+        let mut longest_loss: u16 = 0;
+        let mut loss_is_exact = true;
+        let mut has_draw = false;
+        let mut all_proven = true;
+        for edge in children.iter() {
+            match edge.proven_result() {
+                Some(ProvenResult::Loss { plies, exact }) => {
+                    let win = (plies + 1, !exact);
+                    shortest_win = Some(shortest_win.map_or(win, |shortest| shortest.min(win)))
+                }
+                Some(ProvenResult::Win { plies, exact }) => {
+                    longest_loss = longest_loss.max(plies + 1);
+                    loss_is_exact &= exact;
+                }
+                Some(ProvenResult::Draw) => has_draw = true,
+                None => all_proven = false,
+            }
+        }
+        let proven_result = match shortest_win {
+            Some((plies, is_bound)) => ProvenResult::Win {
+                plies,
+                exact: !is_bound,
+            },
+            None if !all_proven || children.is_empty() => return None,
+            None if has_draw => ProvenResult::Draw,
+            None => ProvenResult::Loss {
+                plies: longest_loss,
+                exact: loss_is_exact,
+            },
+        };
+        self.set_proven_result(proven_result);
+        Some(proven_result)
+    }
+
     /// Perform one iteration of monte carlo tree search.
+    /// Several threads may search the same tree at once, each with their own position and temporary vectors.
     ///
     /// Moves done on the board are not reversed.
     pub fn select<const S: usize>(
-        &mut self,
+        &self,
         position: &mut Position<S>,
         settings: &MctsSetting<S>,
         temp_vectors: &mut TempVectors,
     ) -> Score {
-        if self.visits == 0 {
-            self.expand(position, settings, temp_vectors)
-        } else if self.child.as_ref().unwrap().is_terminal {
-            self.visits += 1;
-            self.child.as_mut().unwrap().total_action_value += self.mean_action_value as f64;
-            self.mean_action_value
-        } else {
-            let node = self.child.as_mut().unwrap();
-            debug_assert_eq!(
-                self.visits,
-                node.children.iter().map(|edge| edge.visits).sum::<u64>() + 1,
-                "{} visits, {} total action value, {} mean action value",
-                self.visits,
-                node.total_action_value,
-                self.mean_action_value
-            );
-            // Only generate child moves on the 2nd visit
-            if self.visits == 1 {
-                let group_data = position.group_data();
-                node.init_children(position, &group_data, settings, temp_vectors);
+        self.select_inner(position, settings, temp_vectors, true)
+    }
+
+    fn select_inner<const S: usize>(
+        &self,
+        position: &mut Position<S>,
+        settings: &MctsSetting<S>,
+        temp_vectors: &mut TempVectors,
+        is_root: bool,
+    ) -> Score {
+        let node = match self.child.get() {
+            Some(node) => node,
+            None => {
+                let mut eval = None;
+                self.child.get_or_init(|| {
+                    // The root is never solved, so that its moves are still searched
+                    let (child, score) = self.expand(position, settings, temp_vectors, !is_root);
+                    eval = Some(score);
+                    child
+                });
+                // If another thread expanded the node first, search through it instead
+                return match eval {
+                    Some(eval) => eval,
+                    None => self.select_inner(position, settings, temp_vectors, is_root),
+                };
             }
+        };
 
-            let visits_sqrt = (self.visits as Score).sqrt();
-            let dynamic_cpuct = settings.c_puct_init()
-                + Score::ln(
-                    (1.0 + self.visits as Score + settings.c_puct_base()) / settings.c_puct_base(),
-                );
+        // A single searching thread needs no virtual loss, and can update the statistics without atomic read-modify-writes
+        let concurrent = settings.threads() > 1;
 
-            assert_ne!(
-                node.children.len(),
-                0,
-                "No legal moves in position\n{:?}",
-                position
-            );
-
-            let mut best_exploration_value = 0.0;
-            let mut best_child_node_index = 0;
-
-            for (i, edge) in node.children.iter().enumerate() {
-                let child_exploration_value = edge.exploration_value(visits_sqrt, dynamic_cpuct);
-                if child_exploration_value >= best_exploration_value {
-                    best_child_node_index = i;
-                    best_exploration_value = child_exploration_value;
-                }
+        // Proven nodes are not searched further, and always back up their exact score
+        if let Some(proven_result) = self.proven_result() {
+            self.add_visit(concurrent);
+            let score = proven_result.score();
+            self.set_mean_action_value(score);
+            node.add_action_value(score as f64, concurrent);
+            return score;
+        }
+
+        // Only generate child moves on the 2nd visit
+        let children = node.children.get_or_init(|| {
+            let group_data = position.group_data();
+            let children = Tree::init_children(position, &group_data, settings, temp_vectors);
+            temp_vectors.allocated_bytes += children.len() * mem::size_of::<TreeEdge>();
+            children
+        });
+
+        let visits = self.visits();
+        let visits_sqrt = (visits as Score).sqrt();
+        let dynamic_cpuct = settings.c_puct_init()
+            + Score::ln((1.0 + visits as Score + settings.c_puct_base()) / settings.c_puct_base());
+
+        assert_ne!(
+            children.len(),
+            0,
+            "No legal moves in position\n{:?}",
+            position
+        );
+
+        let mut best_exploration_value = 0.0;
+        let mut best_child_node_index = 0;
+
+        for (i, edge) in children.iter().enumerate() {
+            // Proven children are skipped. If they are wins for us, this node is already proven
+            if edge.proven_result().is_some() {
+                continue;
+            }
+            let child_exploration_value = edge.exploration_value(visits_sqrt, dynamic_cpuct);
+            if child_exploration_value >= best_exploration_value {
+                best_child_node_index = i;
+                best_exploration_value = child_exploration_value;
             }
+        }
 
-            let child_edge = node.children.get_mut(best_child_node_index).unwrap();
+        let child_edge = &children[best_child_node_index];
 
-            position.do_move(child_edge.mv.clone());
-            let result = 1.0 - child_edge.select::<S>(position, settings, temp_vectors);
-            self.visits += 1;
+        position.do_move(child_edge.mv.clone());
+        if concurrent {
+            child_edge.virtual_loss.fetch_add(1, Ordering::Relaxed);
+        }
+        let mut result =
+            1.0 - child_edge.select_inner::<S>(position, settings, temp_vectors, false);
+        if concurrent {
+            child_edge.virtual_loss.fetch_sub(1, Ordering::Relaxed);
+        }
 
-            node.total_action_value += result as f64;
+        let proven_result = if child_edge.proven_result().is_some() {
+            self.update_proven_result()
+        } else {
+            None
+        };
 
-            self.mean_action_value = (node.total_action_value / self.visits as f64) as f32;
-            result
+        let visits = self.add_visit(concurrent);
+        let total_action_value = node.add_action_value(result as f64, concurrent);
+        match proven_result {
+            Some(proven_result) => {
+                result = proven_result.score();
+                self.set_mean_action_value(result);
+            }
+            None => self.set_mean_action_value((total_action_value / visits as f64) as f32),
         }
+        result
     }
 
+    /// Evaluate the edge's position, returning the new child node and its score.
+    /// Only called once for each edge, before any other thread can see the child.
     // Never inline, for profiling purposes
     #[inline(never)]
     fn expand<const S: usize>(
-        &mut self,
+        &self,
         position: &mut Position<S>,
         settings: &MctsSetting<S>,
         temp_vectors: &mut TempVectors,
-    ) -> Score {
-        debug_assert!(self.child.is_none());
-        self.child = Some(Box::new(Tree::new_node()));
-        let child = self.child.as_mut().unwrap();
+        solve_endgame: bool,
+    ) -> (Box<Tree>, Score) {
+        // Finished games are proven exactly by the rollout, so only unfinished positions go to the solver
+        let solved_result = match settings.endgame_solver_nodes {
+            Some(max_nodes) if solve_endgame && position.game_result().is_none() => {
+                endgame::solve_flat_race(position, max_nodes)
+            }
+            _ => None,
+        };
+        let (eval, proven_result) = match solved_result {
+            Some(game_result) => {
+                let proven_result = ProvenResult::from_game_result_for_us(
+                    GameResultForUs::from_game_result(game_result, position.side_to_move()),
+                    endgame::flat_race_plies(position),
+                    false,
+                );
+                (proven_result.score(), Some(proven_result))
+            }
+            None => rollout(position, settings, settings.rollout_depth, temp_vectors),
+        };
 
-        let (eval, is_terminal) = rollout(position, settings, settings.rollout_depth, temp_vectors);
+        self.visits.store(1, Ordering::Relaxed);
+        self.set_mean_action_value(eval);
+        if let Some(proven_result) = proven_result {
+            self.set_proven_result(proven_result);
+        }
+        temp_vectors.allocated_bytes += mem::size_of::<Tree>();
+        (Box::new(Tree::new_node(eval)), eval)
+    }
 
-        self.visits = 1;
-        child.total_action_value = eval as f64;
-        self.mean_action_value = eval;
-        child.is_terminal = is_terminal;
-        eval
+    /// Memory used by the edge's subtree, not including the edge itself
+    pub fn mem_usage(&self) -> usize {
+        self.child.get().map_or(0, |node| {
+            mem::size_of::<Tree>()
+                + node
+                    .children()
+                    .iter()
+                    .map(|edge| mem::size_of::<TreeEdge>() + edge.mem_usage())
+                    .sum::<usize>()
+        })
     }
 
+    /// The exploration value of the edge, for the parent's side to move.
+    /// Edges that other threads are currently searching are penalized, as if every pending visit was a loss,
+    /// so that threads tend to search different parts of the tree.
     #[inline]
     pub fn exploration_value(&self, parent_visits_sqrt: Score, cpuct: Score) -> Score {
-        (1.0 - self.mean_action_value)
-            + cpuct * self.heuristic_score * parent_visits_sqrt / (1 + self.visits) as Score
+        let visits = self.visits() as Score;
+        let virtual_loss = self.virtual_loss.load(Ordering::Relaxed) as Score;
+        let mean_action_value = if virtual_loss == 0.0 {
+            self.mean_action_value()
+        } else {
+            (self.mean_action_value() * visits + virtual_loss) / (visits + virtual_loss)
+        };
+        (1.0 - mean_action_value)
+            + cpuct * self.heuristic_score * parent_visits_sqrt / (1.0 + visits + virtual_loss)
+    }
+}
+
+impl Clone for TreeEdge {
+    fn clone(&self) -> Self {
+        TreeEdge {
+            child: self.child.clone(),
+            mv: self.mv.clone(),
+            mean_action_value: AtomicU32::new(self.mean_action_value.load(Ordering::Relaxed)),
+            visits: AtomicU64::new(self.visits()),
+            virtual_loss: AtomicU32::new(self.virtual_loss.load(Ordering::Relaxed)),
+            proven_result: AtomicU32::new(self.proven_result.load(Ordering::Relaxed)),
+            heuristic_score: self.heuristic_score,
+        }
+    }
+}
+
+impl PartialEq for TreeEdge {
+    fn eq(&self, other: &Self) -> bool {
+        self.child == other.child
+            && self.mv == other.mv
+            && self.mean_action_value() == other.mean_action_value()
+            && self.visits() == other.visits()
+            && self.proven_result() == other.proven_result()
+            && self.heuristic_score == other.heuristic_score
+    }
+}
+
+impl fmt::Debug for TreeEdge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TreeEdge")
+            .field("child", &self.child)
+            .field("mv", &self.mv)
+            .field("mean_action_value", &self.mean_action_value())
+            .field("visits", &self.visits())
+            .field("proven_result", &self.proven_result())
+            .field("heuristic_score", &self.heuristic_score)
+            .finish()
     }
 }
 
@@ -159,12 +437,11 @@ impl Tree {
     /// Never inline, for profiling purposes
     #[inline(never)]
     fn init_children<const S: usize>(
-        &mut self,
         position: &Position<S>,
         group_data: &GroupData<S>,
         settings: &MctsSetting<S>,
         temp_vectors: &mut TempVectors,
-    ) {
+    ) -> Box<[TreeEdge]> {
         position.generate_moves_with_params(
             &settings.policy_params,
             group_data,
@@ -183,14 +460,82 @@ impl Tree {
                 settings.initial_mean_action_value(),
             ));
         }
-        self.children = children_vec.into_boxed_slice();
+        children_vec.into_boxed_slice()
     }
 
-    fn new_node() -> Self {
+    fn new_node(eval: Score) -> Self {
         Tree {
-            children: Box::new([]),
-            total_action_value: 0.0,
-            is_terminal: false,
+            children: OnceLock::new(),
+            total_action_value: AtomicU64::new((eval as f64).to_bits()),
+        }
+    }
+
+    /// The node's children, or an empty slice if they haven't been generated yet
+    pub fn children(&self) -> &[TreeEdge] {
+        self.children.get().map_or(&[], |children| children)
+    }
+
+    pub fn total_action_value(&self) -> f64 {
+        f64::from_bits(self.total_action_value.load(Ordering::Relaxed))
+    }
+
+    /// Add a score to the total action value, returning the new total.
+    /// The addition is only atomic if other threads may be searching the node `concurrent`ly
+    fn add_action_value(&self, score: f64, concurrent: bool) -> f64 {
+        if !concurrent {
+            let total_action_value = self.total_action_value() + score;
+            self.total_action_value
+                .store(total_action_value.to_bits(), Ordering::Relaxed);
+            return total_action_value;
+        }
+        let old_bits = self
+            .total_action_value
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+                Some((f64::from_bits(bits) + score).to_bits())
+            })
+            .unwrap();
+        f64::from_bits(old_bits) + score
+    }
+
+    /// Memory that would be freed by removing the node's children, not counting memory below the children's nodes
+    fn children_mem_usage(&self) -> usize {
+        self.children()
+            .iter()
+            .map(|edge| {
+                mem::size_of::<TreeEdge>() + edge.child.get().map_or(0, |_| mem::size_of::<Tree>())
+            })
+            .sum()
+    }
+
+    /// For every node below this one that has children, collect the visits of the edge to the node,
+    /// and the memory used by its children. See `prune`
+    pub(crate) fn collect_prunable_nodes(&self, nodes: &mut Vec<(u64, usize)>) {
+        for edge in self.children() {
+            if let Some(node) = edge.child.get() {
+                if node.children.get().is_some() {
+                    nodes.push((edge.visits(), node.children_mem_usage()));
+                    node.collect_prunable_nodes(nodes);
+                }
+            }
+        }
+    }
+
+    /// Remove the children of every node below this one whose edge has at most `max_visits` visits.
+    /// The nodes keep their statistics, and their children are generated again if they are visited.
+    ///
+    /// A node never has more visits than its parent, so every pruned node's subtree is also pruned.
+    pub(crate) fn prune(&mut self, max_visits: u64) {
+        if let Some(children) = self.children.get_mut() {
+            for edge in children.iter_mut() {
+                let visits = edge.visits();
+                if let Some(node) = edge.child.get_mut() {
+                    if visits <= max_visits {
+                        node.children = OnceLock::new();
+                    } else {
+                        node.prune(max_visits);
+                    }
+                }
+            }
         }
     }
 
@@ -199,11 +544,14 @@ impl Tree {
     /// `alpha` is used to generate the noise, lower values generate more varied noise.
     /// Values above 1 are less noisy, and tend towards uniform outputs
     pub fn apply_dirichlet(&mut self, epsilon: f32, alpha: f32) {
+        let children = match self.children.get_mut() {
+            Some(children) => children,
+            None => return,
+        };
         let mut rng = rand::thread_rng();
-        let dirichlet = rand_distr::Dirichlet::new_with_size(alpha, self.children.len()).unwrap();
+        let dirichlet = rand_distr::Dirichlet::new_with_size(alpha, children.len()).unwrap();
         let noise_vec = dirichlet.sample(&mut rng);
-        for (child_prior, eta) in self
-            .children
+        for (child_prior, eta) in children
             .iter_mut()
             .map(|child| &mut child.heuristic_score)
             .zip(noise_vec)
@@ -213,6 +561,30 @@ impl Tree {
     }
 }
 
+impl Clone for Tree {
+    fn clone(&self) -> Self {
+        Tree {
+            children: self.children.clone(),
+            total_action_value: AtomicU64::new(self.total_action_value.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl PartialEq for Tree {
+    fn eq(&self, other: &Self) -> bool {
+        self.children == other.children && self.total_action_value() == other.total_action_value()
+    }
+}
+
+impl fmt::Debug for Tree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tree")
+            .field("children", &self.children)
+            .field("total_action_value", &self.total_action_value())
+            .finish()
+    }
+}
+
 /// Do a mcts rollout up to `depth` plies, before doing a static evaluation.
 /// Depth is 0 on default settings, in which case it immediately does a static evaluation
 /// Higher depths are mainly used for playing with reduced difficulty
@@ -223,10 +595,8 @@ pub fn rollout<const S: usize>(
     settings: &MctsSetting<S>,
     depth: u16,
     temp_vectors: &mut TempVectors,
-) -> (Score, bool) {
-    let group_data = position.group_data();
-
-    if let Some(game_result) = position.game_result_with_group_data(&group_data) {
+) -> (Score, Option<ProvenResult>) {
+    if let Some(game_result) = position.game_result() {
         let game_result_for_us = match (game_result, position.side_to_move()) {
             (GameResult::Draw, _) => GameResultForUs::Draw,
             (GameResult::WhiteWin, Color::Black) => GameResultForUs::Loss, // The side to move has lost
@@ -235,18 +605,27 @@ pub fn rollout<const S: usize>(
             (GameResult::BlackWin, Color::Black) => GameResultForUs::Win, // The side to move has lost
         };
 
-        (game_result_for_us.score(), true)
+        (
+            game_result_for_us.score(),
+            Some(ProvenResult::from_game_result_for_us(
+                game_result_for_us,
+                0,
+                true,
+            )),
+        )
     } else if depth == 0 {
+        let group_data = position.group_data();
         let static_eval = cp_to_win_percentage(position.static_eval_with_params_and_data(
             &group_data,
             &settings.value_params,
             &mut temp_vectors.value_scores,
         ));
         match position.side_to_move() {
-            Color::White => (static_eval, false),
-            Color::Black => (1.0 - static_eval, false),
+            Color::White => (static_eval, None),
+            Color::Black => (1.0 - static_eval, None),
         }
     } else {
+        let group_data = position.group_data();
         position.generate_moves_with_probabilities(
             &group_data,
             &mut temp_vectors.simple_moves,
@@ -262,7 +641,7 @@ pub fn rollout<const S: usize>(
 
         temp_vectors.moves.clear();
         let (score, _) = rollout(position, settings, depth - 1, temp_vectors);
-        (1.0 - score, false)
+        (1.0 - score, None)
     }
 }
 
@@ -287,6 +666,16 @@ impl ops::Not for GameResultForUs {
 }
 
 impl GameResultForUs {
+    fn from_game_result(game_result: GameResult, side_to_move: Color) -> Self {
+        match (game_result, side_to_move) {
+            (GameResult::Draw, _) => GameResultForUs::Draw,
+            (GameResult::WhiteWin, Color::Black) => GameResultForUs::Loss,
+            (GameResult::BlackWin, Color::White) => GameResultForUs::Loss,
+            (GameResult::WhiteWin, Color::White) => GameResultForUs::Win,
+            (GameResult::BlackWin, Color::Black) => GameResultForUs::Win,
+        }
+    }
+
     fn score(self) -> Score {
         match self {
             GameResultForUs::Win => 1.0,
@@ -296,6 +685,28 @@ impl GameResultForUs {
     }
 }
 
+/// The best move to play from a node: The shortest proven win, otherwise the most visited move that isn't a proven loss,
+/// and otherwise the longest proven loss. A proven draw is preferred over a move that is expected to lose.
+pub fn best_child(children: &[TreeEdge]) -> Option<&TreeEdge> {
+    // Results are from the perspective of the child's side to move
+    let best = children
+        .iter()
+        .max_by_key(|edge| match edge.proven_result() {
+            Some(ProvenResult::Loss { plies, .. }) => (2, u64::MAX - plies as u64),
+            Some(ProvenResult::Win { plies, .. }) => (0, plies as u64),
+            Some(ProvenResult::Draw) | None => (1, edge.visits()),
+        })?;
+    if best.proven_result().is_none() && best.mean_action_value() > 0.5 {
+        if let Some(draw) = children
+            .iter()
+            .find(|edge| edge.proven_result() == Some(ProvenResult::Draw))
+        {
+            return Some(draw);
+        }
+    }
+    Some(best)
+}
+
 pub struct Pv<'a> {
     tree: &'a Tree,
 }
@@ -310,16 +721,12 @@ impl<'a> Iterator for Pv<'a> {
     type Item = Move;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.tree
-            .children
-            .iter()
-            .max_by_key(|edge| edge.visits)
-            .and_then(|edge| {
-                edge.child.as_ref().map(|child| {
-                    self.tree = child;
-                    edge.mv.clone()
-                })
+        best_child(self.tree.children()).and_then(|edge| {
+            edge.child.get().map(|child| {
+                self.tree = child;
+                edge.mv.clone()
             })
+        })
     }
 }
 
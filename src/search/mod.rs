@@ -2,16 +2,25 @@
 //!
 //! This implementation does not use full Monte Carlo rollouts, relying on a heuristic evaluation when expanding new nodes instead.
 
-use std::{mem, time};
+use board_game_traits::Position as PositionTrait;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::{mem, thread, time};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 use crate::position::Move;
 use crate::position::Position;
 use crate::position::{Role, Square};
 pub use crate::search::mcts_core::best_move;
+pub use crate::search::mcts_core::ProvenResult;
 use crate::search::mcts_core::{TempVectors, Tree};
 
-use self::mcts_core::{Pv, TreeEdge};
+pub use self::any_tree::{AnyMctsSetting, AnyTree};
+use self::mcts_core::{best_child, Pv, TreeEdge};
 
+mod any_tree;
 /// This module contains the public-facing convenience API for the search.
 /// The implementation itself in in mcts_core.
 mod mcts_core;
@@ -25,6 +34,9 @@ pub struct MctsSetting<const S: usize> {
     excluded_moves: Vec<Move>,
     rollout_depth: u16,
     rollout_temperature: f64,
+    endgame_solver_nodes: Option<u64>,
+    threads: usize,
+    memory_limit: Option<usize>,
 }
 
 impl<const S: usize> Default for MctsSetting<S> {
@@ -37,6 +49,9 @@ impl<const S: usize> Default for MctsSetting<S> {
             excluded_moves: vec![],
             rollout_depth: 0,
             rollout_temperature: 0.25,
+            endgame_solver_nodes: None,
+            threads: 1,
+            memory_limit: None,
         }
     }
 }
@@ -80,6 +95,35 @@ impl<const N: usize> MctsSetting<N> {
         self
     }
 
+    /// Solve flat race endgames exactly when expanding new nodes, making at most `max_nodes` moves for each.
+    /// Proven results are treated as terminal nodes. See `endgame::solve_flat_race`.
+    pub fn add_endgame_solver(mut self, max_nodes: u64) -> Self {
+        self.endgame_solver_nodes = Some(max_nodes);
+        self
+    }
+
+    /// The number of threads searching the tree concurrently. Defaults to 1.
+    /// Running more threads makes the search faster, but non-deterministic.
+    pub fn add_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// The maximum memory used by the search tree, in bytes. Defaults to no limit.
+    /// When the tree reaches the limit, the least visited subtrees are pruned, so that the search can continue indefinitely.
+    pub fn add_memory_limit(mut self, bytes: usize) -> Self {
+        self.memory_limit = Some(bytes);
+        self
+    }
+
+    pub fn memory_limit(&self) -> Option<usize> {
+        self.memory_limit
+    }
+
     pub fn c_puct_init(&self) -> Score {
         self.search_params[0]
     }
@@ -100,6 +144,19 @@ impl<const N: usize> MctsSetting<N> {
 /// Type alias for winning probability, used for scoring positions.
 pub type Score = f32;
 
+/// The outcome of a search, which can be serialized with the `serde` feature.
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SearchResult {
+    pub best_move: Move,
+    /// Winning probability for the side to move, after playing the best move
+    pub score: Score,
+    pub pv: Vec<Move>,
+    pub visits: u64,
+    /// The number of visits to each legal move
+    pub move_visits: Vec<(Move, u64)>,
+}
+
 /// Abstract representation of a Monte Carlo Search Tree.
 /// Gives more fine-grained control of the search process compared to using the `mcts` function.
 #[derive(Clone, PartialEq, Debug)]
@@ -108,100 +165,352 @@ pub struct MonteCarloTree<const S: usize> {
     position: Position<S>,
     settings: MctsSetting<S>,
     temp_vectors: TempVectors,
+    /// Memory used by the tree's nodes and edges
+    mem_usage: usize,
 }
 
 impl<const S: usize> MonteCarloTree<S> {
     pub fn new(position: Position<S>) -> Self {
         MonteCarloTree {
-            edge: TreeEdge {
-                child: None,
-                mv: Move::Place(Role::Flat, Square(0)),
-                mean_action_value: 0.0,
-                visits: 0,
-                heuristic_score: 0.0,
-            },
+            edge: Self::root_edge(),
             position,
             settings: MctsSetting::default(),
             temp_vectors: TempVectors::new::<S>(),
+            mem_usage: 0,
         }
     }
 
     pub fn with_settings(position: Position<S>, settings: MctsSetting<S>) -> Self {
-        #[allow(unused_mut)]
         let mut tree = MonteCarloTree {
-            edge: TreeEdge {
-                child: None,
-                mv: Move::Place(Role::Flat, Square(0)),
-                mean_action_value: 0.0,
-                visits: 0,
-                heuristic_score: 0.0,
-            },
+            edge: Self::root_edge(),
             position,
-            settings: settings.clone(),
+            settings,
             temp_vectors: TempVectors::new::<S>(),
+            mem_usage: 0,
         };
+        tree.prepare_root();
+        tree
+    }
 
-        if let Some(alpha) = tree.settings.dirichlet {
-            tree.select();
-            tree.select();
-            (*tree.edge.child.as_mut().unwrap()).apply_dirichlet(0.25, alpha);
+    /// A virtual edge to the root node, with fake move and heuristic score
+    fn root_edge() -> TreeEdge {
+        TreeEdge::new(Move::Place(Role::Flat, Square(0)), 0.0, 0.0)
+    }
+
+    /// Apply the root-only settings, Dirichlet noise and excluded moves, to the root's children
+    fn prepare_root(&mut self) {
+        if self.settings.dirichlet.is_none() && self.settings.excluded_moves.is_empty() {
+            return;
+        }
+        // The root's children are generated on its second visit
+        while self.visits() < 2 {
+            self.select();
         }
 
-        if !tree.settings.excluded_moves.is_empty() {
-            tree.select();
-            tree.select();
-            let filtered_edges: Vec<TreeEdge> = tree
-                .edge
-                .child
-                .as_ref()
-                .unwrap()
-                .children
+        if let Some(alpha) = self.settings.dirichlet {
+            self.root_mut().apply_dirichlet(0.25, alpha);
+        }
+
+        if !self.settings.excluded_moves.is_empty() {
+            let filtered_edges: Vec<TreeEdge> = self
+                .children()
                 .iter()
-                .filter(|edge| !settings.excluded_moves.contains(&edge.mv))
+                .filter(|edge| !self.settings.excluded_moves.contains(&edge.mv))
                 .cloned()
                 .collect();
-            tree.edge.child.as_mut().unwrap().children = filtered_edges.into_boxed_slice();
+            self.root_mut().children = OnceLock::from(filtered_edges.into_boxed_slice());
+            // The root may have been proven by one of the excluded moves
+            self.edge.clear_proven_result();
+            self.edge.update_proven_result();
+            self.mem_usage = self.edge.mem_usage();
         }
+    }
 
-        tree
+    /// Move the root of the tree forward by playing `moves`, keeping the subtree of the new root and its statistics.
+    /// If a move has not been searched, the search restarts from the new position.
+    ///
+    /// Excluded moves only apply to the position the tree was created with, and are cleared.
+    /// Dirichlet noise is applied again to the new root.
+    pub fn advance(&mut self, moves: &[Move]) {
+        for mv in moves {
+            let old_edge = mem::replace(&mut self.edge, Self::root_edge());
+            let new_edge = old_edge
+                .child
+                .into_inner()
+                .and_then(|root| root.children.into_inner())
+                .and_then(|children| children.into_vec().into_iter().find(|edge| edge.mv == *mv));
+            self.position.do_move(mv.clone());
+
+            // Nodes solved by the endgame solver have no children, but the root must still be searched
+            if let Some(edge) = new_edge.filter(|edge| {
+                edge.child.get().is_some_and(|node| {
+                    edge.proven_result().is_none()
+                        || node.children.get().is_some()
+                        || self.position.game_result().is_some()
+                })
+            }) {
+                self.edge = edge;
+            }
+        }
+        if !moves.is_empty() {
+            self.settings.excluded_moves.clear();
+            self.mem_usage = self.edge.mem_usage();
+            self.prepare_root();
+        }
+    }
+
+    pub fn position(&self) -> &Position<S> {
+        &self.position
     }
 
     /// Run one iteration of MCTS
     pub fn select(&mut self) -> f32 {
-        self.edge.select::<S>(
+        let score = self.edge.select::<S>(
             &mut self.position.clone(),
             &self.settings,
             &mut self.temp_vectors,
-        )
+        );
+        self.mem_usage += mem::take(&mut self.temp_vectors.allocated_bytes);
+        score
+    }
+
+    /// Run `nodes` iterations of MCTS, divided between the number of threads in the settings.
+    /// The threads search the same tree, using virtual loss to spread out across it.
+    pub fn search(&mut self, nodes: u64) {
+        self.search_until_stopped(nodes, &AtomicBool::new(false))
+    }
+
+    /// Run up to `nodes` iterations of MCTS, stopping early if `stop` is set, or if the root has been proven.
+    /// Prunes the tree whenever it reaches the memory limit.
+    fn search_until_stopped(&mut self, nodes: u64, stop: &AtomicBool) {
+        let mut nodes_searched = 0;
+        loop {
+            nodes_searched += self.search_until_full(nodes - nodes_searched, stop);
+            if !self.is_full() {
+                break;
+            }
+            self.prune();
+            // Give up if the root's children alone are too large
+            if self.is_full() || nodes_searched >= nodes {
+                break;
+            }
+        }
+    }
+
+    /// Run up to `nodes` iterations of MCTS, stopping early if `stop` is set, if the root has been proven,
+    /// or if the tree has reached the memory limit. Returns the number of iterations run
+    fn search_until_full(&mut self, nodes: u64, stop: &AtomicBool) -> u64 {
+        if self.settings.threads <= 1 {
+            for i in 0..nodes {
+                if stop.load(Ordering::Relaxed)
+                    || self.edge.proven_result().is_some()
+                    || self.is_full()
+                {
+                    return i;
+                }
+                self.select();
+            }
+            return nodes;
+        }
+        let nodes_searched = AtomicU64::new(0);
+        let shared = SharedSearch::new(self, stop);
+        thread::scope(|scope| {
+            for _ in 0..self.settings.threads {
+                scope.spawn(|| {
+                    let mut temp_vectors = TempVectors::new::<S>();
+                    while !shared.is_stopped()
+                        && nodes_searched.fetch_add(1, Ordering::Relaxed) < nodes
+                    {
+                        shared.select(&mut temp_vectors);
+                    }
+                });
+            }
+        });
+        self.mem_usage = shared.mem_usage.into_inner();
+        nodes_searched.into_inner().min(nodes)
+    }
+
+    fn is_full(&self) -> bool {
+        self.settings
+            .memory_limit
+            .is_some_and(|memory_limit| self.mem_usage > memory_limit)
+    }
+
+    /// Prune the least visited subtrees, until the tree uses at most 3/4 of the memory limit.
+    /// The root's children are never pruned.
+    fn prune(&mut self) {
+        let Some(memory_limit) = self.settings.memory_limit else {
+            return;
+        };
+        let mut prunable_nodes = vec![];
+        self.root().collect_prunable_nodes(&mut prunable_nodes);
+        prunable_nodes.sort_unstable_by_key(|(visits, _)| *visits);
+
+        // Every node with at most `max_visits` visits is pruned, which includes all their subtrees
+        let target = memory_limit / 4 * 3;
+        let mut freed = 0;
+        let mut max_visits = None;
+        for (i, (visits, bytes)) in prunable_nodes.iter().enumerate() {
+            freed += bytes;
+            let is_last_with_visits = prunable_nodes
+                .get(i + 1)
+                .is_none_or(|(next_visits, _)| next_visits > visits);
+            if is_last_with_visits {
+                max_visits = Some(*visits);
+                if self.mem_usage.saturating_sub(freed) <= target {
+                    break;
+                }
+            }
+        }
+        if let Some(max_visits) = max_visits {
+            self.root_mut().prune(max_visits);
+            self.mem_usage = self.mem_usage.saturating_sub(freed);
+            debug_assert_eq!(self.mem_usage, self.edge.mem_usage());
+        }
+    }
+
+    /// Memory used by the tree's nodes and edges, in bytes
+    pub fn mem_usage(&self) -> usize {
+        self.mem_usage
+    }
+
+    /// Keep searching the tree on background threads, until the returned search is stopped.
+    /// Used for pondering on the opponent's time.
+    pub fn search_in_background(self) -> BackgroundSearch<Self> {
+        BackgroundSearch::new(self, |tree, stop| {
+            // The root must have legal moves, or the search would never stop expanding anything
+            if tree.position.game_result().is_none() {
+                tree.search_until_stopped(u64::MAX, stop)
+            }
+        })
+    }
+
+    /// Search for a maximum duration, and return the best move. See `play_move_time`.
+    /// Visits from earlier searches in the tree count towards deciding the move.
+    pub fn search_for_time(&mut self, max_time: time::Duration) -> (Move, Score) {
+        let nodes_per_iteration = if self.settings.rollout_depth == 0 {
+            200
+        } else if self.settings.rollout_depth < 10 {
+            40
+        } else {
+            20
+        };
+        let start_time = time::Instant::now();
+        let mut iteration = 1;
+
+        if self.settings.threads <= 1 {
+            loop {
+                self.search(iteration * nodes_per_iteration);
+                iteration += 1;
+                if self.should_stop_search(start_time, max_time) {
+                    return self.best_move();
+                }
+            }
+        }
+
+        // The helper threads keep searching in the background, until the tree is full or the search is stopped.
+        // They are only restarted after pruning
+        loop {
+            let stop = AtomicBool::new(false);
+            let shared = SharedSearch::new(self, &stop);
+            let finished = thread::scope(|scope| {
+                for _ in 1..self.settings.threads {
+                    scope.spawn(|| {
+                        let mut temp_vectors = TempVectors::new::<S>();
+                        while !shared.is_stopped() {
+                            shared.select(&mut temp_vectors);
+                        }
+                    });
+                }
+                let mut temp_vectors = TempVectors::new::<S>();
+                let finished = loop {
+                    for _ in 0..iteration * nodes_per_iteration {
+                        if shared.is_stopped() {
+                            break;
+                        }
+                        shared.select(&mut temp_vectors);
+                    }
+                    iteration += 1;
+                    if self.should_stop_search(start_time, max_time) {
+                        break true;
+                    }
+                    if shared.is_full() {
+                        break false;
+                    }
+                };
+                stop.store(true, Ordering::Relaxed);
+                finished
+            });
+            self.mem_usage = shared.mem_usage.into_inner();
+            if self.is_full() {
+                self.prune();
+                // Give up if the root's children alone are too large
+                if self.is_full() {
+                    return self.best_move();
+                }
+            }
+            if finished {
+                return self.best_move();
+            }
+        }
+    }
+
+    /// Whether `search_for_time` should stop searching and play its best move
+    fn should_stop_search(&self, start_time: time::Instant, max_time: time::Duration) -> bool {
+        if max_time < (time::Duration::from_millis(10))
+            || start_time.elapsed() > max_time - (time::Duration::from_millis(10))
+            || self.children().len() == 1
+            || self.proven_result().is_some()
+        {
+            return true;
+        }
+        let (best_move, best_score) = self.best_move();
+
+        let mut child_refs: Vec<&TreeEdge> = self.children().iter().collect();
+        child_refs.sort_by_key(|edge| edge.visits());
+        child_refs.reverse();
+
+        let node_ratio = child_refs[1].visits() as f32 / child_refs[0].visits() as f32;
+        let time_ratio = start_time.elapsed().as_secs_f32() / max_time.as_secs_f32();
+
+        // Do not stop if any other child nodes have better action value
+        time_ratio.powf(2.0) > node_ratio / 2.0
+            && !self
+                .children()
+                .iter()
+                .any(|edge| edge.mv != best_move && 1.0 - edge.mean_action_value() > best_score)
+    }
+
+    fn root(&self) -> &Tree {
+        self.edge.child.get().unwrap()
+    }
+
+    fn root_mut(&mut self) -> &mut Tree {
+        self.edge.child.get_mut().unwrap()
     }
 
     /// Returns the best move, and its score (as winning probability) from the perspective of the side to move
     /// Panics if no search iterations have been run
+    /// Proven wins are always preferred, and proven losses avoided. See `ProvenResult`.
     pub fn best_move(&self) -> (Move, f32) {
-        self.edge
-            .child
-            .as_ref()
-            .unwrap()
-            .children
-            .iter()
-            .max_by_key(|edge| edge.visits)
-            .map(|edge| (edge.mv.clone(), 1.0 - edge.mean_action_value))
+        best_child(self.children())
+            .map(|edge| (edge.mv.clone(), 1.0 - edge.mean_action_value()))
             .unwrap_or_else(|| panic!("Couldn't find best move"))
     }
 
     fn children(&self) -> &[TreeEdge] {
-        &self.edge.child.as_ref().unwrap().children
+        self.root().children()
     }
 
     pub fn pv(&self) -> impl Iterator<Item = Move> + '_ {
-        Pv::new(self.edge.child.as_ref().unwrap())
+        Pv::new(self.root())
     }
 
     /// Print human-readable information of the search's progress.
     pub fn print_info(&self) {
         let mut best_children: Vec<&TreeEdge> = self.children().iter().collect();
 
-        best_children.sort_by_key(|edge| edge.visits);
+        best_children.sort_by_key(|edge| edge.visits());
         best_children.reverse();
         let dynamic_cpuct = self.settings.c_puct_init()
             + Score::ln(
@@ -212,29 +521,141 @@ impl<const S: usize> MonteCarloTree<S> {
         best_children.iter().take(8).for_each(|edge| {
             println!(
                 "Move {}: {} visits, {:.2}% mean action value, {:.3}% static score, {:.3} exploration value, pv {}",
-                edge.mv.to_string::<S>(), edge.visits, edge.mean_action_value * 100.0, edge.heuristic_score * 100.0,
+                edge.mv.to_string::<S>(), edge.visits(), edge.mean_action_value() * 100.0, edge.heuristic_score * 100.0,
                 edge.exploration_value((self.visits() as Score).sqrt(), dynamic_cpuct),
-                Pv::new(edge.child.as_ref().unwrap()).map(|mv| mv.to_string::<S>() + " ").collect::<String>()
+                edge.child.get().into_iter().flat_map(|child| Pv::new(child)).map(|mv| mv.to_string::<S>() + " ").collect::<String>()
             )
         });
     }
 
+    /// Summarize the search so far. Panics if no search iterations have been run
+    pub fn search_result(&self) -> SearchResult {
+        let (best_move, score) = self.best_move();
+        SearchResult {
+            best_move,
+            score,
+            pv: self.pv().collect(),
+            visits: self.visits(),
+            move_visits: self
+                .children()
+                .iter()
+                .map(|edge| (edge.mv.clone(), edge.visits()))
+                .collect(),
+        }
+    }
+
+    /// Every legal move, with its number of visits and its score (as winning probability) from the perspective of the side to move.
+    /// Panics if no search iterations have been run
+    pub fn move_visits_and_scores(&self) -> Vec<(Move, u64, Score)> {
+        self.children()
+            .iter()
+            .map(|edge| {
+                (
+                    edge.mv.clone(),
+                    edge.visits(),
+                    1.0 - edge.mean_action_value(),
+                )
+            })
+            .collect()
+    }
+
+    /// The root's proven result from the perspective of the side to move, if the search has proven it
+    pub fn proven_result(&self) -> Option<ProvenResult> {
+        self.edge.proven_result()
+    }
+
     pub fn visits(&self) -> u64 {
-        self.edge.visits
+        self.edge.visits()
     }
 
     pub fn mean_action_value(&self) -> Score {
-        self.edge.mean_action_value
+        self.edge.mean_action_value()
+    }
+}
+
+/// A tree being searched by several threads at once, each with their own temporary vectors.
+/// The tree's memory usage is tracked atomically while the threads are running
+struct SharedSearch<'a, const S: usize> {
+    edge: &'a TreeEdge,
+    position: &'a Position<S>,
+    settings: &'a MctsSetting<S>,
+    mem_usage: AtomicUsize,
+    stop: &'a AtomicBool,
+}
+
+impl<'a, const S: usize> SharedSearch<'a, S> {
+    fn new(tree: &'a MonteCarloTree<S>, stop: &'a AtomicBool) -> Self {
+        SharedSearch {
+            edge: &tree.edge,
+            position: &tree.position,
+            settings: &tree.settings,
+            mem_usage: AtomicUsize::new(tree.mem_usage),
+            stop,
+        }
+    }
+
+    /// Whether the threads should stop, because `stop` is set, the root has been proven, or the tree is full
+    fn is_stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed) || self.edge.proven_result().is_some() || self.is_full()
+    }
+
+    fn is_full(&self) -> bool {
+        self.settings
+            .memory_limit
+            .is_some_and(|memory_limit| self.mem_usage.load(Ordering::Relaxed) > memory_limit)
+    }
+
+    fn select(&self, temp_vectors: &mut TempVectors) {
+        self.edge
+            .select::<S>(&mut self.position.clone(), self.settings, temp_vectors);
+        self.mem_usage.fetch_add(
+            mem::take(&mut temp_vectors.allocated_bytes),
+            Ordering::Relaxed,
+        );
+    }
+}
+
+/// A search running on a background thread, which owns the tree until the search is stopped.
+/// Dropping it also stops the search.
+pub struct BackgroundSearch<T> {
+    stop: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<T>>,
+}
+
+impl<T: Send + 'static> BackgroundSearch<T> {
+    fn new(mut tree: T, search: fn(&mut T, &AtomicBool)) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let handle = thread::spawn(move || {
+            search(&mut tree, &thread_stop);
+            tree
+        });
+        BackgroundSearch {
+            stop,
+            handle: Some(handle),
+        }
+    }
+
+    /// Stop the search, and get back the tree, including everything searched in the background
+    pub fn stop(mut self) -> T {
+        self.stop.store(true, Ordering::Relaxed);
+        self.handle.take().unwrap().join().unwrap()
+    }
+}
+
+impl<T> Drop for BackgroundSearch<T> {
+    fn drop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            self.stop.store(true, Ordering::Relaxed);
+            let _ = handle.join();
+        }
     }
 }
 
 /// The simplest way to use the mcts module. Run Monte Carlo Tree Search for `nodes` nodes, returning the best move, and its estimated winning probability for the side to move.
 pub fn mcts<const S: usize>(position: Position<S>, nodes: u64) -> (Move, Score) {
     let mut tree = MonteCarloTree::new(position);
-
-    for _ in 0..nodes.max(2) {
-        tree.select();
-    }
+    tree.search(nodes.max(2));
     let (mv, score) = tree.best_move();
     (mv, score)
 }
@@ -247,50 +668,8 @@ pub fn play_move_time<const S: usize>(
     max_time: time::Duration,
     settings: MctsSetting<S>,
 ) -> (Move, Score) {
-    let nodes_per_iteration = if settings.rollout_depth == 0 {
-        200
-    } else if settings.rollout_depth < 10 {
-        40
-    } else {
-        20
-    };
     let mut tree = MonteCarloTree::with_settings(board, settings);
-    let start_time = time::Instant::now();
-
-    for i in 1.. {
-        for _ in 0..i * nodes_per_iteration {
-            tree.select();
-        }
-
-        let (best_move, best_score) = tree.best_move();
-
-        if max_time < (time::Duration::from_millis(10))
-            || start_time.elapsed() > max_time - (time::Duration::from_millis(10))
-            || tree.children().len() == 1
-        {
-            return tree.best_move();
-        }
-
-        let mut child_refs: Vec<&TreeEdge> = tree.children().iter().collect();
-        child_refs.sort_by_key(|edge| edge.visits);
-        child_refs.reverse();
-
-        let node_ratio = child_refs[1].visits as f32 / child_refs[0].visits as f32;
-        let time_ratio = start_time.elapsed().as_secs_f32() / max_time.as_secs_f32();
-
-        if time_ratio.powf(2.0) > node_ratio / 2.0 {
-            // Do not stop if any other child nodes have better action value
-            if tree
-                .children()
-                .iter()
-                .any(|edge| edge.mv != best_move && 1.0 - edge.mean_action_value > best_score)
-            {
-                continue;
-            }
-            return (best_move, best_score);
-        }
-    }
-    unreachable!()
+    tree.search_for_time(max_time)
 }
 
 /// Run mcts with specific static evaluation parameters, for optimization the parameter set.
@@ -301,14 +680,19 @@ pub fn mcts_training<const S: usize>(
     settings: MctsSetting<S>,
 ) -> Vec<(Move, Score)> {
     let mut tree = MonteCarloTree::with_settings(position, settings);
+    mcts_training_with_tree(&mut tree, nodes)
+}
 
-    for _ in 0..nodes {
-        tree.select();
-    }
-    let child_visits: u64 = tree.children().iter().map(|edge| edge.visits).sum();
+/// Like `mcts_training`, but continues the search in an existing tree until its root has `nodes` visits.
+pub fn mcts_training_with_tree<const S: usize>(
+    tree: &mut MonteCarloTree<S>,
+    nodes: u64,
+) -> Vec<(Move, Score)> {
+    tree.search(nodes.saturating_sub(tree.visits()));
+    let child_visits: u64 = tree.children().iter().map(|edge| edge.visits()).sum();
     tree.children()
         .iter()
-        .map(|edge| (edge.mv.clone(), edge.visits as f32 / child_visits as f32))
+        .map(|edge| (edge.mv.clone(), edge.visits() as f32 / child_visits as f32))
         .collect()
 }
 
@@ -0,0 +1,107 @@
+use board_game_traits::{Color, Position as PositionTrait};
+use pgn_traits::PgnPosition;
+
+use crate::position::{AnyMove, AnyPosition, Komi, Move, Position, Role, Square, SUPPORTED_SIZES};
+use crate::search::{AnyMctsSetting, AnyTree};
+
+#[test]
+fn start_position_for_supported_sizes_test() {
+    for size in SUPPORTED_SIZES {
+        let position = AnyPosition::start_position(size).unwrap();
+        assert_eq!(position.size(), size);
+        assert_eq!(position.side_to_move(), Color::White);
+        let mut moves = vec![];
+        position.generate_moves(&mut moves);
+        assert_eq!(moves.len(), size * size);
+        assert!(moves.iter().all(|mv| mv.size() == size));
+    }
+    assert!(AnyPosition::start_position(2).is_none());
+    assert!(AnyPosition::start_position(9).is_none());
+    assert!(AnyPosition::from_fen(9, "x9/x9/x9/x9/x9/x9/x9/x9/x9 1 1").is_err());
+}
+
+#[test]
+fn any_move_rejects_unsupported_sizes_test() {
+    let mv = Move::Place(Role::Flat, Square(0));
+    for size in SUPPORTED_SIZES {
+        assert_eq!(
+            AnyMove::new(size, mv.clone()).unwrap().to_string(),
+            format!("a{}", size)
+        );
+    }
+    assert!(AnyMove::new(2, mv.clone()).is_none());
+    assert!(AnyMove::new(9, mv).is_none());
+}
+
+#[test]
+fn any_position_matches_position_test() {
+    let tps = "2,x4/x2,1,x2/x,2,x3/x5/x4,1 1 3";
+    let position = <Position<5>>::from_fen(tps).unwrap();
+    let any_position = AnyPosition::from_fen(5, tps).unwrap();
+    assert_eq!(any_position, AnyPosition::from(position.clone()));
+    assert_eq!(any_position.to_fen(), position.to_fen());
+
+    let mut moves = vec![];
+    position.generate_moves(&mut moves);
+    let mut any_moves = vec![];
+    any_position.generate_moves(&mut any_moves);
+    assert_eq!(
+        any_moves
+            .into_iter()
+            .map(|mv| mv.into_inner())
+            .collect::<Vec<_>>(),
+        moves
+    );
+}
+
+#[test]
+fn do_and_reverse_any_move_test() {
+    let mut position =
+        AnyPosition::start_position_with_komi(6, Komi::from_half_komi(4).unwrap()).unwrap();
+    let original_position = position.clone();
+    assert_eq!(position.komi().half_komi(), 4);
+
+    let mut reverse_moves = vec![];
+    for move_string in ["a1", "f6", "b2", "Ce5", "b2<"] {
+        let mv = position.move_from_san(move_string).unwrap();
+        assert_eq!(position.move_to_san(&mv), move_string);
+        assert_eq!(mv.to_string(), move_string);
+        reverse_moves.push(position.do_move(mv));
+    }
+    assert_eq!(position.half_moves_played(), 5);
+    assert_eq!(position.game_result(), None);
+
+    for reverse_move in reverse_moves.into_iter().rev() {
+        position.reverse_move(reverse_move);
+    }
+    assert_eq!(position, original_position);
+}
+
+#[test]
+#[should_panic]
+fn do_move_for_wrong_size_panics_test() {
+    let position_4s = AnyPosition::start_position(4).unwrap();
+    let mv = position_4s.move_from_san("a1").unwrap();
+    let mut position_5s = AnyPosition::start_position(5).unwrap();
+    position_5s.do_move(mv);
+}
+
+#[test]
+fn any_tree_search_test() {
+    for size in SUPPORTED_SIZES {
+        let position = AnyPosition::start_position(size).unwrap();
+        let settings = AnyMctsSetting::default_for_size(size).unwrap();
+        let mut tree = AnyTree::with_settings(position.clone(), settings);
+        for _ in 0..1000 {
+            tree.select();
+        }
+        assert_eq!(tree.size(), size);
+        assert_eq!(tree.visits(), 1000);
+
+        let (best_move, _score) = tree.best_move();
+        let mut moves = vec![];
+        position.generate_moves(&mut moves);
+        assert!(moves.contains(&best_move));
+        assert_eq!(tree.pv()[0], best_move);
+    }
+}
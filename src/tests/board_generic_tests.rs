@@ -1,48 +1,149 @@
 use crate::evaluation::parameters;
 use crate::evaluation::parameters::PolicyFeatures;
-use board_game_traits::{EvalPosition, GameResult::*, Position as PositionTrait};
+use board_game_traits::{Color, EvalPosition, GameResult::*, Position as PositionTrait};
 use pgn_traits::PgnPosition;
 use rand::seq::SliceRandom;
 
+use crate::position::bitboard::BitBoard;
 use crate::position::Move;
-use crate::position::{squares_iterator, Role, Square};
-use crate::position::{GroupEdgeConnection, Position};
+use crate::position::{squares_iterator, Piece, Role, Square};
+use crate::position::{
+    GroupEdgeConnection, Position, RuleSet, MAX_STACK_HEIGHT, MAX_STARTING_CAPSTONES,
+    MAX_STARTING_STONES,
+};
 use crate::tests::do_moves_and_check_validity;
 
+#[test]
+fn play_random_3s_games_test() {
+    play_random_games_prop::<3>(500, RuleSet::default())
+}
+
 #[test]
 fn play_random_4s_games_test() {
-    play_random_games_prop::<4>(500)
+    play_random_games_prop::<4>(500, RuleSet::default())
 }
 
 #[test]
 fn play_random_5s_games_test() {
-    play_random_games_prop::<5>(500)
+    play_random_games_prop::<5>(500, RuleSet::default())
 }
 
 #[test]
 fn play_random_6s_games_test() {
-    play_random_games_prop::<6>(500)
+    play_random_games_prop::<6>(500, RuleSet::default())
+}
+
+#[test]
+fn play_random_7s_games_test() {
+    play_random_games_prop::<7>(50, RuleSet::default())
+}
+
+#[test]
+fn play_random_8s_games_test() {
+    play_random_games_prop::<8>(50, RuleSet::default())
+}
+
+#[test]
+fn play_random_5s_games_with_custom_rules_test() {
+    let rules = RuleSet::default()
+        .add_starting_stones(12)
+        .unwrap()
+        .add_starting_capstones(3)
+        .unwrap()
+        .without_opening_swap()
+        .without_repetition_draws();
+    play_random_games_prop::<5>(500, rules)
+}
+
+#[test]
+fn large_reserves_test() {
+    let rules = RuleSet::default()
+        .add_starting_stones(MAX_STARTING_STONES)
+        .unwrap()
+        .add_starting_capstones(MAX_STARTING_CAPSTONES)
+        .unwrap();
+    let mut position = <Position<6>>::start_position_with_rules(rules);
+    do_moves_and_check_validity(&mut position, &["a1", "f6", "Cc3", "Cd4", "c3<"]);
+    assert_eq!(position.white_reserves_left(), MAX_STARTING_STONES - 1);
+    assert_eq!(position.black_reserves_left(), MAX_STARTING_STONES - 1);
+    assert_eq!(position.white_caps_left(), MAX_STARTING_CAPSTONES - 1);
+
+    // Reserves that might not fit in a single stack are rejected
+    assert!(RuleSet::<6>::default().add_starting_stones(200).is_none());
+    assert!(RuleSet::<6>::default()
+        .add_starting_capstones(MAX_STARTING_CAPSTONES + 1)
+        .is_none());
+}
+
+#[test]
+fn tall_stack_test() {
+    // Standard 8s reserves are enough for stacks taller than 64 pieces
+    let tall_stack = "12".repeat(40);
+    let empty_row = "x,x,x,x,x,x,x,x/";
+    let tps = format!("{}{},x,x,x,x,x,x,x 2 41", empty_row.repeat(7), tall_stack);
+    let mut position = <Position<8>>::from_fen(&tps).unwrap();
+    assert_eq!(position.to_fen(), tps);
+    do_moves_and_check_validity(&mut position, &["8a1+", "a3"]);
+    assert_eq!(
+        position.to_fen(),
+        format!(
+            "{}1,x,x,x,x,x,x,x/{},x,x,x,x,x,x,x/{},x,x,x,x,x,x,x 2 42",
+            empty_row.repeat(5),
+            &tall_stack[72..],
+            &tall_stack[..72]
+        )
+    );
+
+    // Stacks taller than a stack can hold are a parse error, not a panic
+    let too_tall_stack = "12".repeat(MAX_STACK_HEIGHT as usize / 2 + 1);
+    let tps = format!(
+        "{}{},x,x,x,x,x,x,x 2 66",
+        empty_row.repeat(7),
+        too_tall_stack
+    );
+    let rules = RuleSet::default()
+        .add_starting_stones(MAX_STARTING_STONES)
+        .unwrap();
+    assert!(<Position<8>>::from_fen_with_rules(&tps, rules).is_err());
+}
+
+#[test]
+#[ignore]
+fn play_random_3s_games_test_long() {
+    play_random_games_prop::<3>(10_000, RuleSet::default())
 }
 
 #[test]
 #[ignore]
 fn play_random_4s_games_test_long() {
-    play_random_games_prop::<4>(10_000)
+    play_random_games_prop::<4>(10_000, RuleSet::default())
 }
 
 #[test]
 #[ignore]
 fn play_random_5s_games_test_long() {
-    play_random_games_prop::<5>(10_000)
+    play_random_games_prop::<5>(10_000, RuleSet::default())
 }
 
 #[test]
 #[ignore]
 fn play_random_6s_games_test_long() {
-    play_random_games_prop::<6>(10_000)
+    play_random_games_prop::<6>(10_000, RuleSet::default())
+}
+
+#[test]
+#[ignore]
+fn play_random_7s_games_test_long() {
+    play_random_games_prop::<7>(10_000, RuleSet::default())
+}
+
+#[test]
+#[ignore]
+fn play_random_8s_games_test_long() {
+    play_random_games_prop::<8>(10_000, RuleSet::default())
 }
 
-fn play_random_games_prop<const S: usize>(num_games: usize) {
+fn play_random_games_prop<const S: usize>(num_games: usize, rules: RuleSet<S>) {
     let mut white_wins = 0;
     let mut black_wins = 0;
     let mut draws = 0;
@@ -50,7 +151,7 @@ fn play_random_games_prop<const S: usize>(num_games: usize) {
 
     let mut rng = rand::thread_rng();
     for _ in 0..num_games {
-        let mut position = <Position<S>>::default();
+        let mut position = <Position<S>>::start_position_with_rules(rules);
         let mut moves = vec![];
 
         for i in 0.. {
@@ -64,13 +165,64 @@ fn play_random_games_prop<const S: usize>(num_games: usize) {
             );
             assert_eq!(position, position.flip_colors().flip_colors());
 
-            assert_eq!(Position::from_fen(&position.to_fen()).unwrap(), position);
+            for piece in [
+                Piece::WhiteFlat,
+                Piece::BlackFlat,
+                Piece::WhiteWall,
+                Piece::BlackWall,
+                Piece::WhiteCap,
+                Piece::BlackCap,
+            ] {
+                let top_stones_from_scratch = squares_iterator::<S>()
+                    .filter(|square| position[*square].top_stone() == Some(piece))
+                    .fold(BitBoard::empty(), |bitboard, square| bitboard.set(square.0));
+                assert_eq!(
+                    position.top_stones(piece),
+                    top_stones_from_scratch,
+                    "{:?} bitboard mismatch for board:\n{:?}",
+                    piece,
+                    position
+                );
+            }
+
+            assert_eq!(
+                Position::from_fen_with_rules(&position.to_fen(), rules).unwrap(),
+                position
+            );
 
             let group_data = position.group_data();
 
+            // A square is critical if it's next to, or on, all the edges needed for a road
+            for square in squares_iterator::<S>() {
+                for color in [Color::White, Color::Black] {
+                    let edge_connection = square
+                        .neighbours::<S>()
+                        .filter(|neighbour| {
+                            position[*neighbour].top_stone().map(|piece| piece.color())
+                                == Some(color)
+                        })
+                        .map(|neighbour| {
+                            group_data.amount_in_group[group_data.groups[neighbour] as usize].1
+                        })
+                        .fold(
+                            GroupEdgeConnection::default().connect_square::<S>(square),
+                            |acc, connection| acc | connection,
+                        );
+                    assert_eq!(
+                        edge_connection.is_winning(),
+                        group_data.is_critical_square(square, color),
+                        "Wrong critical square {} for {} on board:\n{:?}",
+                        square.to_string::<S>(),
+                        color,
+                        position
+                    );
+                }
+            }
+
             assert!((group_data.white_road_pieces() & group_data.black_road_pieces()).is_empty());
             assert!(
-                (group_data.white_road_pieces() & group_data.white_blocking_pieces()).count() <= 1
+                (group_data.white_road_pieces() & group_data.white_blocking_pieces()).count()
+                    <= rules.starting_capstones()
             );
 
             let eval = position.static_eval();
@@ -174,6 +326,11 @@ fn play_random_games_prop<const S: usize>(num_games: usize) {
     )
 }
 
+#[test]
+fn go_in_directions_3s_test() {
+    go_in_directions_prop::<3>()
+}
+
 #[test]
 fn go_in_directions_4s_test() {
     go_in_directions_prop::<4>()
@@ -189,6 +346,16 @@ fn go_in_directions_6s_test() {
     go_in_directions_prop::<6>()
 }
 
+#[test]
+fn go_in_directions_7s_test() {
+    go_in_directions_prop::<7>()
+}
+
+#[test]
+fn go_in_directions_8s_test() {
+    go_in_directions_prop::<8>()
+}
+
 fn go_in_directions_prop<const S: usize>() {
     for square in squares_iterator::<S>() {
         assert_eq!(
@@ -206,6 +373,11 @@ fn go_in_directions_prop<const S: usize>() {
     }
 }
 
+#[test]
+fn group_connection_3s_test() {
+    group_connection_generic_prop::<3>()
+}
+
 #[test]
 fn group_connection_4s_test() {
     group_connection_generic_prop::<4>()
@@ -221,6 +393,16 @@ fn group_connection_6s_test() {
     group_connection_generic_prop::<6>()
 }
 
+#[test]
+fn group_connection_7s_test() {
+    group_connection_generic_prop::<7>()
+}
+
+#[test]
+fn group_connection_8s_test() {
+    group_connection_generic_prop::<8>()
+}
+
 fn group_connection_generic_prop<const S: usize>() {
     let group_connection = GroupEdgeConnection::default();
 
@@ -233,6 +415,11 @@ fn group_connection_generic_prop<const S: usize>() {
     assert!(a1_connection.is_connected_west());
 }
 
+#[test]
+fn bitboard_full_board_file_rank_3s_test() {
+    bitboard_full_board_file_rank_prop::<3>()
+}
+
 #[test]
 fn bitboard_full_board_file_rank_4s_test() {
     bitboard_full_board_file_rank_prop::<4>()
@@ -248,6 +435,16 @@ fn bitboard_full_board_file_rank_6s_test() {
     bitboard_full_board_file_rank_prop::<6>()
 }
 
+#[test]
+fn bitboard_full_board_file_rank_7s_test() {
+    bitboard_full_board_file_rank_prop::<7>()
+}
+
+#[test]
+fn bitboard_full_board_file_rank_8s_test() {
+    bitboard_full_board_file_rank_prop::<8>()
+}
+
 fn bitboard_full_board_file_rank_prop<const S: usize>() {
     let mut position = <Position<S>>::start_position();
     let move_strings: Vec<String> = squares_iterator::<S>()
@@ -257,7 +454,7 @@ fn bitboard_full_board_file_rank_prop<const S: usize>() {
         &mut position,
         &(move_strings.iter().map(AsRef::as_ref).collect::<Vec<_>>()),
     );
-    if S % 2 == 0 {
+    if S.is_multiple_of(2) {
         assert_eq!(position.game_result(), Some(BlackWin));
     } else {
         assert_eq!(position.game_result(), Some(WhiteWin));
@@ -283,6 +480,7 @@ fn bitboard_full_board_file_rank_prop<const S: usize>() {
 
 #[test]
 fn square_rank_file_test() {
+    square_rank_file_prop::<3>();
     square_rank_file_prop::<4>();
     square_rank_file_prop::<5>();
     square_rank_file_prop::<6>();
@@ -309,3 +507,20 @@ fn square_rank_file_prop<const S: usize>() {
         }
     }
 }
+
+#[test]
+fn null_move_hash_test() {
+    let mut position = <Position<5>>::start_position();
+    do_moves_and_check_validity(&mut position, &["a1", "e5", "c3"]);
+    let hash = position.zobrist_hash();
+
+    position.null_move();
+    assert_ne!(hash, position.zobrist_hash());
+    assert_eq!(
+        position.zobrist_hash_from_scratch(),
+        position.zobrist_hash()
+    );
+
+    position.null_move();
+    assert_eq!(hash, position.zobrist_hash());
+}
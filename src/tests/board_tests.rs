@@ -2,11 +2,12 @@ use board_game_traits::{Color, Position as PositionTrait};
 use board_game_traits::{GameResult, GameResult::*};
 use pgn_traits::PgnPosition;
 
+use crate::position::bitboard::BitBoard;
 use crate::position::Direction::*;
 use crate::position::Move;
 use crate::position::Piece::{BlackCap, BlackFlat, WhiteFlat, WhiteWall};
-use crate::position::Position;
 use crate::position::{squares_iterator, Piece, Role, Square, Stack};
+use crate::position::{FlatCount, GameEndReason, GameOutcome, Komi, Position, RuleSet};
 use crate::tests::do_moves_and_check_validity;
 use crate::{position as board_mod, search};
 
@@ -24,13 +25,21 @@ fn default_board_test() {
 
 #[test]
 fn get_set_test() {
-    let pieces = vec![WhiteFlat, BlackFlat, BlackFlat, WhiteWall];
+    let pieces = [WhiteFlat, BlackFlat, BlackFlat, WhiteWall];
     let mut position = <Position<5>>::default();
     for &piece in pieces.iter() {
-        position[Square(12)].push(piece);
+        let mut stack = position[Square(12)];
+        stack.push(piece);
+        position.set_stack(Square(12), stack);
     }
     assert_eq!(position[Square(12)].len(), 4);
     assert_eq!(position[Square(12)].top_stone(), Some(WhiteWall));
+    assert_eq!(position.top_stones(WhiteWall), BitBoard::empty().set(12));
+    assert!(position.top_stones(BlackFlat).is_empty());
+    assert_eq!(
+        position.zobrist_hash(),
+        position.zobrist_hash_from_scratch()
+    );
 
     for (i, &piece) in pieces.iter().enumerate() {
         assert_eq!(
@@ -42,23 +51,30 @@ fn get_set_test() {
     }
 
     for &piece in pieces.iter().rev() {
-        assert_eq!(Some(piece), position[Square(12)].pop(), "{:?}", position);
+        let mut stack = position[Square(12)];
+        assert_eq!(Some(piece), stack.pop(), "{:?}", position);
+        position.set_stack(Square(12), stack);
     }
 
     assert!(position[Square(12)].is_empty());
+    assert_eq!(position, <Position<5>>::default());
+    assert_eq!(
+        position.zobrist_hash(),
+        <Position<5>>::default().zobrist_hash()
+    );
 
     for &piece in pieces.iter() {
-        position[Square(12)].push(piece);
+        let mut stack = position[Square(12)];
+        stack.push(piece);
+        position.set_stack(Square(12), stack);
     }
 
     for &piece in pieces.iter() {
-        assert_eq!(
-            piece,
-            position[Square(12)].remove(0),
-            "{:?}",
-            position[Square(12)]
-        );
+        let mut stack = position[Square(12)];
+        assert_eq!(piece, stack.remove(0), "{:?}", stack);
+        position.set_stack(Square(12), stack);
     }
+    assert!(position.top_stones(WhiteWall).is_empty());
 }
 
 #[test]
@@ -314,6 +330,41 @@ fn games_ends_when_board_is_full_test() {
     );
 }
 
+#[test]
+fn komi_decides_flat_win_test() {
+    let move_strings: Vec<String> = squares_iterator::<5>()
+        .map(|sq| sq.to_string::<5>())
+        .collect();
+
+    for (half_komi, game_result) in [(0, WhiteWin), (1, WhiteWin), (2, Draw), (3, BlackWin)] {
+        let komi = Komi::from_half_komi(half_komi).unwrap();
+        let mut position = <Position<5>>::start_position_with_komi(komi);
+        do_moves_and_check_validity(
+            &mut position,
+            &(move_strings.iter().map(AsRef::as_ref).collect::<Vec<_>>()),
+        );
+        assert_eq!(
+            position.game_result(),
+            Some(game_result),
+            "Wrong result with komi {}:\n{:?}",
+            komi,
+            position
+        );
+    }
+}
+
+#[test]
+fn parse_komi_test() {
+    for (komi_string, half_komi) in [("0", 0), ("2", 4), ("2.5", 5), ("-0.5", -1), ("1.0", 2)] {
+        let komi: Komi = komi_string.parse().unwrap();
+        assert_eq!(komi.half_komi(), half_komi);
+        assert_eq!(komi.to_string().parse::<Komi>().unwrap(), komi);
+    }
+    for komi_string in ["", "2.4", "a", "11", "0.25"] {
+        assert!(komi_string.parse::<Komi>().is_err());
+    }
+}
+
 #[test]
 fn every_move_is_suicide_test() {
     let mut position = <Position<5>>::start_position();
@@ -352,14 +403,12 @@ fn critical_square_test() {
     assert_eq!(
         group_data
             .critical_squares(Color::White)
-            .into_iter()
             .collect::<Vec<_>>(),
         vec![e1]
     );
     assert_eq!(
         group_data
             .critical_squares(Color::Black)
-            .into_iter()
             .collect::<Vec<_>>(),
         vec![a5]
     );
@@ -397,6 +446,87 @@ fn repetitions_are_draws_test() {
     assert_eq!(position.game_result(), None);
 }
 
+#[test]
+fn repetition_outcome_test() {
+    let mut position = <Position<5>>::start_position();
+    do_moves_and_check_validity(&mut position, &["a1", "e5"]);
+    for _ in 0..2 {
+        do_moves_and_check_validity(&mut position, &["e5-", "a1+", "e4+", "a2-"]);
+    }
+    assert_eq!(
+        position.game_outcome(),
+        Some(GameOutcome {
+            result: Draw,
+            reason: GameEndReason::Repetition
+        })
+    );
+}
+
+#[test]
+fn road_outcome_test() {
+    let mut position = <Position<5>>::from_fen("2,2,x3/1,1,1,1,x/x5/x5/x4,1 1 5").unwrap();
+    assert_eq!(position.game_outcome(), None);
+    do_moves_and_check_validity(&mut position, &["e4"]);
+
+    let outcome = position.game_outcome().unwrap();
+    assert_eq!(outcome.result, WhiteWin);
+    assert_eq!(outcome.ptn_result_string(), "R-0");
+    match outcome.reason {
+        GameEndReason::Road(squares) => assert_eq!(
+            squares
+                .iter()
+                .map(|square| square.to_string::<5>())
+                .collect::<Vec<String>>(),
+            vec!["a4", "b4", "c4", "d4", "e4"]
+        ),
+        reason => panic!("Expected road win, got {:?}", reason),
+    }
+}
+
+#[test]
+fn flat_outcome_test() {
+    let position =
+        <Position<5>>::from_fen("2,2,1,1,2/1,1,2,2,1/2,1,2,1,2/1S,2S,1S,2S,1C/1,2,1,2,2C 1 20")
+            .unwrap();
+    let outcome = position.game_outcome().unwrap();
+    assert_eq!(outcome.result, BlackWin);
+    assert_eq!(outcome.ptn_result_string(), "0-F");
+    assert_eq!(
+        outcome.reason,
+        GameEndReason::BoardFill(FlatCount {
+            white_flats: 9,
+            black_flats: 10,
+            komi: Komi::default()
+        })
+    );
+
+    let komi = Komi::from_half_komi(-3).unwrap();
+    let mut position = <Position<5>>::start_position_with_rules(
+        RuleSet::default()
+            .add_starting_stones(2)
+            .unwrap()
+            .add_starting_capstones(0)
+            .unwrap()
+            .add_komi(komi),
+    );
+    do_moves_and_check_validity(&mut position, &["a1", "e5", "b1", "d5"]);
+    assert_eq!(
+        position.game_outcome().unwrap().to_string(),
+        "White wins on flats, 2 to 2 with komi -1.5, after a player ran out of stones"
+    );
+    assert_eq!(
+        position.game_outcome(),
+        Some(GameOutcome {
+            result: WhiteWin,
+            reason: GameEndReason::ReservesDepleted(FlatCount {
+                white_flats: 2,
+                black_flats: 2,
+                komi
+            })
+        })
+    );
+}
+
 #[test]
 fn fake_repetitions_are_not_draws_test() {
     let mut position = <Position<6>>::start_position();
@@ -411,6 +541,54 @@ fn fake_repetitions_are_not_draws_test() {
     assert_eq!(position.game_result(), None);
 }
 
+#[test]
+fn repetitions_are_not_draws_without_repetition_rule_test() {
+    let mut position =
+        <Position<5>>::start_position_with_rules(RuleSet::default().without_repetition_draws());
+    do_moves_and_check_validity(&mut position, &["a1", "e5"]);
+    for _ in 0..3 {
+        do_moves_and_check_validity(&mut position, &["e5-", "a1+", "e4+", "a2-"]);
+    }
+    assert_eq!(position.game_result(), None);
+}
+
+#[test]
+fn first_move_places_own_stone_without_opening_swap_test() {
+    let mut position =
+        <Position<5>>::start_position_with_rules(RuleSet::default().without_opening_swap());
+    let mut moves = vec![];
+    position.generate_moves(&mut moves);
+    assert!(moves.contains(&position.move_from_san("Ca1").unwrap()));
+
+    do_moves_and_check_validity(&mut position, &["a1", "Se5"]);
+    assert_eq!(position[Square(20)].top_stone(), Some(WhiteFlat));
+    assert_eq!(
+        position[Square(4)].top_stone(),
+        Some(Piece::from_role_color(Role::Wall, Color::Black))
+    );
+    assert_eq!(
+        position.to_fen(),
+        "x,x,x,x,2S/x,x,x,x,x/x,x,x,x,x/x,x,x,x,x/1,x,x,x,x 1 2"
+    );
+}
+
+#[test]
+fn custom_reserves_test() {
+    let rules = RuleSet::default()
+        .add_starting_stones(3)
+        .unwrap()
+        .add_starting_capstones(0)
+        .unwrap();
+    let mut position = <Position<5>>::start_position_with_rules(rules);
+    do_moves_and_check_validity(&mut position, &["a1", "e5", "b1", "d5"]);
+    assert_eq!(position.game_result(), None);
+    do_moves_and_check_validity(&mut position, &["c1"]);
+    // White has run out of stones, and wins on flats
+    assert_eq!(position.game_result(), Some(WhiteWin));
+
+    assert!(<Position<5>>::from_fen_with_rules("1,1,1,1,x/x5/x5/x5/x5 1 3", rules).is_err());
+}
+
 #[test]
 fn parse_tps_test() {
     let tps_string = "x4,1/x5/x5/x5/2,x4 1 2";
@@ -0,0 +1,170 @@
+use std::io;
+
+use board_game_traits::{GameResult, Position as PositionTrait};
+use pgn_traits::PgnPosition;
+use rand::seq::SliceRandom;
+
+use crate::dataset::{
+    read_move, read_position, write_move, write_position, DatasetEntry, DatasetReader,
+    DatasetWriter,
+};
+use crate::position::{Komi, Position, RuleSet};
+
+#[test]
+fn position_and_move_round_trip_3s_test() {
+    position_and_move_round_trip_prop::<3>(RuleSet::default())
+}
+
+#[test]
+fn position_and_move_round_trip_5s_test() {
+    position_and_move_round_trip_prop::<5>(RuleSet::default())
+}
+
+#[test]
+fn position_and_move_round_trip_8s_test() {
+    position_and_move_round_trip_prop::<8>(RuleSet::default())
+}
+
+#[test]
+fn position_and_move_round_trip_custom_rules_test() {
+    position_and_move_round_trip_prop::<6>(
+        RuleSet::default()
+            .add_komi(Komi::from_half_komi(-5).unwrap())
+            .without_opening_swap()
+            .without_repetition_draws(),
+    )
+}
+
+fn position_and_move_round_trip_prop<const S: usize>(rules: RuleSet<S>) {
+    let mut rng = rand::thread_rng();
+    let mut moves = vec![];
+    for _ in 0..20 {
+        let mut position = <Position<S>>::start_position_with_rules(rules);
+        for _ in 0..200 {
+            let mut bytes = vec![];
+            write_position(&mut bytes, &position).unwrap();
+            let mut reader = bytes.as_slice();
+            let decoded = read_position::<_, S>(&mut reader, rules).unwrap();
+            assert!(reader.is_empty());
+            assert_eq!(decoded, position, "TPS {}", position.to_fen());
+            assert_eq!(decoded.zobrist_hash(), position.zobrist_hash());
+
+            if position.game_result().is_some() {
+                break;
+            }
+            moves.clear();
+            position.generate_moves(&mut moves);
+            for mv in moves.iter() {
+                let mut bytes = vec![];
+                write_move::<_, S>(&mut bytes, mv).unwrap();
+                assert_eq!(bytes.len(), 2);
+                assert_eq!(read_move::<_, S>(&mut bytes.as_slice()).unwrap(), *mv);
+            }
+            position.do_move(moves.choose(&mut rng).unwrap().clone());
+        }
+    }
+}
+
+#[test]
+fn tall_stack_round_trip_test() {
+    let tps = "x5/x5/x5/x5/2121212121212121212121212121211212121212121C,x4 1 40";
+    let position = <Position<5>>::from_fen(tps).unwrap();
+    let mut bytes = vec![];
+    write_position(&mut bytes, &position).unwrap();
+    let decoded = read_position::<_, 5>(&mut bytes.as_slice(), RuleSet::default()).unwrap();
+    assert_eq!(decoded, position);
+}
+
+fn sample_entries() -> Vec<DatasetEntry<6>> {
+    let mut position = <Position<6>>::start_position();
+    let mut entries = vec![];
+    for move_string in ["a1", "f6", "c3", "d4", "c3>", "Cd2"] {
+        let mut moves = vec![];
+        position.generate_moves(&mut moves);
+        let score = 1.0 / moves.len() as f32;
+        entries.push(DatasetEntry {
+            position: position.clone(),
+            move_scores: moves.into_iter().map(|mv| (mv, score)).collect(),
+            game_result: Some(GameResult::BlackWin),
+        });
+        position.do_move(position.move_from_san(move_string).unwrap());
+    }
+    entries.push(DatasetEntry {
+        position,
+        move_scores: vec![],
+        game_result: None,
+    });
+    entries
+}
+
+#[test]
+fn dataset_round_trip_test() {
+    let entries = sample_entries();
+    let mut writer = DatasetWriter::new(vec![], RuleSet::default()).unwrap();
+    for entry in entries.iter() {
+        writer.write_entry(entry).unwrap();
+    }
+    let bytes = writer.into_inner();
+
+    let reader = DatasetReader::<_, 6>::new(bytes.as_slice()).unwrap();
+    assert_eq!(*reader.rules(), RuleSet::default());
+    let read_entries: Vec<DatasetEntry<6>> = reader.collect::<io::Result<_>>().unwrap();
+    assert_eq!(read_entries, entries);
+}
+
+#[test]
+fn dataset_errors_test() {
+    let mut writer = DatasetWriter::new(vec![], RuleSet::default()).unwrap();
+    for entry in sample_entries().iter() {
+        writer.write_entry(entry).unwrap();
+    }
+    let bytes = writer.into_inner();
+
+    assert!(DatasetReader::<_, 5>::new(bytes.as_slice()).is_err());
+    assert!(DatasetReader::<_, 6>::new(&bytes[1..]).is_err());
+    let mut wrong_version = bytes.clone();
+    wrong_version[4] += 1;
+    assert!(DatasetReader::<_, 6>::new(wrong_version.as_slice()).is_err());
+
+    // A truncated entry is an error, not the end of the dataset
+    let reader = DatasetReader::<_, 6>::new(&bytes[..bytes.len() - 1]).unwrap();
+    let read_entries: Vec<io::Result<DatasetEntry<6>>> = reader.collect();
+    assert_eq!(read_entries.len(), 7);
+    assert!(read_entries[6].is_err());
+
+    let komi_position = <Position<6>>::start_position_with_komi(Komi::from_half_komi(4).unwrap());
+    let mut writer = DatasetWriter::new(vec![], RuleSet::default()).unwrap();
+    assert!(writer
+        .write_entry(&DatasetEntry {
+            position: komi_position,
+            move_scores: vec![],
+            game_result: None,
+        })
+        .is_err());
+}
+
+#[cfg(feature = "constant-tuning")]
+#[test]
+fn dataset_from_games_with_mixed_rules_test() {
+    use crate::ptn::Game;
+    use crate::tune::training::write_dataset_from_games;
+
+    let games: Vec<Game<Position<6>>> = vec![
+        RuleSet::default(),
+        RuleSet::default().add_komi(Komi::from_half_komi(4).unwrap()),
+    ]
+    .into_iter()
+    .map(|rules| Game {
+        start_position: Position::start_position_with_rules(rules),
+        moves: vec![],
+        game_result: None,
+        tags: vec![],
+    })
+    .collect();
+
+    let file_name = std::env::temp_dir().join("tiltak_mixed_rules_test.bin");
+    assert!(
+        write_dataset_from_games(file_name.to_str().unwrap(), &games, &[vec![], vec![]]).is_err()
+    );
+    assert!(!file_name.exists());
+}
@@ -0,0 +1,142 @@
+use board_game_traits::{GameResult, Position as PositionTrait};
+use pgn_traits::PgnPosition;
+
+use crate::endgame::{is_flat_race, solve_flat_race};
+use crate::position::{Komi, Move, Position, Role, RuleSet};
+use crate::search::{MctsSetting, MonteCarloTree, ProvenResult};
+
+fn flat_race_rules() -> RuleSet<4> {
+    RuleSet::default()
+        .add_starting_stones(5)
+        .unwrap()
+        .add_starting_capstones(0)
+        .unwrap()
+}
+
+#[test]
+fn side_to_move_places_last_stone_test() {
+    let position = <Position<4>>::from_fen_with_rules(
+        "1,x,2,x/x,1,x,2/1,x,2,x/x,1,x,x 1 5",
+        flat_race_rules(),
+    )
+    .unwrap();
+    assert!(is_flat_race(&position));
+    assert_eq!(
+        solve_flat_race(&position, 1_000_000),
+        Some(GameResult::WhiteWin)
+    );
+}
+
+#[test]
+fn opponent_places_last_stone_test() {
+    let position = <Position<4>>::from_fen_with_rules(
+        "1,x,2,x/x,1,x,2/1,x,2,x/x,1,x,x 2 5",
+        flat_race_rules(),
+    )
+    .unwrap();
+    assert_eq!(
+        solve_flat_race(&position, 1_000_000),
+        Some(GameResult::WhiteWin)
+    );
+    // Refuting every move of the side to move takes more than a few nodes
+    assert_eq!(solve_flat_race(&position, 10), None);
+}
+
+#[test]
+fn flat_race_with_komi_test() {
+    let tps = "1,x,2,x/x,1,x,2/1,x,2,x/x,1,x,x 1 5";
+    let position = <Position<4>>::from_fen_with_rules(
+        tps,
+        flat_race_rules().add_komi(Komi::from_half_komi(3).unwrap()),
+    )
+    .unwrap();
+    assert_eq!(
+        solve_flat_race(&position, 1_000_000),
+        Some(GameResult::WhiteWin)
+    );
+
+    // White can only reach a draw, which black can avoid proving by delaying with stack moves
+    let position = <Position<4>>::from_fen_with_rules(
+        tps,
+        flat_race_rules().add_komi(Komi::from_half_komi(4).unwrap()),
+    )
+    .unwrap();
+    assert_ne!(
+        solve_flat_race(&position, 1_000_000),
+        Some(GameResult::WhiteWin)
+    );
+}
+
+#[test]
+fn not_flat_race_test() {
+    let position = <Position<5>>::start_position();
+    assert!(!is_flat_race(&position));
+    assert_eq!(solve_flat_race(&position, 1_000_000), None);
+}
+
+#[test]
+fn mcts_uses_endgame_solver_test() {
+    let position = <Position<4>>::from_fen_with_rules(
+        "1,x,2,x/x,1,x,2/1,x,2,x/x,1,x,x 1 5",
+        flat_race_rules(),
+    )
+    .unwrap();
+    let mut tree =
+        MonteCarloTree::with_settings(position, MctsSetting::default().add_endgame_solver(10_000));
+    for _ in 0..1000 {
+        tree.select();
+    }
+    let (best_move, score) = tree.best_move();
+    assert!(matches!(best_move, Move::Place(Role::Flat, _)));
+    assert!(score > 0.99, "Score was {}", score);
+    // Placing the last stone ends the game, which is an exact result
+    assert_eq!(
+        tree.proven_result(),
+        Some(ProvenResult::Win {
+            plies: 1,
+            exact: true
+        })
+    );
+
+    // Every black move leaves a flat race that only the solver proves
+    let position = <Position<4>>::from_fen_with_rules(
+        "1,x,2,x/x,1,x,2/1,x,2,x/x,1,x,x 2 5",
+        flat_race_rules(),
+    )
+    .unwrap();
+    let mut tree =
+        MonteCarloTree::with_settings(position, MctsSetting::default().add_endgame_solver(10_000));
+    for _ in 0..1000 {
+        tree.select();
+    }
+    // The solver only gives an upper bound for the length of the game
+    assert!(
+        matches!(
+            tree.proven_result(),
+            Some(ProvenResult::Loss { exact: false, .. })
+        ),
+        "Proven result was {:?}",
+        tree.proven_result()
+    );
+}
+
+#[test]
+fn mcts_with_endgame_solver_proves_exact_road_win_test() {
+    let mut position = <Position<5>>::default();
+    for mv in ["b4", "c2", "d2", "c4", "b2", "d4", "e2", "c3"] {
+        position.do_move(position.move_from_san(mv).unwrap());
+    }
+    let mut tree = MonteCarloTree::with_settings(
+        position.clone(),
+        MctsSetting::default().add_endgame_solver(10_000),
+    );
+    tree.search(10_000);
+    // Finished games are exact results, not bounds from the solver
+    assert_eq!(
+        tree.proven_result(),
+        Some(ProvenResult::Win {
+            plies: 1,
+            exact: true
+        })
+    );
+}
@@ -0,0 +1,217 @@
+use board_game_traits::Position as PositionTrait;
+use pgn_traits::PgnPosition;
+use rand::seq::SliceRandom;
+
+use crate::position::{
+    squares_iterator, Direction, IllegalMoveError, Move, Movement, Position, Role, RuleSet,
+    StackMovement,
+};
+use crate::ptn::ptn_parser;
+
+#[test]
+fn is_legal_agrees_with_move_gen_3s_test() {
+    is_legal_agrees_with_move_gen_prop::<3>(RuleSet::default())
+}
+
+#[test]
+fn is_legal_agrees_with_move_gen_4s_test() {
+    is_legal_agrees_with_move_gen_prop::<4>(RuleSet::default())
+}
+
+#[test]
+fn is_legal_agrees_with_move_gen_5s_test() {
+    is_legal_agrees_with_move_gen_prop::<5>(RuleSet::default())
+}
+
+#[test]
+fn is_legal_agrees_with_move_gen_6s_test() {
+    is_legal_agrees_with_move_gen_prop::<6>(RuleSet::default())
+}
+
+#[test]
+fn is_legal_agrees_with_move_gen_custom_rules_test() {
+    is_legal_agrees_with_move_gen_prop::<4>(
+        RuleSet::default()
+            .add_starting_stones(6)
+            .unwrap()
+            .add_starting_capstones(2)
+            .unwrap()
+            .without_opening_swap(),
+    )
+}
+
+/// Every possible placement, and every spread where each step carries fewer pieces than the last
+fn candidate_moves<const S: usize>() -> Vec<Move> {
+    let mut moves = vec![];
+    for square in squares_iterator::<S>() {
+        for role in [Role::Flat, Role::Wall, Role::Cap] {
+            moves.push(Move::Place(role, square));
+        }
+        for direction in [
+            Direction::North,
+            Direction::West,
+            Direction::East,
+            Direction::South,
+        ] {
+            // Each subset of 1..=S+1 is a strictly decreasing sequence of pieces carried
+            for subset in 1..(1_u32 << (S + 1)) {
+                let mut stack_movement = StackMovement::new();
+                for pieces_to_take in (1..=S as u8 + 1).rev() {
+                    if subset & (1 << (pieces_to_take - 1)) != 0 && stack_movement.len() < S {
+                        stack_movement.push(Movement { pieces_to_take });
+                    }
+                }
+                moves.push(Move::Move(square, direction, stack_movement));
+            }
+        }
+    }
+    moves
+}
+
+fn is_legal_agrees_with_move_gen_prop<const S: usize>(rules: RuleSet<S>) {
+    let candidates = candidate_moves::<S>();
+    let mut rng = rand::thread_rng();
+    let mut moves = vec![];
+    for _ in 0..10 {
+        let mut position = <Position<S>>::start_position_with_rules(rules);
+        for _ in 0..100 {
+            moves.clear();
+            position.generate_moves(&mut moves);
+            for mv in moves.iter() {
+                assert!(
+                    position.is_legal(mv),
+                    "Generated move {} was not legal: {}\n{:?}",
+                    position.move_to_san(mv),
+                    position.clone().try_do_move(mv.clone()).unwrap_err(),
+                    position
+                );
+            }
+            for mv in candidates.iter() {
+                assert_eq!(
+                    position.is_legal(mv),
+                    moves.contains(mv),
+                    "Move {} was legal: {}, generated: {}\n{:?}",
+                    position.move_to_san(mv),
+                    position.is_legal(mv),
+                    moves.contains(mv),
+                    position
+                );
+            }
+            if position.game_result().is_some() {
+                break;
+            }
+            position.do_move(moves.choose(&mut rng).unwrap().clone());
+        }
+    }
+}
+
+fn illegal_move_error<const S: usize>(tps: &str, move_string: &str) -> IllegalMoveError {
+    let mut position = <Position<S>>::from_fen(tps).unwrap();
+    let mv = position.move_from_san(move_string).unwrap();
+    let old_position = position.clone();
+    let error = position.try_do_move(mv).unwrap_err();
+    assert_eq!(position, old_position);
+    error
+}
+
+#[test]
+fn illegal_placement_test() {
+    assert_eq!(
+        illegal_move_error::<5>("x5/x5/x5/x5/x5 1 1", "Ca1"),
+        IllegalMoveError::OpeningMoveNotFlat
+    );
+    assert_eq!(
+        illegal_move_error::<5>("x5/x5/x5/x5/2,x4 2 1", "Sa2"),
+        IllegalMoveError::OpeningMoveNotFlat
+    );
+    assert_eq!(
+        illegal_move_error::<5>("x5/x5/x5/x5/2,1,x3 1 2", "a1"),
+        IllegalMoveError::OccupiedSquare
+    );
+    assert_eq!(
+        illegal_move_error::<5>("x5/x5/x5/x5/2,1C,x3 1 2", "Cc1"),
+        IllegalMoveError::NoCapstonesLeft
+    );
+}
+
+#[test]
+fn illegal_stack_movement_test() {
+    let tps = "x5/x5/x,1S,x3/x,2C,x3/2S,1,x,21,2 1 5";
+    assert_eq!(
+        illegal_move_error::<5>(tps, "c1>"),
+        IllegalMoveError::EmptySquare
+    );
+    assert_eq!(
+        illegal_move_error::<5>(tps, "b2+"),
+        IllegalMoveError::OpponentsStack
+    );
+    assert_eq!(
+        illegal_move_error::<5>(tps, "3d1>"),
+        IllegalMoveError::NotEnoughPieces {
+            taken: 3,
+            stack_height: 2
+        }
+    );
+    assert_eq!(
+        illegal_move_error::<5>(tps, "b1<"),
+        IllegalMoveError::BlockedByWall
+    );
+    assert_eq!(
+        illegal_move_error::<5>(tps, "b1+"),
+        IllegalMoveError::BlockedByCapstone
+    );
+    assert_eq!(
+        illegal_move_error::<5>(tps, "b1-"),
+        IllegalMoveError::SpreadOffBoard
+    );
+    let position = <Position<5>>::from_fen(tps).unwrap();
+    assert!(position.is_legal(&position.move_from_san("b3+").unwrap()));
+    assert!(position.is_legal(&position.move_from_san("2d1>").unwrap()));
+}
+
+#[test]
+fn carry_limit_test() {
+    let tps = "x5/x5/x5/x5/2222221,x4 1 12";
+    assert_eq!(
+        illegal_move_error::<5>(tps, "6a1>"),
+        IllegalMoveError::CarryLimitExceeded { taken: 6, limit: 5 }
+    );
+    assert_eq!(
+        illegal_move_error::<5>(tps, "5a1>11111"),
+        IllegalMoveError::SpreadOffBoard
+    );
+    assert_eq!(
+        illegal_move_error::<5>(tps, "5a1>0113"),
+        IllegalMoveError::MustDropPiece
+    );
+    let mut position = <Position<5>>::from_fen(tps).unwrap();
+    let mv = position.move_from_san("5a1>1112").unwrap();
+    assert!(position.try_do_move(mv).is_ok());
+}
+
+#[test]
+fn capstone_flattens_wall_test() {
+    let tps = "x5/x5/x5/x5/21C,2S,x,2S,x 1 6";
+    let position = <Position<5>>::from_fen(tps).unwrap();
+    assert!(position.is_legal(&position.move_from_san("a1>").unwrap()));
+    assert_eq!(
+        illegal_move_error::<5>(tps, "2a1>"),
+        IllegalMoveError::BlockedByWall
+    );
+
+    let tps = "x5/x5/x5/x5/221C,x,2S,x2 1 6";
+    let position = <Position<5>>::from_fen(tps).unwrap();
+    assert!(position.is_legal(&position.move_from_san("2a1>11").unwrap()));
+    assert!(position.is_legal(&position.move_from_san("3a1>21").unwrap()));
+    assert_eq!(
+        illegal_move_error::<5>(tps, "3a1>12"),
+        IllegalMoveError::BlockedByWall
+    );
+}
+
+#[test]
+fn parse_illegal_move_in_ptn_test() {
+    let ptn = "[Size \"5\"]\n\n1. a1 e5 2. Ca1 *";
+    let error = ptn_parser::parse_ptn::<Position<5>>(ptn).unwrap_err();
+    assert!(error.to_string().contains("Ca1"), "{}", error);
+}
@@ -1,6 +1,6 @@
 use crate::position::Position;
 use crate::search;
-use crate::search::MctsSetting;
+use crate::search::{MctsSetting, ProvenResult};
 use crate::tests::do_moves_and_check_validity;
 use board_game_traits::Position as PositionTrait;
 use pgn_traits::PgnPosition;
@@ -14,6 +14,219 @@ fn play_on_low_time() {
     search::play_move_time(position, time, MctsSetting::default());
 }
 
+#[test]
+fn search_3s_7s_and_8s_test() {
+    search::mcts(<Position<3>>::default(), 1000);
+    search::mcts(<Position<7>>::default(), 1000);
+    search::mcts(<Position<8>>::default(), 1000);
+}
+
+#[test]
+fn multithreaded_search_test() {
+    let mut tree = search::MonteCarloTree::with_settings(
+        <Position<5>>::default(),
+        MctsSetting::default().add_threads(4),
+    );
+    tree.search(20_000);
+    assert_eq!(tree.visits(), 20_000);
+    let search_result = tree.search_result();
+    assert_eq!(
+        search_result
+            .move_visits
+            .iter()
+            .map(|(_, visits)| visits)
+            .sum::<u64>(),
+        20_000 - 1
+    );
+    assert!(search_result.score > 0.0 && search_result.score < 1.0);
+}
+
+#[test]
+fn multithreaded_win_in_two_moves_test() {
+    let mut position = <Position<5>>::default();
+    do_moves_and_check_validity(
+        &mut position,
+        &["e5", "c3", "c2", "d5", "c1", "c5", "d3", "a4", "e3"],
+    );
+    let mut tree = search::MonteCarloTree::with_settings(
+        position.clone(),
+        MctsSetting::default().add_threads(4),
+    );
+    tree.search(25_000);
+    let (best_move, _score) = tree.best_move();
+    assert!(["b4", "b5", "Cb4", "Cb5"]
+        .iter()
+        .any(|mv| best_move == position.move_from_san(mv).unwrap()));
+}
+
+#[test]
+fn multithreaded_search_for_time_test() {
+    let position = <Position<5>>::default();
+    let memory_limit = 1 << 20;
+    let mut tree = search::MonteCarloTree::with_settings(
+        position.clone(),
+        MctsSetting::default()
+            .add_threads(4)
+            .add_memory_limit(memory_limit),
+    );
+    let (best_move, _) = tree.search_for_time(Duration::from_millis(500));
+    assert!(position.is_legal(&best_move));
+    assert!(tree.mem_usage() <= memory_limit);
+}
+
+#[test]
+fn advance_tree_keeps_subtree_test() {
+    let position = <Position<5>>::default();
+    let mut tree = search::MonteCarloTree::new(position.clone());
+    tree.search(10_000);
+    let pv: Vec<_> = tree.pv().take(2).collect();
+    let (_, first_move_visits, _) = tree
+        .move_visits_and_scores()
+        .into_iter()
+        .find(|(mv, _, _)| *mv == pv[0])
+        .unwrap();
+
+    tree.advance(&pv[..1]);
+    assert_eq!(tree.visits(), first_move_visits);
+    let mut expected_position = position.clone();
+    expected_position.do_move(pv[0].clone());
+    assert_eq!(*tree.position(), expected_position);
+
+    tree.advance(&pv[1..]);
+    expected_position.do_move(pv[1].clone());
+    assert_eq!(*tree.position(), expected_position);
+    let visits = tree.visits();
+    assert!(visits > 0);
+    tree.search(1000);
+    assert_eq!(tree.visits(), visits + 1000);
+}
+
+#[test]
+fn advance_tree_by_unsearched_move_test() {
+    let mut position = <Position<5>>::default();
+    let mut tree = search::MonteCarloTree::with_settings(
+        position.clone(),
+        MctsSetting::default().add_dirichlet(0.25),
+    );
+    let mv = position.move_from_san("a3").unwrap();
+    position.do_move(mv.clone());
+    tree.advance(&[mv]);
+    assert_eq!(*tree.position(), position);
+    tree.search(1000);
+    assert!(position.is_legal(&tree.best_move().0));
+}
+
+#[test]
+fn ponder_in_background_test() {
+    let mut position = <Position<5>>::default();
+    let tree = search::MonteCarloTree::with_settings(
+        position.clone(),
+        MctsSetting::default().add_threads(2),
+    );
+    let background_search = tree.search_in_background();
+    std::thread::sleep(Duration::from_millis(100));
+    let mut tree = background_search.stop();
+    let visits = tree.visits();
+    assert!(visits > 100);
+
+    let (best_move, _) = tree.best_move();
+    position.do_move(best_move.clone());
+    tree.advance(&[best_move]);
+    assert!(tree.visits() > 0);
+    assert_eq!(*tree.position(), position);
+
+    // Dropping the search also stops it
+    drop(tree.search_in_background());
+}
+
+#[test]
+fn proven_win_in_one_test() {
+    let mut position = <Position<5>>::default();
+    do_moves_and_check_validity(
+        &mut position,
+        &["b4", "c2", "d2", "c4", "b2", "d4", "e2", "c3"],
+    );
+    let mut tree = search::MonteCarloTree::new(position.clone());
+    tree.search(10_000);
+    assert_eq!(
+        tree.proven_result(),
+        Some(ProvenResult::Win {
+            plies: 1,
+            exact: true
+        })
+    );
+    // The search stops once the root is proven
+    assert!(tree.visits() < 10_000);
+
+    let (best_move, score) = tree.best_move();
+    assert!(["a2", "Ca2"]
+        .iter()
+        .any(|mv| best_move == position.move_from_san(mv).unwrap()));
+    assert_eq!(score, 1.0);
+}
+
+#[test]
+fn proven_win_in_two_moves_test() {
+    let mut position = <Position<5>>::default();
+    do_moves_and_check_validity(
+        &mut position,
+        &["e5", "c3", "c2", "d5", "c1", "c5", "d3", "a4", "e3"],
+    );
+    let mut tree = search::MonteCarloTree::new(position.clone());
+    tree.search(100_000);
+    assert_eq!(
+        tree.proven_result(),
+        Some(ProvenResult::Win {
+            plies: 3,
+            exact: true
+        })
+    );
+    assert_eq!(tree.pv().count(), 3);
+}
+
+#[test]
+fn proven_loss_test() {
+    // White threatens roads on both a5 and e1
+    let position = <Position<5>>::from_fen("x5/1,2,2,x2/1,2,2,x2/1,2,2,2,x/1,1,1,1,x 2 8").unwrap();
+    let mut tree = search::MonteCarloTree::new(position.clone());
+    tree.search(50_000);
+    assert_eq!(
+        tree.proven_result(),
+        Some(ProvenResult::Loss {
+            plies: 2,
+            exact: true
+        })
+    );
+
+    let (best_move, score) = tree.best_move();
+    assert!(position.is_legal(&best_move));
+    assert_eq!(score, 0.0);
+}
+
+#[test]
+fn memory_limited_search_test() {
+    let position = <Position<5>>::default();
+    let memory_limit = 1 << 20;
+    let mut tree = search::MonteCarloTree::with_settings(
+        position.clone(),
+        MctsSetting::default().add_memory_limit(memory_limit),
+    );
+    tree.search(50_000);
+    assert_eq!(tree.visits(), 50_000);
+    assert!(tree.mem_usage() <= memory_limit);
+    assert!(position.is_legal(&tree.best_move().0));
+
+    let mut tree = search::MonteCarloTree::with_settings(
+        position.clone(),
+        MctsSetting::default()
+            .add_memory_limit(memory_limit)
+            .add_threads(4),
+    );
+    tree.search(50_000);
+    assert!(tree.mem_usage() <= memory_limit);
+    assert!(position.is_legal(&tree.best_move().0));
+}
+
 #[test]
 fn win_in_two_moves_test() {
     let move_strings = ["e5", "c3", "c2", "d5", "c1", "c5", "d3", "a4", "e3"];
@@ -1,13 +1,23 @@
+mod any_position_tests;
 mod blunder_tests;
 mod board_generic_tests;
 mod board_tests;
+mod dataset_tests;
+mod endgame_tests;
+mod legality_tests;
 mod mcts_tests;
 mod move_gen_5s_tests;
 mod move_gen_generic_tests;
+mod opening_book_tests;
+mod perft_tests;
 mod policy_tests;
 mod ptn_tests;
+#[cfg(feature = "serde")]
+mod serde_tests;
+mod symmetry_tests;
 mod tactics_tests_5s;
 mod tactics_tests_6s;
+mod tinue_tests;
 
 use crate::position::Position;
 use crate::search;
@@ -1,3 +1,4 @@
+use crate::move_gen::perft;
 use crate::position::Position;
 use board_game_traits::Position as PositionTrait;
 
@@ -10,30 +11,6 @@ fn start_position_move_gen_test() {
     start_position_move_gen_prop::<8>();
 }
 
-pub fn perft<const S: usize>(position: &mut Position<S>, depth: u16) -> u64 {
-    if depth == 0 || position.game_result().is_some() {
-        1
-    } else {
-        let mut moves = vec![];
-        position.generate_moves(&mut moves);
-        moves
-            .into_iter()
-            .map(|mv| {
-                let old_position = position.clone();
-                let reverse_move = position.do_move(mv.clone());
-                let num_moves = perft(position, depth - 1);
-                position.reverse_move(reverse_move);
-                debug_assert_eq!(
-                    *position, old_position,
-                    "Failed to restore old board after {:?} on\n{:?}",
-                    mv, old_position
-                );
-                num_moves
-            })
-            .sum()
-    }
-}
-
 /// Verifies the perft result of a position against a known answer
 pub fn perft_check_answers<const S: usize>(position: &mut Position<S>, answers: &[u64]) {
     for (depth, &answer) in answers.iter().enumerate() {
@@ -0,0 +1,141 @@
+use board_game_traits::Position as PositionTrait;
+use pgn_traits::PgnPosition;
+
+use crate::opening_book::OpeningBook;
+use crate::position::{AnyPosition, Position};
+use crate::ptn::{ptn_parser, AnyGame, Game};
+use crate::search::MonteCarloTree;
+use crate::tests::do_moves_and_check_validity;
+
+#[test]
+fn book_move_found_in_symmetric_position_test() {
+    let mut book = OpeningBook::new(5);
+    let mut position = <Position<5>>::start_position();
+    do_moves_and_check_validity(&mut position, &["a1", "e5"]);
+    book.add_move(&position, &position.move_from_san("b2").unwrap(), 1, 0.6);
+    assert_eq!(book.len(), 1);
+
+    // Mirrored along the vertical axis
+    let mut mirrored = <Position<5>>::start_position();
+    do_moves_and_check_validity(&mut mirrored, &["e1", "a5"]);
+    let moves = book.moves(&mirrored);
+    assert_eq!(moves.len(), 1);
+    assert_eq!(mirrored.move_to_san(&moves[0].mv), "d2");
+    assert_eq!(moves[0].score, 0.6);
+
+    for symmetry in position.symmetries() {
+        let moves = book.moves(&symmetry);
+        assert_eq!(moves.len(), 1);
+        assert!(symmetry.is_legal(&moves[0].mv));
+    }
+
+    let mut other_position = <Position<5>>::start_position();
+    do_moves_and_check_validity(&mut other_position, &["a1", "e4"]);
+    assert!(book.moves(&other_position).is_empty());
+    assert!(book.moves(&<Position<6>>::start_position()).is_empty());
+}
+
+#[test]
+fn book_merges_moves_test() {
+    let mut book = OpeningBook::new(5);
+    let position = <Position<5>>::start_position();
+    book.add_move(&position, &position.move_from_san("a1").unwrap(), 3, 0.5);
+    // The start position is symmetric, so this is the same move
+    book.add_move(&position, &position.move_from_san("e5").unwrap(), 1, 0.1);
+    book.add_move(&position, &position.move_from_san("c3").unwrap(), 0, 0.3);
+
+    let moves = book.moves(&position);
+    assert_eq!(moves.len(), 2);
+    assert_eq!(moves[0].weight, 4);
+    assert!((moves[0].score - 0.4).abs() < 0.0001);
+
+    // Moves with zero weight are never chosen
+    let mut rng = rand::thread_rng();
+    for _ in 0..20 {
+        let (mv, _) = book.choose_move(&position, &mut rng).unwrap();
+        assert_eq!(mv, moves[0].mv);
+    }
+}
+
+#[test]
+fn book_from_games_test() {
+    let ptn = "[Size \"5\"]\n\n1. a1 e5 2. c3 1-0\n\n[Size \"5\"]\n\n1. e1 a5 2. c3 0-1\n\n[Size \"5\"]\n\n1. a1 e5 *\n";
+    let games: Vec<Game<Position<5>>> = ptn_parser::parse_ptn(ptn).unwrap();
+    let mut book = OpeningBook::new(5);
+    for game in games.iter() {
+        book.add_game(game, 3);
+    }
+    // The start position, after 1. a1 and after 1. a1 e5, since both games are symmetric
+    assert_eq!(book.len(), 3);
+
+    let mut position = <Position<5>>::start_position();
+    do_moves_and_check_validity(&mut position, &["a1", "e5"]);
+    let moves = book.moves(&position);
+    assert_eq!(moves.len(), 1);
+    assert_eq!(moves[0].weight, 2);
+    assert_eq!(moves[0].score, 0.5);
+
+    let mut any_book = OpeningBook::new(5);
+    for game in AnyGame::parse_ptn(5, ptn).unwrap() {
+        any_book.add_any_game(&game, 3);
+    }
+    assert_eq!(any_book, book);
+}
+
+#[test]
+fn book_from_analysis_test() {
+    let mut position = <Position<4>>::start_position();
+    do_moves_and_check_validity(&mut position, &["a1", "d4"]);
+    let mut tree = MonteCarloTree::new(position.clone());
+    for _ in 0..1000 {
+        tree.select();
+    }
+    let mut book = OpeningBook::new(4);
+    book.add_analysis(&position, &tree, 100);
+    let moves = book.moves(&position);
+    assert!(!moves.is_empty());
+    assert!(moves.iter().all(|book_move| book_move.weight >= 100));
+    // Equivalent moves in this symmetric position are merged
+    let best_move_visits = tree
+        .move_visits_and_scores()
+        .into_iter()
+        .map(|(_, visits, _)| visits)
+        .max()
+        .unwrap();
+    assert!(moves
+        .iter()
+        .any(|book_move| book_move.weight >= best_move_visits));
+}
+
+#[test]
+fn book_write_read_test() {
+    let mut book = OpeningBook::new(6);
+    let position = <Position<6>>::start_position();
+    book.add_move(&position, &position.move_from_san("a1").unwrap(), 10, 0.5);
+    book.add_move(&position, &position.move_from_san("b2").unwrap(), 2, 0.25);
+    // Scores from analysis are not round numbers
+    book.add_move(
+        &position,
+        &position.move_from_san("c3").unwrap(),
+        7,
+        0.123_456_79,
+    );
+    let mut position = <Position<6>>::start_position();
+    do_moves_and_check_validity(&mut position, &["a1", "f6", "c3"]);
+    book.add_move(&position, &position.move_from_san("d4").unwrap(), 1, 1.0);
+
+    let mut output = vec![];
+    book.write(&mut output).unwrap();
+    let read_book = OpeningBook::read(output.as_slice()).unwrap();
+    assert_eq!(read_book, book);
+
+    let any_position = AnyPosition::from_fen(6, &position.to_fen()).unwrap();
+    let (mv, score) = read_book
+        .choose_any_move(&any_position, &mut rand::thread_rng())
+        .unwrap();
+    assert_eq!(mv.to_string(), "d4");
+    assert_eq!(score, 1.0);
+
+    assert!(OpeningBook::read("size 6\nx6/x6/x6/x6/x6/x6 1 1; a7 1 0.5\n".as_bytes()).is_err());
+    assert!(OpeningBook::read("x6/x6/x6/x6/x6/x6 1 1; a1 1 0.5\n".as_bytes()).is_err());
+}
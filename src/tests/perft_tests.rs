@@ -0,0 +1,125 @@
+//! Reference perft results for verifying the move generator.
+//! Each entry is a TPS string, or an empty string for the start position, followed by the node counts from depth 0.
+
+use crate::position::Position;
+use crate::tests::move_gen_generic_tests::perft_check_answers;
+use board_game_traits::Position as PositionTrait;
+use pgn_traits::PgnPosition;
+
+/// Only check depths up to this many nodes by default, so the test suite runs quickly in debug builds
+const MAX_NODES: u64 = 1_000_000;
+
+const PERFT_3S: &[(&str, &[u64])] = &[("", &[1, 9, 72, 1200, 17_792, 271_812])];
+
+const PERFT_4S: &[(&str, &[u64])] = &[
+    ("", &[1, 16, 240, 7440, 216_464, 6_468_872]),
+    (
+        "1,21S,x2/x,2112,1,x/x,2,x2/1,x2,2 1 8",
+        &[1, 33, 1475, 54_076, 1_938_039],
+    ),
+];
+
+const PERFT_5S: &[(&str, &[u64])] = &[
+    ("", &[1, 25, 600, 43_320, 2_999_784]),
+    (
+        "x2,2,x2/x,2221C,1,x2/x,1,212S,2,x/1,x2,12C,x/x5 1 10",
+        &[1, 77, 5751, 366_143, 25_780_525],
+    ),
+    // Both players threaten to finish a road
+    (
+        "1,1,1,1,x/2,2,2,2,x/x5/x5/x5 1 5",
+        &[1, 62, 3820, 212_595, 12_246_338],
+    ),
+];
+
+const PERFT_6S: &[(&str, &[u64])] = &[
+    ("", &[1, 36, 1260, 132_720, 13_586_048]),
+    (
+        "x2,2,x3/x,1,221C,1,x2/x,12,21112S,2,2,x/x,1,x,1S,x2/x,2C,x4/1,x5 1 14",
+        &[1, 80, 10_567, 945_018],
+    ),
+    // Both players threaten to finish a road
+    (
+        "2,2,2,2,2,x/1,1,1,1,1,x/x6/x6/x6/2C,x4,1C 1 7",
+        &[1, 69, 4270, 291_606],
+    ),
+];
+
+const PERFT_7S: &[(&str, &[u64])] = &[
+    ("", &[1, 49, 2352, 339_696]),
+    // Capstone on a stack taller than the carry limit
+    (
+        "x3,2,x3/x,1,x5/x2,1221212121C,x4/x7/x3,2S,1,x2/x,2C,x5/1,x6 1 16",
+        &[1, 387, 62_451, 14_216_224],
+    ),
+];
+
+const PERFT_8S: &[(&str, &[u64])] = &[
+    ("", &[1, 64, 4032, 764_064]),
+    // Capstone on a stack taller than the carry limit
+    (
+        "x8/x2,2,x5/x,11221122112C,x6/x3,1S,x4/x8/x4,2C,x3/x,2,x6/1,x7 2 18",
+        &[1, 636, 131_611, 33_762_310],
+    ),
+];
+
+#[test]
+fn perft_reference_3s_4s_test() {
+    check_reference_perfts::<3>(PERFT_3S, MAX_NODES);
+    check_reference_perfts::<4>(PERFT_4S, MAX_NODES);
+}
+
+#[test]
+fn perft_reference_5s_6s_test() {
+    check_reference_perfts::<5>(PERFT_5S, MAX_NODES);
+    check_reference_perfts::<6>(PERFT_6S, MAX_NODES);
+}
+
+#[test]
+fn perft_reference_7s_8s_test() {
+    check_reference_perfts::<7>(PERFT_7S, MAX_NODES);
+    check_reference_perfts::<8>(PERFT_8S, MAX_NODES);
+}
+
+#[test]
+#[ignore]
+fn perft_reference_full_test() {
+    check_reference_perfts::<3>(PERFT_3S, u64::MAX);
+    check_reference_perfts::<4>(PERFT_4S, u64::MAX);
+    check_reference_perfts::<5>(PERFT_5S, u64::MAX);
+    check_reference_perfts::<6>(PERFT_6S, u64::MAX);
+    check_reference_perfts::<7>(PERFT_7S, u64::MAX);
+    check_reference_perfts::<8>(PERFT_8S, u64::MAX);
+}
+
+#[test]
+fn divide_sums_to_perft_test() {
+    let mut position = <Position<5>>::from_fen(PERFT_5S[1].0).unwrap();
+    let divided = crate::move_gen::divide(&mut position, 2);
+    let mut moves = vec![];
+    position.generate_moves(&mut moves);
+
+    assert_eq!(
+        divided.iter().map(|(mv, _)| mv.clone()).collect::<Vec<_>>(),
+        moves
+    );
+    assert_eq!(
+        divided.iter().map(|(_, nodes)| nodes).sum::<u64>(),
+        PERFT_5S[1].1[2]
+    );
+}
+
+fn check_reference_perfts<const S: usize>(references: &[(&str, &[u64])], max_nodes: u64) {
+    for (tps, answers) in references {
+        let mut position = if tps.is_empty() {
+            <Position<S>>::start_position()
+        } else {
+            <Position<S>>::from_fen(tps).unwrap()
+        };
+        let num_answers = answers
+            .iter()
+            .take_while(|&&nodes| nodes <= max_nodes)
+            .count();
+        perft_check_answers(&mut position, &answers[..num_answers]);
+    }
+}
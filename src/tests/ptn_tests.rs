@@ -1,8 +1,11 @@
-use crate::position::{Move, Position};
-use crate::ptn::{ptn_parser, Game, PtnMove};
+use crate::position::{Komi, Move, Position, RuleSet};
+use crate::ptn::ptn_parser::{PtnError, PtnReader};
+use crate::ptn::{ptn_parser, Annotations, CommentTags, Game, MoveQuality, PtnMove, TakMark};
 use crate::tests::do_moves_and_check_validity;
 use board_game_traits::{GameResult, Position as PositionTrait};
+use pgn_traits::PgnPosition;
 use std::io::Cursor;
+use std::time::Duration;
 
 #[test]
 pub fn write_and_read_ptn_test() {
@@ -16,8 +19,10 @@ pub fn write_and_read_ptn_test() {
         .iter()
         .map(|mv| PtnMove {
             mv: mv.clone(),
-            annotations: vec![],
+            annotations: Annotations::default(),
             comment: "".to_string(),
+            comment_tags: CommentTags::default(),
+            variations: vec![],
         })
         .collect();
 
@@ -44,6 +49,103 @@ pub fn write_and_read_ptn_test() {
     assert_eq!(parsed_games, vec![game])
 }
 
+#[test]
+fn write_road_win_result_test() {
+    let mut position = <Position<5>>::start_position();
+    do_moves_and_check_validity(
+        &mut position,
+        &["a1", "e5", "e4", "a2", "e3", "a3", "c3", "a4", "c2", "a5"],
+    );
+    let moves: Vec<PtnMove<Move>> = position
+        .moves()
+        .iter()
+        .map(|mv| PtnMove {
+            mv: mv.clone(),
+            annotations: Annotations::default(),
+            comment: "".to_string(),
+            comment_tags: CommentTags::default(),
+            variations: vec![],
+        })
+        .collect();
+
+    for (game_result, result_string) in [
+        (Some(GameResult::BlackWin), "0-R"),
+        (Some(GameResult::WhiteWin), "1-0"),
+    ] {
+        let game: Game<Position<5>> = Game {
+            start_position: Position::start_position(),
+            moves: moves.clone(),
+            game_result,
+            tags: vec![],
+        };
+        let mut ptn_writer = Cursor::new(vec![]);
+        game.game_to_ptn(&mut ptn_writer).unwrap();
+        let ptn = String::from_utf8(ptn_writer.into_inner()).unwrap();
+        assert!(
+            ptn.contains(&format!("[Result \"{}\"]", result_string)),
+            "{}",
+            ptn
+        );
+        assert!(ptn.contains(&format!("a5 {}", result_string)), "{}", ptn);
+    }
+}
+
+#[test]
+fn write_and_read_ptn_with_komi_test() {
+    let komi = Komi::from_half_komi(5).unwrap();
+    let mut position = <Position<6>>::start_position_with_komi(komi);
+    do_moves_and_check_validity(&mut position, &["a1", "f6", "e6"]);
+    let moves = position
+        .moves()
+        .iter()
+        .map(|mv| PtnMove {
+            mv: mv.clone(),
+            annotations: Annotations::default(),
+            comment: "".to_string(),
+            comment_tags: CommentTags::default(),
+            variations: vec![],
+        })
+        .collect();
+
+    let game: Game<Position<6>> = Game {
+        start_position: Position::start_position_with_komi(komi),
+        moves,
+        game_result: None,
+        tags: vec![],
+    };
+
+    let mut ptn_writer = Cursor::new(vec![]);
+    game.game_to_ptn(&mut ptn_writer).unwrap();
+    let ptn = String::from_utf8(ptn_writer.into_inner()).unwrap();
+    assert!(ptn.contains("[Komi \"2.5\"]"), "{}", ptn);
+
+    let parsed_games: Vec<Game<Position<6>>> = ptn_parser::parse_ptn(&ptn).unwrap();
+
+    assert_eq!(parsed_games.len(), 1);
+    assert_eq!(parsed_games[0].start_position, game.start_position);
+    assert_eq!(parsed_games[0].moves, game.moves);
+}
+
+#[test]
+fn parse_ptn_with_rule_tags_test() {
+    let ptn =
+        "[Size \"5\"]\n[Flats \"20\"]\n[Caps \"2\"]\n[Opening \"no-swap\"]\n\n1. Ca1 Cb1 2. c1 *";
+
+    let games: Vec<Game<Position<5>>> = ptn_parser::parse_ptn(ptn).unwrap();
+
+    let rules = RuleSet::default()
+        .add_starting_stones(20)
+        .unwrap()
+        .add_starting_capstones(2)
+        .unwrap()
+        .without_opening_swap();
+    assert_eq!(
+        games[0].start_position,
+        Position::start_position_with_rules(rules)
+    );
+    assert_eq!(games[0].moves.len(), 3);
+}
+
 #[test]
 // PTNs without a result shouldn't exist, but try to handle it correctly anyway
 fn parse_ptn_without_result() {
@@ -70,3 +172,317 @@ fn parse_ptn_without_result2() {
 fn parse_bad_direction_test() {
     assert!(Move::from_string::<6>("a1d").is_err())
 }
+
+const PTN_WITH_VARIATIONS: &str =
+    "[Size \"5\"]\n\n1. a1 e5 (1... e1 2. c3) 2. c3 (2. d3 {Also good} c4) c4 1-0";
+
+#[test]
+fn parse_ptn_with_variations_test() {
+    let games: Vec<Game<Position<5>>> = ptn_parser::parse_ptn(PTN_WITH_VARIATIONS).unwrap();
+    let game = &games[0];
+    let san = |ptn_move: &PtnMove<Move>| ptn_move.mv.to_string::<5>();
+
+    assert_eq!(game.mainline().count(), 4);
+    assert_eq!(game.game_result, Some(GameResult::WhiteWin));
+    assert_eq!(
+        game.children(&[0]).into_iter().map(san).collect::<Vec<_>>(),
+        vec!["e5", "e1"]
+    );
+    assert_eq!(game.node(&[0, 1, 0]).map(san), Some("c3".to_string()));
+    assert_eq!(game.node(&[0, 0, 1]).unwrap().comment, "Also good");
+    assert_eq!(
+        game.path_to_node(&[0, 0, 1, 0])
+            .unwrap()
+            .into_iter()
+            .map(san)
+            .collect::<Vec<_>>(),
+        vec!["a1", "e5", "d3", "c4"]
+    );
+
+    let mut position = <Position<5>>::start_position();
+    do_moves_and_check_validity(&mut position, &["a1", "e5", "d3", "c4"]);
+    assert_eq!(
+        game.position_at(&[0, 0, 1, 0]).unwrap().to_fen(),
+        position.to_fen()
+    );
+
+    assert!(game.node(&[]).is_none());
+    assert!(game.node(&[0, 2]).is_none());
+    assert!(game.children(&[0, 0, 0, 0]).is_empty());
+}
+
+#[test]
+fn write_and_read_ptn_with_variations_test() {
+    let games: Vec<Game<Position<5>>> = ptn_parser::parse_ptn(PTN_WITH_VARIATIONS).unwrap();
+
+    let mut ptn_writer = Cursor::new(vec![]);
+    games[0].game_to_ptn(&mut ptn_writer).unwrap();
+    let ptn = String::from_utf8(ptn_writer.into_inner()).unwrap();
+    assert!(ptn.contains("(1... e1 2. c3)"), "{}", ptn);
+    assert!(ptn.contains("2... c4 1-0"), "{}", ptn);
+
+    let parsed_games: Vec<Game<Position<5>>> = ptn_parser::parse_ptn(&ptn).unwrap();
+    assert_eq!(parsed_games[0].moves, games[0].moves);
+    assert_eq!(parsed_games[0].game_result, games[0].game_result);
+}
+
+#[test]
+fn add_variation_test() {
+    let mut games: Vec<Game<Position<5>>> = ptn_parser::parse_ptn("1. a1 e5 2. c3 *").unwrap();
+    let game = &mut games[0];
+    let ptn_move = |mv: &str| PtnMove {
+        mv: Move::from_string::<5>(mv).unwrap(),
+        annotations: Annotations::default(),
+        comment: String::new(),
+        comment_tags: CommentTags::default(),
+        variations: vec![],
+    };
+
+    assert_eq!(
+        game.add_variation(&[0], vec![ptn_move("e1"), ptn_move("c3")])
+            .unwrap(),
+        vec![0, 1]
+    );
+    assert_eq!(game.children(&[0]).len(), 2);
+
+    // Moves after the end of a line extend it
+    assert_eq!(
+        game.add_variation(&[0, 0, 0], vec![ptn_move("c4")])
+            .unwrap(),
+        vec![0, 0, 0, 0]
+    );
+    assert_eq!(game.mainline().count(), 4);
+
+    assert!(game.add_variation(&[0], vec![ptn_move("a1")]).is_err());
+    assert!(game.add_variation(&[0, 3], vec![ptn_move("c3")]).is_err());
+    assert!(game.add_variation(&[0], vec![]).is_err());
+}
+
+#[test]
+fn parse_unbalanced_variation_test() {
+    assert!(ptn_parser::parse_ptn::<Position<5>>("1. a1 e5 (1... e1 2. c3 *").is_err());
+    assert!(ptn_parser::parse_ptn::<Position<5>>("1. a1 e5 ) 2. c3 *").is_err());
+    assert!(ptn_parser::parse_ptn::<Position<5>>("(1. a1) 1. e5 *").is_err());
+}
+
+#[test]
+fn parse_ptn_with_tps_tag_test() {
+    let tps = "2,x4/x5/x2,1,x2/x5/1,x4 2 2";
+    let ptn = format!("[Size \"5\"]\n[TPS \"{}\"]\n\n2... e1 3. c4 *", tps);
+
+    let games: Vec<Game<Position<5>>> = ptn_parser::parse_ptn(&ptn).unwrap();
+    assert_eq!(
+        games[0].start_position,
+        <Position<5>>::from_fen(tps).unwrap()
+    );
+    assert_eq!(games[0].moves.len(), 2);
+
+    // The moves are checked against the TPS, not the empty board
+    let ptn = format!("[Size \"5\"]\n[TPS \"{}\"]\n\n2... a5 *", tps);
+    assert!(ptn_parser::parse_ptn::<Position<5>>(&ptn).is_err());
+}
+
+#[test]
+fn write_and_read_ptn_with_tps_test() {
+    let tps = "2,x4/x5/x2,1,x2/x5/1,x4 2 2";
+    let start_position = <Position<5>>::from_fen(tps).unwrap();
+    let moves = ["e1", "c4", "b2"]
+        .iter()
+        .map(|mv| PtnMove {
+            mv: start_position.move_from_san(mv).unwrap(),
+            annotations: Annotations::default(),
+            comment: String::new(),
+            comment_tags: CommentTags::default(),
+            variations: vec![],
+        })
+        .collect();
+    let game = Game {
+        start_position,
+        moves,
+        game_result: None,
+        tags: vec![],
+    };
+
+    let mut ptn_writer = Cursor::new(vec![]);
+    game.game_to_ptn(&mut ptn_writer).unwrap();
+    let ptn = String::from_utf8(ptn_writer.into_inner()).unwrap();
+    assert!(
+        ptn.contains(&format!("[TPS \"{}\"]\n", game.start_position.to_fen())),
+        "{}",
+        ptn
+    );
+    assert!(ptn.contains("2... e1 3. c4 b2"), "{}", ptn);
+
+    let parsed_games: Vec<Game<Position<5>>> = ptn_parser::parse_ptn(&ptn).unwrap();
+    assert_eq!(parsed_games[0].start_position, game.start_position);
+    assert_eq!(parsed_games[0].moves, game.moves);
+}
+
+#[test]
+fn parse_and_write_annotations_test() {
+    let ptn = "1. a1 e5' 2. c3!? c4''? 3. d3\"!! b4?? *";
+    let games: Vec<Game<Position<5>>> = ptn_parser::parse_ptn(ptn).unwrap();
+    let annotations: Vec<Annotations> = games[0]
+        .moves
+        .iter()
+        .map(|ptn_move| ptn_move.annotations)
+        .collect();
+    assert_eq!(
+        annotations,
+        vec![
+            Annotations::default(),
+            Annotations {
+                tak_mark: Some(TakMark::Tak),
+                quality: None
+            },
+            Annotations {
+                tak_mark: None,
+                quality: Some(MoveQuality::Interesting)
+            },
+            Annotations {
+                tak_mark: Some(TakMark::Tinue),
+                quality: Some(MoveQuality::Mistake)
+            },
+            Annotations {
+                tak_mark: Some(TakMark::Tinue),
+                quality: Some(MoveQuality::Brilliant)
+            },
+            Annotations {
+                tak_mark: None,
+                quality: Some(MoveQuality::Blunder)
+            },
+        ]
+    );
+
+    let mut ptn_writer = Cursor::new(vec![]);
+    games[0].game_to_ptn(&mut ptn_writer).unwrap();
+    let written_ptn = String::from_utf8(ptn_writer.into_inner()).unwrap();
+    assert!(written_ptn.contains("2. c3!? c4\"?"), "{}", written_ptn);
+
+    let parsed_games: Vec<Game<Position<5>>> = ptn_parser::parse_ptn(&written_ptn).unwrap();
+    assert_eq!(parsed_games[0].moves, games[0].moves);
+
+    assert!(ptn_parser::parse_ptn::<Position<5>>("1. a1!!! *").is_err());
+}
+
+#[test]
+fn parse_and_write_comment_tags_test() {
+    let ptn = "1. a1 {[%eval 0.45] [%depth 12] [%best e5] [%clk 0:04:30.5] Corner [%custom x]} e5 {[%clk 5:00]} *";
+    let games: Vec<Game<Position<5>>> = ptn_parser::parse_ptn(ptn).unwrap();
+    let first_move = &games[0].moves[0];
+    assert_eq!(
+        first_move.comment_tags,
+        CommentTags {
+            eval: Some(0.45),
+            depth: Some(12),
+            best_move: Some(Move::from_string::<5>("e5").unwrap()),
+            clock: Some(Duration::from_millis(270_500)),
+        }
+    );
+    assert_eq!(first_move.comment, "Corner [%custom x]");
+    assert_eq!(
+        games[0].moves[1].comment_tags.clock,
+        Some(Duration::from_secs(300))
+    );
+    assert!(games[0].moves[1].comment.is_empty());
+
+    let mut ptn_writer = Cursor::new(vec![]);
+    games[0].game_to_ptn(&mut ptn_writer).unwrap();
+    let written_ptn = String::from_utf8(ptn_writer.into_inner()).unwrap();
+    assert!(
+        written_ptn.contains("{[%eval 0.45] [%depth 12] [%best e5] [%clk 0:04:30.500] Corner"),
+        "{}",
+        written_ptn
+    );
+
+    let parsed_games: Vec<Game<Position<5>>> = ptn_parser::parse_ptn(&written_ptn).unwrap();
+    assert_eq!(parsed_games[0].moves, games[0].moves);
+
+    assert!(ptn_parser::parse_ptn::<Position<5>>("1. a1 {[%eval high]} *").is_err());
+}
+
+#[test]
+fn ptn_reader_skips_bad_games_test() {
+    let ptn = "[Size \"5\"]\n\n1. a1 e5 2. c3 *\n\n[Size \"5\"]\n\n1. a1 e5\n2. Ca1 *\n\n[Size \"5\"]\n\n1. a1 {multi-line\n[%eval 0.5] comment} e5 1-0\n\n1. a2 e4 *\n";
+    let results: Vec<Result<Game<Position<5>>, PtnError>> =
+        PtnReader::new(Cursor::new(ptn)).collect();
+    assert_eq!(results.len(), 4);
+    assert_eq!(results[0].as_ref().unwrap().moves.len(), 3);
+
+    let error = results[1].as_ref().unwrap_err();
+    assert_eq!(
+        (error.game_number, error.line, error.column),
+        (2, 8, 4),
+        "{}",
+        error
+    );
+    assert!(error.to_string().contains("Ca1"), "{}", error);
+
+    let game = results[2].as_ref().unwrap();
+    assert_eq!(game.moves[0].comment_tags.eval, Some(0.5));
+    assert_eq!(game.game_result, Some(GameResult::WhiteWin));
+    assert_eq!(results[3].as_ref().unwrap().moves.len(), 2);
+
+    assert!(ptn_parser::parse_ptn::<Position<5>>(ptn).is_err());
+}
+
+#[test]
+fn ptn_reader_splits_games_without_tags_test() {
+    let ptn = "1. a1 e5 2. Ca1 R-0\n1. a1 e5 2. c3 {0-R} (2. d3 R-0) c4 1/2-1/2\n1. a2 e4 F-0\n";
+    let results: Vec<Result<Game<Position<5>>, PtnError>> =
+        PtnReader::new(Cursor::new(ptn)).collect();
+    assert_eq!(results.len(), 3);
+
+    let error = results[0].as_ref().unwrap_err();
+    assert_eq!((error.game_number, error.line), (1, 1), "{}", error);
+
+    // Results in variations and comments don't end the game
+    let game = results[1].as_ref().unwrap();
+    assert_eq!(game.moves.len(), 4);
+    assert_eq!(game.moves[2].variations.len(), 1);
+    assert_eq!(game.game_result, Some(GameResult::Draw));
+
+    let game = results[2].as_ref().unwrap();
+    assert_eq!(game.moves.len(), 2);
+    assert_eq!(game.game_result, Some(GameResult::WhiteWin));
+}
+
+#[test]
+fn ptn_reader_skips_unterminated_comment_test() {
+    let ptn = "[Size \"5\"]\n\n1. a1 {oops e5 1-0\n\n[Size \"5\"]\n\n1. a1 e5 *\n\n[Size \"5\"]\n\n1. a2 e4 F-0\n";
+    let results: Vec<Result<Game<Position<5>>, PtnError>> =
+        PtnReader::new(Cursor::new(ptn)).collect();
+    assert_eq!(results.len(), 3);
+
+    let error = results[0].as_ref().unwrap_err();
+    assert_eq!(
+        (error.game_number, error.line, error.column),
+        (1, 3, 7),
+        "{}",
+        error
+    );
+
+    assert_eq!(results[1].as_ref().unwrap().moves.len(), 2);
+    let game = results[2].as_ref().unwrap();
+    assert_eq!(game.moves.len(), 2);
+    assert_eq!(game.game_result, Some(GameResult::WhiteWin));
+
+    assert!(ptn_parser::parse_ptn::<Position<5>>(ptn).is_err());
+}
+
+#[test]
+fn ptn_reader_error_location_test() {
+    let ptn = "[Size \"5\"]\n[Komi \"x\"]\n\n1. a1 e5 *\n\n[Size \"5\"]\n\n1. a1 e5\n   2. c3 (2. f3) *\n";
+    let errors: Vec<PtnError> = PtnReader::<_, Position<5>>::new(Cursor::new(ptn))
+        .filter_map(Result::err)
+        .collect();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(
+        (errors[0].game_number, errors[0].line, errors[0].column),
+        (1, 2, 2)
+    );
+    assert_eq!(
+        (errors[1].game_number, errors[1].line, errors[1].column),
+        (2, 9, 14)
+    );
+}
@@ -0,0 +1,114 @@
+use board_game_traits::{GameResult, Position as PositionTrait};
+use pgn_traits::PgnPosition;
+
+use crate::position::{Komi, Move, Piece, Position, RuleSet, Square};
+use crate::ptn::{Annotations, CommentTags, Game, MoveQuality, PtnMove};
+use crate::search::{MonteCarloTree, SearchResult};
+use crate::tests::do_moves_and_check_validity;
+use std::time::Duration;
+
+#[test]
+fn position_round_trip_test() {
+    let rules = RuleSet::default()
+        .add_komi(Komi::from_half_komi(4).unwrap())
+        .without_opening_swap();
+    let mut position = <Position<6>>::start_position_with_rules(rules);
+    do_moves_and_check_validity(&mut position, &["a1", "f6", "Cc3", "d4", "c3+", "d4-"]);
+
+    let json = serde_json::to_string(&position).unwrap();
+    assert!(json.contains(&position.to_fen()), "{}", json);
+
+    let deserialized: Position<6> = serde_json::from_str(&json).unwrap();
+    assert_eq!(deserialized, position);
+    assert_eq!(deserialized.rules(), position.rules());
+}
+
+#[test]
+fn deserialize_bad_position_test() {
+    let position = <Position<5>>::start_position();
+    let json = serde_json::to_string(&position).unwrap();
+    assert!(serde_json::from_str::<Position<6>>(&json).is_err());
+    assert!(serde_json::from_str::<Position<5>>(&json.replace(" 1 1", " 3 1")).is_err());
+}
+
+#[test]
+fn move_and_piece_round_trip_test() {
+    let position = <Position<5>>::start_position();
+    for move_string in ["a1", "Sb2", "Cc3", "3d4>111", "e5<"] {
+        let mv = position.move_from_san(move_string).unwrap();
+        let json = serde_json::to_string(&mv).unwrap();
+        assert_eq!(serde_json::from_str::<Move>(&json).unwrap(), mv);
+    }
+    let json = serde_json::to_string(&(Piece::BlackCap, Square(7))).unwrap();
+    assert_eq!(
+        serde_json::from_str::<(Piece, Square)>(&json).unwrap(),
+        (Piece::BlackCap, Square(7))
+    );
+}
+
+#[test]
+fn game_round_trip_test() {
+    let mut position = <Position<5>>::start_position();
+    do_moves_and_check_validity(&mut position, &["a1", "e5", "c3", "c2"]);
+    let moves = position
+        .moves()
+        .iter()
+        .map(|mv| PtnMove {
+            mv: mv.clone(),
+            annotations: Annotations {
+                tak_mark: None,
+                quality: Some(MoveQuality::Good),
+            },
+            comment: "Good move".to_string(),
+            comment_tags: CommentTags {
+                eval: Some(0.5),
+                depth: Some(3),
+                best_move: Some(mv.clone()),
+                clock: Some(Duration::from_secs(90)),
+            },
+            variations: vec![],
+        })
+        .collect();
+    let game: Game<Position<5>> = Game {
+        start_position: Position::start_position(),
+        moves,
+        game_result: Some(GameResult::Draw),
+        tags: vec![("Player1".to_string(), "tiltak".to_string())],
+    };
+
+    let json = serde_json::to_string(&game).unwrap();
+    assert!(json.contains("\"1/2-1/2\""), "{}", json);
+    assert_eq!(
+        serde_json::from_str::<Game<Position<5>>>(&json).unwrap(),
+        game
+    );
+    assert!(
+        serde_json::from_str::<Game<Position<5>>>(&json.replace("\"Good\"", "\"Great\"")).is_err()
+    );
+}
+
+#[test]
+fn search_result_round_trip_test() {
+    let mut tree = MonteCarloTree::new(<Position<5>>::start_position());
+    for _ in 0..1000 {
+        tree.select();
+    }
+    let search_result = tree.search_result();
+    assert_eq!(search_result.best_move, tree.best_move().0);
+    assert_eq!(search_result.move_visits.len(), 25);
+    assert_eq!(
+        search_result
+            .move_visits
+            .iter()
+            .map(|(_, visits)| visits)
+            .sum::<u64>()
+            + 1,
+        search_result.visits
+    );
+
+    let json = serde_json::to_string(&search_result).unwrap();
+    assert_eq!(
+        serde_json::from_str::<SearchResult>(&json).unwrap(),
+        search_result
+    );
+}
@@ -0,0 +1,124 @@
+use board_game_traits::Position as PositionTrait;
+use pgn_traits::PgnPosition;
+use rand::seq::SliceRandom;
+
+use crate::position::{Move, Position, Square, Transform};
+
+#[test]
+fn transform_inverse_test() {
+    for transform in Transform::all().iter() {
+        assert_eq!(transform.then(transform.inverse()), Transform::identity());
+        assert_eq!(transform.inverse().then(*transform), Transform::identity());
+        for square in 0..25 {
+            let square = Square(square);
+            assert_eq!(
+                transform
+                    .inverse()
+                    .apply_square::<5>(transform.apply_square::<5>(square)),
+                square
+            );
+        }
+    }
+}
+
+#[test]
+fn transform_composition_test() {
+    for first in Transform::all().iter() {
+        for second in Transform::all().iter() {
+            for square in 0..36 {
+                let square = Square(square);
+                assert_eq!(
+                    first.then(*second).apply_square::<6>(square),
+                    second.apply_square::<6>(first.apply_square::<6>(square))
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn rotate_square_test() {
+    let position = <Position<5>>::default();
+    let a1 = position.move_from_san("a1").unwrap();
+    let a5 = position.move_from_san("a5").unwrap();
+    let e1 = position.move_from_san("e1").unwrap();
+    assert_eq!(Transform::rotation().apply_move::<5>(&a1), a5);
+    assert_eq!(Transform::mirror().apply_move::<5>(&a1), e1);
+
+    let spread = position.move_from_san("3c3+12").unwrap();
+    assert_eq!(
+        Transform::rotation().apply_move::<5>(&spread),
+        position.move_from_san("3c3>12").unwrap()
+    );
+    assert_eq!(
+        Transform::mirror().apply_move::<5>(&position.move_from_san("b2<").unwrap()),
+        position.move_from_san("d2>").unwrap()
+    );
+}
+
+#[test]
+fn canonical_5s_test() {
+    canonical_prop::<5>()
+}
+
+#[test]
+fn canonical_6s_test() {
+    canonical_prop::<6>()
+}
+
+fn canonical_prop<const S: usize>() {
+    let mut rng = rand::thread_rng();
+    let mut moves = vec![];
+    for _ in 0..10 {
+        let mut position = <Position<S>>::start_position();
+        for _ in 0..60 {
+            if position.game_result().is_some() {
+                break;
+            }
+            let (canonical, transform) = position.canonical();
+            assert_eq!(canonical, position.transform(transform));
+            assert_eq!(canonical.zobrist_hash(), position.canonical_hash());
+            assert_eq!(
+                canonical.zobrist_hash(),
+                canonical.zobrist_hash_from_scratch()
+            );
+
+            for symmetry in position.symmetries() {
+                assert_eq!(symmetry.canonical_hash(), position.canonical_hash());
+                assert_eq!(symmetry.canonical().0, canonical);
+            }
+
+            moves.clear();
+            position.generate_moves(&mut moves);
+            let mut canonical_moves = vec![];
+            canonical.generate_moves(&mut canonical_moves);
+            assert_eq!(moves.len(), canonical_moves.len());
+            for mv in moves.iter() {
+                let canonical_move: Move = transform.apply_move::<S>(mv);
+                assert!(canonical_moves.contains(&canonical_move));
+                assert_eq!(transform.inverse().apply_move::<S>(&canonical_move), *mv);
+
+                let mut child = position.clone();
+                child.do_move(mv.clone());
+                let mut canonical_child = canonical.clone();
+                canonical_child.do_move(canonical_move);
+                assert_eq!(child.transform(transform), canonical_child);
+                assert_eq!(child.canonical_hash(), canonical_child.canonical_hash());
+            }
+            position.do_move(moves.choose(&mut rng).unwrap().clone());
+        }
+    }
+}
+
+#[test]
+fn symmetries_have_correct_hash_test() {
+    let position =
+        <Position<5>>::from_fen("2,x4/x,1S,x3/x,2C,1,x2/x2,12,x2/1,x,21,x,2 1 7").unwrap();
+    for symmetry in position.symmetries() {
+        assert_eq!(
+            symmetry.zobrist_hash(),
+            symmetry.zobrist_hash_from_scratch()
+        );
+        assert_eq!(symmetry.canonical_hash(), position.canonical_hash());
+    }
+}
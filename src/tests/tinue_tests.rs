@@ -0,0 +1,68 @@
+use board_game_traits::{GameResult, Position as PositionTrait};
+use pgn_traits::PgnPosition;
+
+use crate::position::{AnyPosition, Position};
+use crate::tinue::{find_tinue, TinueResult};
+
+/// Check that the line is legal and ends in a win for the side to move
+fn check_winning_line<const S: usize>(tps: &str, result: &TinueResult) -> Vec<String> {
+    let mut position = <Position<S>>::from_fen(tps).unwrap();
+    let winner = match position.side_to_move() {
+        board_game_traits::Color::White => GameResult::WhiteWin,
+        board_game_traits::Color::Black => GameResult::BlackWin,
+    };
+    let line = match result {
+        TinueResult::Win(line) => line,
+        _ => panic!("Expected a win, got {:?}", result),
+    };
+    let mut move_strings = vec![];
+    for mv in line {
+        assert_eq!(position.game_result(), None);
+        move_strings.push(position.move_to_san(mv));
+        position.try_do_move(mv.clone()).unwrap();
+    }
+    assert_eq!(position.game_result(), Some(winner));
+    move_strings
+}
+
+#[test]
+fn win_in_one_test() {
+    let tps = "2,2,2,2,x/x5/x5/x5/1,1,1,1,x 1 5";
+    let position = <Position<5>>::from_fen(tps).unwrap();
+    let result = find_tinue(&position, 1, 1_000_000);
+    assert_eq!(check_winning_line::<5>(tps, &result), vec!["e1"]);
+}
+
+#[test]
+fn double_threat_tinue_test() {
+    let tps = "2,2,2,x2/x3,1,x/1,1,1,x2/x3,1,x/x3,1,x 1 7";
+    let position = <Position<5>>::from_fen(tps).unwrap();
+
+    assert_eq!(find_tinue(&position, 1, 1_000_000), TinueResult::NoWin);
+
+    let result = find_tinue(&position, 5, 10_000_000);
+    assert_eq!(check_winning_line::<5>(tps, &result).len(), 3);
+}
+
+#[test]
+fn single_threat_is_not_tinue_test() {
+    let tps = "2,2,2,x2/x5/1,1,1,x2/x5/x5 1 4";
+    let position = <Position<5>>::from_fen(tps).unwrap();
+    assert_eq!(find_tinue(&position, 3, 10_000_000), TinueResult::NoWin);
+}
+
+#[test]
+fn tinue_node_limit_test() {
+    let tps = "2,2,2,x2/x3,1,x/1,1,1,x2/x3,1,x/x3,1,x 1 7";
+    let position = <Position<5>>::from_fen(tps).unwrap();
+    assert_eq!(find_tinue(&position, 5, 100), TinueResult::Unknown);
+}
+
+#[test]
+fn tinue_any_position_test() {
+    let position = AnyPosition::from_fen(6, "2,2,2,2,2,x/x6/x6/x6/x6/1,1,1,1,1,x 2 6").unwrap();
+    match position.find_tinue(1, 1_000_000) {
+        TinueResult::Win(line) => assert_eq!(line[0].to_string(), "f6"),
+        result => panic!("Expected a win, got {:?}", result),
+    }
+}
@@ -0,0 +1,183 @@
+//! A solver for tinue, a forced road win for the side to move.
+//!
+//! The attacker only plays moves that threaten to win on the next move, while every reply from the defender is checked.
+//! A win found by the solver is therefore proven, but `TinueResult::NoWin` only means that there is no such sequence of threats within the depth limit.
+
+use std::collections::HashMap;
+
+use board_game_traits::{Color, GameResult, Position as PositionTrait};
+
+use crate::position::{Move, Position, ReverseMove};
+
+/// The result of a tinue search
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum TinueResult<M = Move> {
+    /// The side to move has a forced win. Contains the winning line, where the defender always plays the reply that holds out the longest.
+    Win(Vec<M>),
+    /// There is no forced win within the depth limit
+    NoWin,
+    /// The node limit was reached before the search finished
+    Unknown,
+}
+
+/// Search for a forced win for the side to move, looking at most `max_plies` plies ahead and making at most `max_nodes` moves on the board.
+pub fn find_tinue<const S: usize>(
+    position: &Position<S>,
+    max_plies: u16,
+    max_nodes: u64,
+) -> TinueResult {
+    TinueSolver::new(position.clone(), max_nodes).solve(max_plies)
+}
+
+pub struct TinueSolver<const S: usize> {
+    position: Position<S>,
+    attacker: Color,
+    max_nodes: u64,
+    nodes: u64,
+    out_of_nodes: bool,
+    /// Positions with the attacker to move, and the number of plies they are known not to win within
+    refuted: HashMap<u64, u16>,
+}
+
+impl<const S: usize> TinueSolver<S> {
+    pub fn new(position: Position<S>, max_nodes: u64) -> Self {
+        TinueSolver {
+            attacker: position.side_to_move(),
+            position,
+            max_nodes,
+            nodes: 0,
+            out_of_nodes: false,
+            refuted: HashMap::new(),
+        }
+    }
+
+    /// Number of moves made on the board so far
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    /// Search with iterative deepening, so that the shortest win is found first
+    pub fn solve(&mut self, max_plies: u16) -> TinueResult {
+        if self.position.game_result().is_some() {
+            return TinueResult::NoWin;
+        }
+        for plies in (1..=max_plies).step_by(2) {
+            if let Some(line) = self.attack(plies) {
+                return TinueResult::Win(line);
+            }
+            if self.out_of_nodes {
+                return TinueResult::Unknown;
+            }
+        }
+        TinueResult::NoWin
+    }
+
+    fn attacker_win(&self) -> GameResult {
+        match self.attacker {
+            Color::White => GameResult::WhiteWin,
+            Color::Black => GameResult::BlackWin,
+        }
+    }
+
+    fn do_move(&mut self, mv: Move) -> ReverseMove {
+        self.nodes += 1;
+        self.position.do_move(mv)
+    }
+
+    /// Find a move that immediately wins the game for the side to move
+    fn winning_move(&mut self) -> Option<Move> {
+        let win = match self.position.side_to_move() {
+            Color::White => GameResult::WhiteWin,
+            Color::Black => GameResult::BlackWin,
+        };
+        let mut moves = vec![];
+        self.position.generate_moves(&mut moves);
+        for mv in moves {
+            let reverse_move = self.do_move(mv.clone());
+            let result = self.position.game_result();
+            self.position.reverse_move(reverse_move);
+            if result == Some(win) {
+                return Some(mv);
+            }
+        }
+        None
+    }
+
+    /// Whether the attacker could win immediately, if it were their turn
+    fn attacker_threatens_win(&mut self) -> bool {
+        self.position.null_move();
+        let threat = self.winning_move().is_some();
+        self.position.null_move();
+        threat
+    }
+
+    /// The attacker is to move, and tries to win within `plies` plies
+    fn attack(&mut self, plies: u16) -> Option<Vec<Move>> {
+        if self.nodes >= self.max_nodes {
+            self.out_of_nodes = true;
+            return None;
+        }
+        let hash = self.position.zobrist_hash();
+        if self.refuted.get(&hash).is_some_and(|&p| p >= plies) {
+            return None;
+        }
+
+        if let Some(mv) = self.winning_move() {
+            return Some(vec![mv]);
+        }
+
+        if plies >= 3 {
+            let mut moves = vec![];
+            self.position.generate_moves(&mut moves);
+            for mv in moves {
+                let reverse_move = self.do_move(mv.clone());
+                let line = if self.position.game_result().is_none() && self.attacker_threatens_win()
+                {
+                    self.defend(plies - 1)
+                } else {
+                    None
+                };
+                self.position.reverse_move(reverse_move);
+                if let Some(mut line) = line {
+                    line.insert(0, mv);
+                    return Some(line);
+                }
+                if self.out_of_nodes {
+                    return None;
+                }
+            }
+        }
+
+        self.refuted.insert(hash, plies);
+        None
+    }
+
+    /// The defender is to move, and every reply must lose within `plies` plies
+    fn defend(&mut self, plies: u16) -> Option<Vec<Move>> {
+        let mut moves = vec![];
+        self.position.generate_moves(&mut moves);
+        let mut longest_line: Option<Vec<Move>> = None;
+        for mv in moves {
+            let reverse_move = self.do_move(mv.clone());
+            let line = match self.position.game_result() {
+                Some(result) if result == self.attacker_win() => Some(vec![]),
+                Some(_) => None,
+                None => self.attack(plies - 1),
+            };
+            self.position.reverse_move(reverse_move);
+            match line {
+                Some(mut line) => {
+                    if longest_line
+                        .as_ref()
+                        .is_none_or(|longest| line.len() + 1 > longest.len())
+                    {
+                        line.insert(0, mv);
+                        longest_line = Some(line);
+                    }
+                }
+                None => return None,
+            }
+        }
+        longest_line
+    }
+}
@@ -19,15 +19,12 @@ pub fn openings_from_file<const S: usize>(path: &str) -> io::Result<Vec<Vec<Move
             let mv = position
                 .move_from_san(mv_string)
                 .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
-            let mut legal_moves = vec![];
-            position.generate_moves(&mut legal_moves);
-            if !legal_moves.contains(&mv) {
-                return Err(io::Error::new(
+            position.try_do_move(mv.clone()).map_err(|err| {
+                io::Error::new(
                     io::ErrorKind::InvalidData,
-                    format!("Illegal move {}", mv_string),
-                ));
-            }
-            position.do_move(mv.clone());
+                    format!("Illegal move {}: {}", mv_string, err),
+                )
+            })?;
             moves.push(mv);
         }
         openings.push(moves);
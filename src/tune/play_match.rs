@@ -4,16 +4,19 @@ use rand::seq::SliceRandom;
 use crate::position::Move;
 use crate::position::Position;
 use crate::position::Role;
-use crate::ptn::{Game, PtnMove};
+use crate::ptn::{Annotations, CommentTags, Game, PtnMove};
 use crate::search;
-use crate::search::{MctsSetting, Score};
+use crate::search::{MctsSetting, MonteCarloTree, Score};
 
-/// Play a single training game between two parameter sets
+/// Play a single training game between two parameter sets.
+/// With `reuse_trees`, each player continues searching its tree from the previous move.
+/// Games used as training data should not re-use trees, so that every policy target comes from a fresh search with root noise
 pub fn play_game<const S: usize>(
     white_settings: &MctsSetting<S>,
     black_settings: &MctsSetting<S>,
     opening: &[Move],
     temperature: f64,
+    reuse_trees: bool,
 ) -> (Game<Position<S>>, Vec<Vec<(Move, Score)>>) {
     const MCTS_NODES: u64 = 100_000;
 
@@ -24,6 +27,9 @@ pub fn play_game<const S: usize>(
         position.do_move(mv.clone());
     }
     let mut rng = rand::thread_rng();
+    // Each player keeps their own tree between moves, because they use different settings
+    let mut white_tree: Option<MonteCarloTree<S>> = None;
+    let mut black_tree: Option<MonteCarloTree<S>> = None;
 
     while position.game_result().is_none() {
         let num_plies = game_moves.len();
@@ -31,14 +37,21 @@ pub fn play_game<const S: usize>(
             break;
         }
 
-        let moves_scores = match position.side_to_move() {
-            Color::White => {
-                search::mcts_training::<S>(position.clone(), MCTS_NODES, white_settings.clone())
-            }
-            Color::Black => {
-                search::mcts_training::<S>(position.clone(), MCTS_NODES, black_settings.clone())
+        let (tree, settings) = match position.side_to_move() {
+            Color::White => (&mut white_tree, white_settings),
+            Color::Black => (&mut black_tree, black_settings),
+        };
+        let tree = match tree {
+            Some(tree) if reuse_trees => {
+                tree.advance(&game_moves[tree.position().half_moves_played()..]);
+                tree
             }
+            _ => tree.insert(MonteCarloTree::with_settings(
+                position.clone(),
+                settings.clone(),
+            )),
         };
+        let moves_scores = search::mcts_training_with_tree(tree, MCTS_NODES);
 
         // For white's first and second move, choose a random flatstone move
         // This reduces white's first move advantage, and prevents white from "cheesing"
@@ -68,8 +81,10 @@ pub fn play_game<const S: usize>(
                 .into_iter()
                 .map(|mv| PtnMove {
                     mv,
-                    annotations: vec![],
+                    annotations: Annotations::default(),
                     comment: String::new(),
+                    comment_tags: CommentTags::default(),
+                    variations: vec![],
                 })
                 .collect::<Vec<_>>(),
             game_result: position.game_result(),
@@ -33,7 +33,7 @@ pub fn tune<const S: usize>(variables: &mut [Variable], book_path: Option<&str>)
     };
     let mutex_variables = Mutex::new(variables);
 
-    (1..usize::max_value()).into_par_iter().for_each(|i| {
+    (1..usize::MAX).into_par_iter().for_each(|i| {
         let cloned_variables = (*mutex_variables.lock().unwrap()).to_vec();
         let mut rng = rand::rngs::StdRng::from_entropy();
 
@@ -95,7 +95,7 @@ fn tuning_iteration<R: rand::Rng, const S: usize>(
     let player2_settings = <MctsSetting<S>>::default()
         .add_search_params(player2_variables.iter().map(|(_, a)| *a).collect());
 
-    let (game, _) = play_game::<S>(&player1_settings, &player2_settings, opening, 0.2);
+    let (game, _) = play_game::<S>(&player1_settings, &player2_settings, opening, 0.2, true);
     match game.game_result {
         Some(GameResult::WhiteWin) => player1_variables.iter().map(|(a, _)| *a).collect(),
         Some(GameResult::BlackWin) => player2_variables.iter().map(|(a, _)| *a).collect(),
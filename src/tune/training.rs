@@ -1,6 +1,5 @@
 use std::convert::TryFrom;
 use std::io::Read;
-use std::io::Write;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time;
 use std::{error, fs, io};
@@ -12,6 +11,7 @@ use pgn_traits::PgnPosition;
 use rand::prelude::*;
 use rayon::prelude::*;
 
+use crate::dataset::{DatasetEntry, DatasetReader, DatasetWriter};
 use crate::evaluation::policy_eval::inverse_sigmoid;
 use crate::position::Move;
 use crate::position::Position;
@@ -41,66 +41,62 @@ pub fn train_from_scratch<const S: usize, const N: usize, const M: usize>(
         training_id,
         &initial_value_params,
         &initial_policy_params,
-        vec![],
-        vec![],
         0,
     )
 }
 
+/// Resume training after the last batch written to disk.
+/// Batches from before the binary dataset format are converted from their PTN and move score files.
 pub fn continue_training<const S: usize, const N: usize, const M: usize>(
     training_id: usize,
 ) -> Result<(), DynError> {
-    let mut games = vec![];
-    let mut move_scores = vec![];
     let mut batch_id = 0;
     loop {
-        match read_games_from_file::<S>(&format!(
-            "games{}_{}s_batch{}.ptn",
-            training_id, S, batch_id
-        )) {
-            Ok(mut game_batch) => {
-                games.append(&mut game_batch);
-            }
-            Err(error) => {
-                let io_error = error.downcast::<io::Error>()?;
-                if io_error.kind() == io::ErrorKind::NotFound {
-                    break;
-                } else {
-                    return Err(io_error);
+        let dataset_file_name = dataset_file_name::<S>(training_id, batch_id);
+        if fs::metadata(&dataset_file_name).is_err() {
+            match read_games_from_file::<S>(&format!(
+                "games{}_{}s_batch{}.ptn",
+                training_id, S, batch_id
+            )) {
+                Ok(games) => {
+                    let move_scores = read_move_scores_from_file::<S>(&format!(
+                        "move_scores{}_{}s_batch{}.ptn",
+                        training_id, S, batch_id
+                    ))?;
+                    assert_eq!(games.len(), move_scores.len());
+                    write_dataset_from_games(&dataset_file_name, &games, &move_scores)?;
+                }
+                Err(error) => {
+                    let io_error = error.downcast::<io::Error>()?;
+                    if io_error.kind() == io::ErrorKind::NotFound {
+                        break;
+                    } else {
+                        return Err(io_error);
+                    }
                 }
             }
         }
-        let mut move_scores_batch = read_move_scores_from_file::<S>(&format!(
-            "move_scores{}_{}s_batch{}.ptn",
-            training_id, S, batch_id
-        ))?;
-        move_scores.append(&mut move_scores_batch);
         batch_id += 1;
     }
 
-    assert_eq!(games.len(), move_scores.len());
-    println!(
-        "Resumed training with {} games and {} moves",
-        games.len(),
-        move_scores.iter().map(Vec::len).sum::<usize>()
-    );
+    println!("Resumed training after {} batches", batch_id);
 
     train_perpetually::<S, N, M>(
         training_id,
         &<[f32; N]>::try_from(<Position<S>>::value_params())?,
         &<[f32; M]>::try_from(<Position<S>>::policy_params())?,
-        games,
-        move_scores,
         batch_id,
     )
 }
 
+fn dataset_file_name<const S: usize>(training_id: usize, batch_id: usize) -> String {
+    format!("samples{}_{}s_batch{}.bin", training_id, S, batch_id)
+}
+
 pub fn train_perpetually<const S: usize, const N: usize, const M: usize>(
     training_id: usize,
     initial_value_params: &[f32; N],
     initial_policy_params: &[f32; M],
-    mut all_games: Vec<Game<Position<S>>>,
-    mut all_move_scores: Vec<MoveScoresForGame>,
     mut batch_id: usize,
 ) -> Result<(), DynError> {
     const BATCH_SIZE: usize = 1000;
@@ -138,9 +134,6 @@ pub fn train_perpetually<const S: usize, const N: usize, const M: usize>(
             .unzip();
         playing_time += playing_start_time.elapsed();
 
-        all_move_scores.extend_from_slice(&move_scores[..]);
-        all_games.extend_from_slice(&games[..]);
-
         let file_name = format!("games{}_{}s_batch{}.ptn", training_id, S, batch_id);
 
         let outfile = fs::OpenOptions::new()
@@ -155,32 +148,11 @@ pub fn train_perpetually<const S: usize, const N: usize, const M: usize>(
             game.game_to_ptn(&mut writer)?;
         }
 
-        let games_and_move_scores_outfile = fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(format!(
-                "move_scores{}_{}s_batch{}.ptn",
-                training_id, S, batch_id
-            ))
-            .unwrap();
-
-        let mut writer = io::BufWriter::new(games_and_move_scores_outfile);
-
-        for (game, move_scores) in games.iter().zip(move_scores) {
-            for (mv, move_scores) in game
-                .moves
-                .iter()
-                .map(|PtnMove { mv, .. }| mv)
-                .zip(move_scores)
-            {
-                write!(writer, "{}: ", mv.to_string::<S>())?;
-                for (mv, score) in move_scores {
-                    write!(writer, "{} {}, ", mv.to_string::<S>(), score)?;
-                }
-                writeln!(writer)?;
-            }
-            writeln!(writer)?;
-        }
+        write_dataset_from_games(
+            &dataset_file_name::<S>(training_id, batch_id),
+            &games,
+            &move_scores,
+        )?;
 
         let game_stats = GameStats::from_games(&games);
 
@@ -188,38 +160,24 @@ pub fn train_perpetually<const S: usize, const N: usize, const M: usize>(
         let losses = last_params_wins.into_inner();
         let draws = BATCH_SIZE as u64 - wins - losses;
 
-        println!("Finished playing batch of {} games. {} games played in total. {} white wins, {} draws, {} black wins, {} aborted. New vs old parameters was +{}-{}={}.",
-            games.len(), all_games.len(), game_stats.white_wins, game_stats.draws, game_stats.black_wins, game_stats.aborted, wins, losses, draws
+        println!("Finished playing batch {} of {} games. {} white wins, {} draws, {} black wins, {} aborted. New vs old parameters was +{}-{}={}.",
+            batch_id, games.len(), game_stats.white_wins, game_stats.draws, game_stats.black_wins, game_stats.aborted, wins, losses, draws
         );
 
-        // Only take the most recent half of the games, to avoid training on bad, old games
-        let max_training_games = all_games.len() / 2;
+        // Only take the most recent half of the batches, to avoid training on bad, old games
+        let batches_in_training = (batch_id + 1).div_ceil(2).clamp(1, BATCHES_FOR_TRAINING);
 
-        let games_in_training_batch = all_games
-            .iter()
-            .cloned()
-            .rev()
-            .take(usize::min(
-                max_training_games,
-                BATCH_SIZE * BATCHES_FOR_TRAINING,
-            ))
-            .collect::<Vec<_>>();
-
-        let move_scores_in_training_batch = all_move_scores
-            .iter()
-            .cloned()
-            .rev()
-            .take(usize::min(
-                max_training_games,
-                BATCH_SIZE * BATCHES_FOR_TRAINING,
-            ))
-            .collect::<Vec<_>>();
+        let mut entries_in_training_batch = vec![];
+        for training_batch_id in batch_id + 1 - batches_in_training..=batch_id {
+            entries_in_training_batch.append(&mut read_dataset_from_file::<S>(
+                &dataset_file_name::<S>(training_id, training_batch_id),
+            )?);
+        }
 
         let value_tuning_start_time = time::Instant::now();
 
         let (new_value_params, new_policy_params): ([f32; N], [f32; M]) = tune_value_and_policy(
-            &games_in_training_batch,
-            &move_scores_in_training_batch,
+            &mut entries_in_training_batch,
             &value_params,
             &policy_params,
         )?;
@@ -259,8 +217,8 @@ fn play_game_pair<const S: usize>(
         .add_value_params(last_value_params.to_vec())
         .add_policy_params(last_policy_params.to_vec())
         .add_dirichlet(0.2);
-    if i % 2 == 0 {
-        let game = play_game::<S>(&settings, &last_settings, &[], 1.0);
+    if i.is_multiple_of(2) {
+        let game = play_game::<S>(&settings, &last_settings, &[], 1.0, false);
         match game.0.game_result {
             Some(GameResult::WhiteWin) => {
                 current_params_wins.fetch_add(1, Ordering::Relaxed);
@@ -272,7 +230,7 @@ fn play_game_pair<const S: usize>(
         };
         game
     } else {
-        let game = play_game::<S>(&last_settings, &settings, &[], 1.0);
+        let game = play_game::<S>(&last_settings, &settings, &[], 1.0, false);
         match game.0.game_result {
             Some(GameResult::BlackWin) => {
                 current_params_wins.fetch_add(1, Ordering::Relaxed);
@@ -312,11 +270,23 @@ impl GameStats {
 pub fn read_games_from_file<const S: usize>(
     file_name: &str,
 ) -> Result<Vec<Game<Position<S>>>, DynError> {
-    let mut file = fs::File::open(file_name)?;
-    let mut input = String::new();
-    file.read_to_string(&mut input)?;
-    let games = ptn_parser::parse_ptn(&input)?;
-    println!("Read {} games from PTN", games.len());
+    let file = io::BufReader::new(fs::File::open(file_name)?);
+    let mut games = vec![];
+    let mut skipped_games = 0;
+    for game in ptn_parser::PtnReader::new(file) {
+        match game {
+            Ok(game) => games.push(game),
+            Err(err) => {
+                eprintln!("Skipping game in {}: {}", file_name, err);
+                skipped_games += 1;
+            }
+        }
+    }
+    println!(
+        "Read {} games from PTN, skipped {} games",
+        games.len(),
+        skipped_games
+    );
     Ok(games)
 }
 
@@ -360,31 +330,23 @@ pub fn tune_value_from_file<const S: usize, const N: usize>(
     Ok(tuned_parameters)
 }
 
+/// Tune value and policy parameters from dataset entries. The entries are shuffled in place.
 pub fn tune_value_and_policy<const S: usize, const N: usize, const M: usize>(
-    games: &[Game<Position<S>>],
-    move_scoress: &[MoveScoresForGame],
+    entries: &mut [DatasetEntry<S>],
     initial_value_params: &[f32; N],
     initial_policy_params: &[f32; M],
 ) -> Result<([f32; N], [f32; M]), DynError> {
-    let mut games_and_move_scoress: Vec<(&Game<Position<S>>, &MoveScoresForGame)> =
-        games.iter().zip(move_scoress).collect();
-
     let mut rng = rand::rngs::StdRng::from_seed([0; 32]);
 
-    games_and_move_scoress.shuffle(&mut rng);
-
-    let (games, move_scoress): (Vec<_>, Vec<_>) = games_and_move_scoress.into_iter().unzip();
-
-    let (positions, results) =
-        positions_and_results_from_games(games.iter().cloned().cloned().collect());
+    entries.shuffle(&mut rng);
 
-    let value_training_samples = positions
+    let value_training_samples = entries
         .iter()
-        .zip(results)
-        .map(|(position, game_result)| {
+        .filter(|entry| entry.position.game_result().is_none())
+        .map(|entry| {
             let mut features = [0.0; N];
-            position.static_eval_features(&mut features);
-            let result = match game_result {
+            entry.position.static_eval_features(&mut features);
+            let result = match entry.game_result.unwrap_or(GameResult::Draw) {
                 GameResult::WhiteWin => 1.0,
                 GameResult::Draw => 0.5,
                 GameResult::BlackWin => 0.0,
@@ -397,42 +359,37 @@ pub fn tune_value_and_policy<const S: usize, const N: usize, const M: usize>(
         })
         .collect::<Vec<_>>();
 
-    let number_of_feature_sets = move_scoress.iter().flat_map(|a| *a).flatten().count();
+    let number_of_feature_sets = entries.iter().map(|entry| entry.move_scores.len()).sum();
 
     let mut policy_training_samples = Vec::with_capacity(number_of_feature_sets);
 
-    for (game, move_scores) in games.iter().zip(move_scoress) {
-        let mut position = game.start_position.clone();
-
-        for (mv, move_scores) in game
-            .moves
-            .iter()
-            .map(|PtnMove { mv, .. }| mv)
-            .zip(move_scores)
-        {
-            let group_data = position.group_data();
-
-            let mut feature_sets = vec![[0.0; M]; move_scores.len()];
-            let mut policy_feature_sets: Vec<PolicyFeatures> = feature_sets
-                .iter_mut()
-                .map(|feature_set| PolicyFeatures::new::<S>(feature_set))
-                .collect();
-            let moves: Vec<Move> = move_scores.iter().map(|(mv, _score)| mv.clone()).collect();
-
-            position.features_for_moves(&mut policy_feature_sets, &moves, &group_data);
-
-            for ((_, result), features) in move_scores.iter().zip(feature_sets) {
-                let offset = inverse_sigmoid(1.0 / move_scores.len().max(2) as f32);
-
-                policy_training_samples.push({
-                    TrainingSample {
-                        features,
-                        offset,
-                        result: *result,
-                    }
-                });
-            }
-            position.do_move(mv.clone());
+    for DatasetEntry {
+        position,
+        move_scores,
+        ..
+    } in entries.iter()
+    {
+        let group_data = position.group_data();
+
+        let mut feature_sets = vec![[0.0; M]; move_scores.len()];
+        let mut policy_feature_sets: Vec<PolicyFeatures> = feature_sets
+            .iter_mut()
+            .map(|feature_set| PolicyFeatures::new::<S>(feature_set))
+            .collect();
+        let moves: Vec<Move> = move_scores.iter().map(|(mv, _score)| mv.clone()).collect();
+
+        position.features_for_moves(&mut policy_feature_sets, &moves, &group_data);
+
+        for ((_, result), features) in move_scores.iter().zip(feature_sets) {
+            let offset = inverse_sigmoid(1.0 / move_scores.len().max(2) as f32);
+
+            policy_training_samples.push({
+                TrainingSample {
+                    features,
+                    offset,
+                    result: *result,
+                }
+            });
         }
     }
 
@@ -463,8 +420,7 @@ pub fn tune_value_and_policy_from_file<const S: usize, const N: usize, const M:
     let initial_policy_params: [f32; M] = array_from_fn(|| rng.gen_range(-0.01..0.01));
 
     tune_value_and_policy(
-        &games,
-        &move_scoress,
+        &mut dataset_entries_from_games(&games, &move_scoress),
         &initial_value_params,
         &initial_policy_params,
     )
@@ -557,10 +513,7 @@ pub fn read_move_scores_from_file<const S: usize>(
         }
         move_scoress.last_mut().unwrap().push(scores_for_this_move);
     }
-    move_scoress = move_scoress
-        .into_iter()
-        .filter(|move_scores| !move_scores.is_empty())
-        .collect();
+    move_scoress.retain(|move_scores| !move_scores.is_empty());
 
     println!(
         "Read {} move scores from {} games",
@@ -571,6 +524,60 @@ pub fn read_move_scores_from_file<const S: usize>(
     Ok(move_scoress)
 }
 
+/// Every position from the games, along with its move scores and the game's result
+pub fn dataset_entries_from_games<const S: usize>(
+    games: &[Game<Position<S>>],
+    move_scoress: &[MoveScoresForGame],
+) -> Vec<DatasetEntry<S>> {
+    let mut entries = vec![];
+    for (game, move_scores) in games.iter().zip(move_scoress) {
+        let mut position = game.start_position.clone();
+        for (PtnMove { mv, .. }, move_scores) in game.moves.iter().zip(move_scores) {
+            entries.push(DatasetEntry {
+                position: position.clone(),
+                move_scores: move_scores.clone(),
+                game_result: game.game_result,
+            });
+            position.do_move(mv.clone());
+        }
+    }
+    entries
+}
+
+/// Store every position from the games in the binary dataset format, along with its move scores.
+/// Returns an error without creating the file if the games were not all played with the same rules.
+pub fn write_dataset_from_games<const S: usize>(
+    file_name: &str,
+    games: &[Game<Position<S>>],
+    move_scoress: &[MoveScoresForGame],
+) -> Result<(), DynError> {
+    let rules = games
+        .first()
+        .map(|game| *game.start_position.rules())
+        .unwrap_or_default();
+    if games
+        .iter()
+        .any(|game| *game.start_position.rules() != rules)
+    {
+        return Err("Cannot write games with different rules to the same dataset".into());
+    }
+    let outfile = io::BufWriter::new(fs::File::create(file_name)?);
+    let mut writer = DatasetWriter::new(outfile, rules)?;
+    for entry in dataset_entries_from_games(games, move_scoress) {
+        writer.write_entry(&entry)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn read_dataset_from_file<const S: usize>(
+    file_name: &str,
+) -> Result<Vec<DatasetEntry<S>>, DynError> {
+    let file = io::BufReader::new(fs::File::open(file_name)?);
+    let entries = DatasetReader::<_, S>::new(file)?.collect::<Result<Vec<_>, _>>()?;
+    Ok(entries)
+}
+
 pub fn positions_and_results_from_games<const S: usize>(
     games: Vec<Game<Position<S>>>,
 ) -> (Vec<Position<S>>, Vec<GameResult>) {